@@ -0,0 +1,1589 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use ratatui::{layout::Rect, widgets::ListState};
+
+use crate::{
+    backup::{BackupView, Entry, Store},
+    config::Config,
+    fileops::{self, FileOp},
+    fuzzy::Filter,
+    git::{Diff, DiffBase, Git, LogView, Repo},
+    grep::Grep,
+    journal::{self, Journal, JournalView, Step},
+    keymap::{Keymap, Mode},
+    links::{self, LinkAction, LinkView, Operation},
+    metadata::{DirSizes, Names},
+    preview::PreviewCache,
+    scan::{self, Scanner},
+    state::{State, MAX_SPLIT, MIN_SPLIT},
+    theme::Theme,
+    tree::{Node, Tree},
+    watch::Watcher,
+};
+
+// Commit message being typed, and the repository it goes to
+#[derive(Debug)]
+pub struct CommitPrompt {
+    pub repo: Repo,
+    pub message: String,
+}
+
+// What the name being typed is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameKind {
+    NewFile,
+    NewDir,
+    Rename,
+    Duplicate,
+}
+
+// Name being typed for a new, renamed or copied entry, relative to `dir`
+#[derive(Debug)]
+pub struct NamePrompt {
+    pub kind: NameKind,
+    // The selected entry when the prompt opened
+    pub source: PathBuf,
+    pub dir: PathBuf,
+    pub input: String,
+}
+
+// Which pane receives navigation keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Focus {
+    List,
+    Preview,
+}
+
+// Changes waiting for the user to accept a dry-run summary
+#[derive(Debug)]
+pub enum Pending {
+    Links(LinkAction, Vec<Operation>),
+    // Snapshot entries to put back
+    Restore(Vec<Entry>),
+    File(FileOp),
+    // The last record of the journal, or the last undone one
+    Undo,
+    Redo,
+}
+
+// A dialog listing what is about to happen, answered with y or n
+#[derive(Debug)]
+pub struct Confirm {
+    pub title: String,
+    pub lines: Vec<String>,
+    pub pending: Pending,
+}
+
+#[derive(Debug)]
+pub struct App {
+    pub dotfiles: Tree,
+    // Every entry under the scan roots, for searching
+    pub index: Vec<PathBuf>,
+    // Background scan still adding to the index
+    pub scan: Option<Scanner>,
+    // Change notifications for the scan roots, if the platform has them
+    watcher: Option<Watcher>,
+    // Paths changed on disk since startup, and their directories, with the
+    // time of the last change
+    pub changed: HashMap<PathBuf, Instant>,
+    // Active "/" search, shown in place of the tree
+    pub filter: Option<Filter>,
+    // Active content search, shown in place of the tree
+    pub grep: Option<Grep>,
+    // Managed dotfiles repo from the config, and its view when open
+    pub repo: Option<PathBuf>,
+    pub links: Option<LinkView>,
+    // Snapshots view, shown in place of the tree
+    pub backups: Option<BackupView>,
+    // Undo journal, shown in place of the tree
+    pub journal: Option<JournalView>,
+    pub confirm: Option<Confirm>,
+    // Repositories holding the scanned files and their last `git status`
+    pub git: Git,
+    // Whether to say how the running git refresh went, as the user asked for it
+    report_git: bool,
+    // Diff shown in place of the file preview, and the last one computed,
+    // kept until the selection or the base changes
+    pub diff_base: Option<DiffBase>,
+    diff: Option<(PathBuf, DiffBase, io::Result<Diff>)>,
+    // History of the selected path, shown in place of the tree
+    pub log: Option<LogView>,
+    pub commit: Option<CommitPrompt>,
+    pub name_prompt: Option<NamePrompt>,
+    // Owner names and background directory sizes for the metadata panel
+    pub names: Names,
+    pub dir_sizes: DirSizes,
+    pub list_state: ListState,
+    pub theme: Theme,
+    pub keymap: Keymap,
+    // Scroll offset of the key binding overlay while it is shown
+    pub help: Option<usize>,
+    pub focus: Focus,
+    // Highlighted file contents, so drawing does not read the disk
+    pub preview: PreviewCache,
+    // First preview line shown at the top of the pane
+    pub preview_scroll: usize,
+    // 1-based preview line to highlight, e.g. a search match
+    pub preview_highlight: Option<usize>,
+    // Sizes from the last frame, used for paging and clamping
+    pub preview_lines: usize,
+    pub preview_height: usize,
+    pub list_height: usize,
+    // Where the last frame drew the panes, for the mouse
+    pub body_area: Rect,
+    pub list_area: Rect,
+    pub preview_area: Rect,
+    // Width of the list pane in percent, changed by dragging the divider
+    pub split: u16,
+    dragging: bool,
+    // Message shown in the status line until the next key press
+    pub status: Option<String>,
+}
+
+impl App {
+    pub fn new() -> io::Result<Self> {
+        let config = Config::load()?;
+        let dotfiles = Tree::new(&config.roots);
+        let scan = Scanner::start(config.roots.clone());
+        let (watcher, watch_error) = match Watcher::start(config.roots.clone()) {
+            Ok(watcher) => (Some(watcher), None),
+            Err(e) => (None, Some(format!("Not watching for changes: {}", e))),
+        };
+
+        let git = Git::for_config(&config)?;
+
+        let mut list_state = ListState::default();
+        list_state.select(Some(0));
+
+        Ok(Self {
+            dotfiles,
+            index: Vec::new(),
+            scan: Some(scan),
+            watcher,
+            changed: HashMap::new(),
+            filter: None,
+            grep: None,
+            repo: config.repo,
+            links: None,
+            backups: None,
+            journal: None,
+            confirm: None,
+            status: watch_error,
+            git,
+            report_git: false,
+            diff_base: None,
+            diff: None,
+            log: None,
+            commit: None,
+            name_prompt: None,
+            names: Names::load(),
+            dir_sizes: DirSizes::default(),
+            list_state,
+            theme: config.theme,
+            keymap: config.keymap,
+            help: None,
+            focus: Focus::List,
+            preview: PreviewCache::default(),
+            preview_scroll: 0,
+            preview_highlight: None,
+            preview_lines: 0,
+            preview_height: 0,
+            list_height: 0,
+            body_area: Rect::default(),
+            list_area: Rect::default(),
+            preview_area: Rect::default(),
+            split: State::load().split,
+            dragging: false,
+        })
+    }
+
+    // Which key bindings apply, after the global ones
+    pub fn mode(&self) -> Mode {
+        if self.log.is_some() {
+            Mode::Log
+        } else if self.backups.is_some() {
+            Mode::Backups
+        } else if self.journal.is_some() {
+            Mode::Journal
+        } else if self.links.is_some() {
+            Mode::Links
+        } else if self.grep.is_some() {
+            Mode::Grep
+        } else {
+            Mode::Tree
+        }
+    }
+
+    pub fn scroll_help(&mut self, delta: isize) {
+        if let Some(scroll) = &mut self.help {
+            // Clamped when drawn
+            *scroll = scroll.saturating_add_signed(delta);
+        }
+    }
+
+    pub fn selected_node(&self) -> Option<&Node> {
+        self.dotfiles.node(self.list_state.selected()?)
+    }
+
+    // Path under the cursor, in the search results or the tree
+    pub fn selected_path(&self) -> Option<&Path> {
+        if let Some(log) = &self.log {
+            return Some(log.path.as_path());
+        }
+        if let Some(backups) = &self.backups {
+            return backups.selected_entry().map(|entry| entry.path.as_path());
+        }
+        if let Some(view) = &self.journal {
+            return view
+                .selected()
+                .and_then(|(record, _)| Some(record.steps.first()?.path()));
+        }
+        if let Some(links) = &self.links {
+            return links.selected().map(|entry| entry.source.as_path());
+        }
+        if let Some(grep) = &self.grep {
+            return grep.selected().map(|result| result.path.as_path());
+        }
+        match &self.filter {
+            Some(filter) => filter.selected().map(|result| result.path.as_path()),
+            None => self.selected_node().map(|node| node.path.as_path()),
+        }
+    }
+
+    // Take in what the background scan found since the last frame
+    pub fn poll_scan(&mut self) {
+        let Some(scan) = &mut self.scan else {
+            return;
+        };
+        let start = self.index.len();
+        let (found, running) = scan.poll(&mut self.index);
+        if found > 0 {
+            if let Some(filter) = &mut self.filter {
+                filter.extend(&self.index[start..]);
+            }
+        }
+        if !running {
+            self.scan = None;
+        }
+    }
+
+    // Bring the tree, index and preview up to date with changes made by other programs
+    pub fn poll_watch(&mut self) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        let paths = watcher.poll();
+        if let Some(e) = watcher.error.take() {
+            self.status = Some(format!("Watching for changes failed: {}", e));
+        }
+        if paths.is_empty() {
+            return;
+        }
+
+        let now = Instant::now();
+        for path in &paths {
+            for changed in path.ancestors().take_while(|ancestor| {
+                watcher
+                    .roots()
+                    .iter()
+                    .any(|root| ancestor.starts_with(&root.path))
+            }) {
+                self.changed.insert(changed.to_path_buf(), now);
+            }
+        }
+        let paths: Vec<&Path> = paths.iter().map(PathBuf::as_path).collect();
+        self.paths_changed(&paths);
+        // Changes outside the repos, or to files git hides, leave the status as it is
+        if paths.iter().any(|path| self.git.affected_by(path)) {
+            self.git_changed();
+        }
+    }
+
+    // Update the index, previews and tree for entries that appeared, went
+    // away or changed
+    fn paths_changed(&mut self, paths: &[&Path]) {
+        let mut dirs: Vec<&Path> = Vec::new();
+        let mut added = Vec::new();
+        for &path in paths {
+            self.preview.invalidate(path);
+            if let Some(dir) = path.parent().filter(|dir| !dirs.contains(dir)) {
+                dirs.push(dir);
+            }
+            // A running scan will still come across it
+            if self.scan.is_some() {
+                continue;
+            }
+            if fs::symlink_metadata(path).is_err() {
+                self.index.retain(|entry| !entry.starts_with(path));
+            } else if !self.index.iter().any(|entry| entry == path) {
+                self.index.push(path.to_path_buf());
+                added.push(path.to_path_buf());
+            }
+        }
+        if let Some(filter) = &mut self.filter {
+            filter.extend(&added);
+        }
+
+        self.reload_dirs(&dirs);
+    }
+
+    pub fn cancel_scan(&mut self) {
+        let Some(scan) = self.scan.take() else {
+            return;
+        };
+        scan.cancel();
+        self.status = Some(format!(
+            "Scan cancelled after {}; searches only cover those",
+            count(self.index.len(), "path")
+        ));
+    }
+
+    // Whether the index is still incomplete, telling the user so
+    fn still_scanning(&mut self) -> bool {
+        if self.scan.is_some() {
+            self.status = Some("Still scanning, try again when it is done".into());
+        }
+        self.scan.is_some()
+    }
+
+    pub fn start_filter(&mut self) {
+        self.filter = Some(Filter::new(&self.index));
+        self.focus = Focus::List;
+    }
+
+    pub fn cancel_filter(&mut self) {
+        self.filter = None;
+        self.preview_scroll = 0;
+    }
+
+    // Leave the search and select the chosen entry in the tree
+    pub fn accept_filter(&mut self) {
+        let Some(filter) = self.filter.take() else {
+            return;
+        };
+        let Some(path) = filter.selected().map(|result| result.path.clone()) else {
+            return;
+        };
+        match self.dotfiles.reveal(&path) {
+            Some(row) => self.select(row),
+            None => self.status = Some(format!("{} is not in the tree", scan::display_path(&path))),
+        }
+        self.preview_scroll = 0;
+    }
+
+    pub fn start_grep(&mut self) {
+        self.grep = Some(Grep::new());
+        self.focus = Focus::List;
+    }
+
+    pub fn cancel_grep(&mut self) {
+        self.grep = None;
+        self.preview_scroll = 0;
+        self.preview_highlight = None;
+    }
+
+    pub fn run_grep(&mut self) {
+        if let Some(grep) = &mut self.grep {
+            grep.run(&self.index, self.scan.is_some());
+        }
+        self.show_grep_match();
+    }
+
+    pub fn move_grep_selection(&mut self, delta: isize) {
+        if let Some(grep) = &mut self.grep {
+            grep.move_selection(delta);
+        }
+        self.show_grep_match();
+    }
+
+    // Leave the search, selecting the matched file in the tree at the matched line
+    pub fn accept_grep(&mut self) {
+        let Some(grep) = self.grep.take() else {
+            return;
+        };
+        let Some((path, line)) = grep
+            .selected()
+            .map(|found| (found.path.clone(), found.line))
+        else {
+            return;
+        };
+        match self.dotfiles.reveal(&path) {
+            Some(row) => self.select(row),
+            None => self.status = Some(format!("{} is not in the tree", scan::display_path(&path))),
+        }
+        self.jump_to_line(line);
+    }
+
+    // Scroll the preview to the selected search match
+    fn show_grep_match(&mut self) {
+        match self.grep.as_ref().and_then(|grep| grep.selected()) {
+            Some(found) => self.jump_to_line(found.line),
+            None => {
+                self.preview_scroll = 0;
+                self.preview_highlight = None;
+            }
+        }
+    }
+
+    // Highlight a 1-based line and scroll it to the middle of the preview
+    pub fn jump_to_line(&mut self, line: usize) {
+        self.preview_highlight = Some(line);
+        self.preview_scroll = line.saturating_sub(1 + self.preview_height / 2);
+    }
+
+    pub fn open_links(&mut self) {
+        let Some(repo) = self.repo.clone() else {
+            self.status = Some("No managed repo: set repo = \"~/dotfiles\" in config.toml".into());
+            return;
+        };
+        match LinkView::new(repo) {
+            Ok(view) => {
+                self.links = Some(view);
+                self.focus = Focus::List;
+                self.preview_scroll = 0;
+            }
+            Err(e) => self.status = Some(e.to_string()),
+        }
+    }
+
+    pub fn close_links(&mut self) {
+        self.links = None;
+        self.preview_scroll = 0;
+    }
+
+    pub fn move_links_selection(&mut self, delta: isize) {
+        if let Some(links) = &mut self.links {
+            links.move_selection(delta);
+            self.preview_scroll = 0;
+        }
+    }
+
+    // Work out what `action` would do to the selected entry (or all of them)
+    // and ask for confirmation
+    pub fn plan_links(&mut self, action: LinkAction, all: bool) {
+        let Some(view) = &self.links else {
+            return;
+        };
+        let entries: Vec<_> = if all {
+            view.entries.iter().collect()
+        } else {
+            view.selected().into_iter().collect()
+        };
+        let (operations, skipped) = links::plan(action, &entries);
+
+        if operations.is_empty() {
+            self.status = Some(match skipped.first() {
+                Some(reason) => format!("Nothing to {}: {}", action.name(), reason),
+                None => format!("Nothing to {}", action.name()),
+            });
+            return;
+        }
+
+        let mut lines: Vec<String> = operations.iter().map(|op| op.to_string()).collect();
+        if !skipped.is_empty() {
+            lines.push(String::new());
+            lines.extend(skipped.iter().map(|reason| format!("skip    {}", reason)));
+        }
+        self.confirm = Some(Confirm {
+            title: format!("Dry run: {} ({} changes)", action.name(), operations.len()),
+            lines,
+            pending: Pending::Links(action, operations),
+        });
+    }
+
+    pub fn cancel_confirm(&mut self) {
+        self.confirm = None;
+        self.status = Some("Cancelled, nothing was changed".to_string());
+    }
+
+    pub fn accept_confirm(&mut self) {
+        let Some(confirm) = self.confirm.take() else {
+            return;
+        };
+        match confirm.pending {
+            Pending::Links(action, operations) => self.apply_links(action, &operations),
+            Pending::Restore(entries) => self.restore(&entries),
+            Pending::File(op) => self.apply_file_op(&op),
+            Pending::Undo => self.undo(),
+            Pending::Redo => self.redo(),
+        }
+    }
+
+    // Back up the selected file, or everything found below the selected directory
+    pub fn backup_selected(&mut self) {
+        let Some(path) = self.selected_path().map(|path| path.to_path_buf()) else {
+            return;
+        };
+        let paths: Vec<PathBuf> = if path.is_dir() {
+            if self.still_scanning() {
+                return;
+            }
+            self.index
+                .iter()
+                .filter(|entry| entry.starts_with(&path))
+                .cloned()
+                .collect()
+        } else {
+            vec![path]
+        };
+        self.backup(&paths);
+    }
+
+    // Back up every file under the scan roots
+    pub fn backup_all(&mut self) {
+        if self.still_scanning() {
+            return;
+        }
+        let paths = self.index.clone();
+        self.backup(&paths);
+    }
+
+    fn backup(&mut self, paths: &[PathBuf]) {
+        let result = Store::open().and_then(|store| store.snapshot(paths, "manual"));
+        self.status = Some(match result {
+            Ok((snapshot, skipped)) if skipped.is_empty() => format!(
+                "Backed up {} to snapshot {}",
+                count(snapshot.entries.len(), "file"),
+                snapshot.id
+            ),
+            Ok((snapshot, skipped)) => format!(
+                "Backed up {} to snapshot {}, skipped {}: {}",
+                count(snapshot.entries.len(), "file"),
+                snapshot.id,
+                skipped.len(),
+                skipped[0]
+            ),
+            Err(e) => format!("Backup failed: {}", e),
+        });
+        if let Some(view) = &mut self.backups {
+            if let Err(e) = view.refresh() {
+                self.status = Some(e.to_string());
+            }
+        }
+    }
+
+    pub fn open_backups(&mut self) {
+        match Store::open().and_then(BackupView::new) {
+            Ok(view) if view.snapshots.is_empty() => {
+                self.status =
+                    Some("No snapshots yet: b backs up the selection, B everything".into())
+            }
+            Ok(view) => {
+                self.backups = Some(view);
+                self.focus = Focus::List;
+                self.preview_scroll = 0;
+                self.preview_highlight = None;
+            }
+            Err(e) => self.status = Some(e.to_string()),
+        }
+    }
+
+    // Leave the files of a snapshot, or the view itself
+    pub fn close_backups(&mut self) {
+        if let Some(view) = &mut self.backups {
+            if view.close_snapshot() {
+                self.preview_scroll = 0;
+                self.preview_highlight = None;
+                return;
+            }
+        }
+        self.backups = None;
+        self.preview_scroll = 0;
+        self.preview_highlight = None;
+    }
+
+    pub fn move_backups_selection(&mut self, delta: isize) {
+        if let Some(view) = &mut self.backups {
+            view.move_selection(delta);
+            self.preview_scroll = 0;
+            self.preview_highlight = None;
+        }
+    }
+
+    pub fn open_snapshot(&mut self) {
+        if let Some(view) = &mut self.backups {
+            view.open_selected();
+            self.preview_scroll = 0;
+            self.preview_highlight = None;
+        }
+    }
+
+    // Ask before restoring the selected file, or the whole selected snapshot
+    pub fn plan_restore(&mut self) {
+        let Some(view) = &self.backups else {
+            return;
+        };
+        let Some(snapshot) = view.selected_snapshot() else {
+            return;
+        };
+        let entries: Vec<Entry> = match view.selected_entry() {
+            Some(entry) => vec![entry.clone()],
+            None if view.open.is_some() => return,
+            None => snapshot.entries.clone(),
+        };
+        let mut lines: Vec<String> = entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "restore {} ({})",
+                    scan::display_path(&entry.path),
+                    view.store.live_state(entry).label()
+                )
+            })
+            .collect();
+        lines.push(String::new());
+        lines.push("The current files are backed up first.".to_string());
+
+        self.confirm = Some(Confirm {
+            title: format!("Restore from snapshot {}?", snapshot.id),
+            lines,
+            pending: Pending::Restore(entries),
+        });
+    }
+
+    fn restore(&mut self, entries: &[Entry]) {
+        let store = match Store::open() {
+            Ok(store) => store,
+            Err(e) => {
+                self.status = Some(format!("Restore failed: {}", e));
+                return;
+            }
+        };
+        // Keep what is about to be overwritten
+        let live: Vec<PathBuf> = entries
+            .iter()
+            .map(|entry| entry.path.clone())
+            .filter(|path| path.symlink_metadata().is_ok())
+            .collect();
+        let before = match store.snapshot(&live, "before restore") {
+            Ok((snapshot, _)) => Some(snapshot),
+            Err(_) if live.is_empty() => None,
+            Err(e) => {
+                self.status = Some(format!(
+                    "Not restoring, backing up the current files failed: {}",
+                    e
+                ));
+                return;
+            }
+        };
+
+        let mut restored = 0;
+        let mut failed = None;
+        let mut steps = Vec::new();
+        for entry in entries {
+            match store.restore(entry) {
+                Ok(()) => {
+                    restored += 1;
+                    let previous = before
+                        .iter()
+                        .flat_map(|snapshot| &snapshot.entries)
+                        .find(|previous| previous.path == entry.path);
+                    steps.push(Step::Restore {
+                        entry: entry.clone(),
+                        previous: previous.cloned(),
+                    });
+                }
+                Err(e) => {
+                    failed = Some(format!("{}: {}", scan::display_path(&entry.path), e));
+                    break;
+                }
+            }
+        }
+        self.status = Some(match failed {
+            None => match before {
+                Some(before) => format!(
+                    "Restored {}, previous versions in snapshot {}",
+                    count(restored, "file"),
+                    before.id
+                ),
+                None => format!("Restored {}", count(restored, "file")),
+            },
+            Some(e) => format!(
+                "Restored {} of {} files, then failed: {}",
+                restored,
+                entries.len(),
+                e
+            ),
+        });
+        self.journal_record("restore", steps);
+
+        if let Some(view) = &mut self.backups {
+            if let Err(e) = view.refresh() {
+                self.status = Some(e.to_string());
+            }
+        }
+        let mut dirs: Vec<&Path> = entries
+            .iter()
+            .filter_map(|entry| entry.path.parent())
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        self.reload_dirs(&dirs);
+        self.git_changed();
+    }
+
+    fn apply_links(&mut self, action: LinkAction, operations: &[Operation]) {
+        // Adopting replaces the repo's copies, keep them for undoing
+        let replaced: Vec<PathBuf> = operations
+            .iter()
+            .filter_map(|op| match op {
+                Operation::Move { to, .. } if to.symlink_metadata().is_ok() => Some(to.clone()),
+                _ => None,
+            })
+            .collect();
+        let backed_up = if replaced.is_empty() {
+            Vec::new()
+        } else {
+            match Store::open().and_then(|store| store.snapshot(&replaced, "before adopt")) {
+                Ok((snapshot, _)) => snapshot.entries,
+                Err(e) => {
+                    self.status = Some(format!(
+                        "Not adopting, backing up the repo files failed: {}",
+                        e
+                    ));
+                    return;
+                }
+            }
+        };
+        let steps: Vec<Step> = operations
+            .iter()
+            .map(|op| Step::for_link(op, &backed_up))
+            .collect();
+
+        let done = match links::apply(operations) {
+            Ok(done) => {
+                self.status = Some(format!("Applied {} changes", done));
+                done
+            }
+            Err((done, e)) => {
+                self.status = Some(format!(
+                    "Applied {} of {} changes, then failed: {}",
+                    done,
+                    operations.len(),
+                    e
+                ));
+                done
+            }
+        };
+        self.journal_record(action.name(), steps.into_iter().take(done).collect());
+
+        if let Some(view) = &mut self.links {
+            if let Err(e) = view.refresh() {
+                self.status = Some(e.to_string());
+            }
+        }
+        let mut dirs: Vec<&Path> = operations
+            .iter()
+            .filter_map(|op| match op {
+                Operation::CreateDir(path) | Operation::RemoveLink(path) => path.parent(),
+                Operation::Symlink { target, .. } => target.parent(),
+                Operation::Move { from, to } => from.parent().or(to.parent()),
+            })
+            .collect();
+        dirs.sort();
+        dirs.dedup();
+        self.reload_dirs(&dirs);
+        self.git_changed();
+    }
+
+    // Take in the git status found in the background, and check the shown
+    // entries against the ignore rules
+    pub fn poll_git(&mut self) {
+        let error = self.git.error.clone();
+        if self.git.poll() {
+            if self.report_git {
+                self.report_git = false;
+                self.status = Some(match &self.git.error {
+                    Some(e) => e.clone(),
+                    None => "Git status refreshed".to_string(),
+                });
+            } else if self.git.error != error {
+                self.status = self.git.error.clone().or(self.status.take());
+            }
+        }
+        let shown: Vec<&Path> = self
+            .dotfiles
+            .iter()
+            .map(|(_, node)| node.path.as_path())
+            .collect();
+        self.git.check_ignored(&shown);
+    }
+
+    // Run `git status` again, e.g. after committing from a shell
+    pub fn refresh_git(&mut self) {
+        if self.git.is_empty() {
+            self.status = Some("No git repository found".to_string());
+            return;
+        }
+        self.git_changed();
+        self.report_git = true;
+        self.status = Some("Refreshing git status".to_string());
+    }
+
+    // Forget everything derived from the last `git status`
+    fn git_changed(&mut self) {
+        self.git.refresh();
+        self.diff = None;
+    }
+
+    pub fn stage_selected(&mut self) {
+        self.run_git_on_selected("Staged", |repo, path| repo.stage(path));
+    }
+
+    pub fn unstage_selected(&mut self) {
+        self.run_git_on_selected("Unstaged", |repo, path| repo.unstage(path));
+    }
+
+    fn run_git_on_selected(
+        &mut self,
+        done: &str,
+        command: impl Fn(&Repo, &Path) -> io::Result<()>,
+    ) {
+        let Some(path) = self.selected_path().map(|path| path.to_path_buf()) else {
+            return;
+        };
+        let Some(repo) = self.git.repo_for(&path) else {
+            self.status = Some("Not in a git repository".to_string());
+            return;
+        };
+        self.status = Some(match command(repo, &path) {
+            Ok(()) => format!("{} {}", done, scan::display_path(&path)),
+            Err(e) => format!("git: {}", e),
+        });
+        self.git_changed();
+    }
+
+    // Ask for a message to commit what is staged in the selected path's repo
+    pub fn start_commit(&mut self) {
+        let Some(path) = self.selected_path() else {
+            return;
+        };
+        let Some(repo) = self.git.repo_for(path).cloned() else {
+            self.status = Some("Not in a git repository".to_string());
+            return;
+        };
+        if !self
+            .git
+            .status(&repo.work_tree)
+            .is_some_and(|status| status.staged)
+        {
+            self.status = Some(format!(
+                "Nothing staged in {}",
+                scan::display_path(&repo.work_tree)
+            ));
+            return;
+        }
+        self.commit = Some(CommitPrompt {
+            repo,
+            message: String::new(),
+        });
+    }
+
+    pub fn cancel_commit(&mut self) {
+        self.commit = None;
+    }
+
+    pub fn accept_commit(&mut self) {
+        let Some(prompt) = self.commit.take() else {
+            return;
+        };
+        let message = prompt.message.trim();
+        if message.is_empty() {
+            self.status = Some("Empty commit message, nothing was committed".to_string());
+            return;
+        }
+        self.status = Some(match prompt.repo.commit(message) {
+            Ok(hash) => format!("Committed {} {}", hash, message),
+            Err(e) => format!("git commit: {}", e),
+        });
+        self.git_changed();
+    }
+
+    // Ask for the name of a new entry in the selected directory (or next to
+    // the selected file), or a new name or copy of the selected entry
+    pub fn start_name_prompt(&mut self, kind: NameKind) {
+        let Some(node) = self.selected_node() else {
+            return;
+        };
+        let source = node.path.clone();
+        if kind == NameKind::Rename && node.is_root() {
+            self.status = Some(format!("{} is a scan root", scan::display_path(&source)));
+            return;
+        }
+        let dir = match kind {
+            NameKind::NewFile | NameKind::NewDir if node.is_dir => source.clone(),
+            _ => match source.parent() {
+                Some(parent) => parent.to_path_buf(),
+                None => return,
+            },
+        };
+        let name = source
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let input = match kind {
+            NameKind::NewFile | NameKind::NewDir => String::new(),
+            NameKind::Rename => name,
+            NameKind::Duplicate => format!("{}.copy", name),
+        };
+        self.name_prompt = Some(NamePrompt {
+            kind,
+            source,
+            dir,
+            input,
+        });
+    }
+
+    pub fn cancel_name_prompt(&mut self) {
+        self.name_prompt = None;
+    }
+
+    // Show what the typed name will do and wait for confirmation
+    pub fn accept_name_prompt(&mut self) {
+        let Some(prompt) = self.name_prompt.take() else {
+            return;
+        };
+        let name = prompt.input.trim();
+        if name.is_empty() {
+            self.status = Some("Empty name, nothing was changed".to_string());
+            return;
+        }
+        // "~/..." and absolute paths are taken as they are
+        let path = match scan::expand_tilde(name) {
+            Ok(path) => prompt.dir.join(path),
+            Err(e) => {
+                self.status = Some(e.to_string());
+                return;
+            }
+        };
+        if path == prompt.source && prompt.kind == NameKind::Rename {
+            self.status = Some("Same name, nothing was changed".to_string());
+            return;
+        }
+        if fs::symlink_metadata(&path).is_ok() {
+            self.status = Some(format!("{} already exists", scan::display_path(&path)));
+            return;
+        }
+        let (title, op) = match prompt.kind {
+            NameKind::NewFile => ("Create this file?", FileOp::CreateFile(path)),
+            NameKind::NewDir => ("Create this directory?", FileOp::CreateDir(path)),
+            NameKind::Rename => (
+                "Rename?",
+                FileOp::Rename {
+                    from: prompt.source,
+                    to: path,
+                },
+            ),
+            NameKind::Duplicate => (
+                "Copy?",
+                FileOp::Duplicate {
+                    from: prompt.source,
+                    to: path,
+                },
+            ),
+        };
+        self.confirm = Some(Confirm {
+            title: title.to_string(),
+            lines: vec![op.to_string()],
+            pending: Pending::File(op),
+        });
+    }
+
+    // Ask before moving the selected entry to the trash
+    pub fn plan_delete(&mut self) {
+        let Some(node) = self.selected_node() else {
+            return;
+        };
+        let path = node.path.clone();
+        if node.is_root() {
+            self.status = Some(format!("{} is a scan root", scan::display_path(&path)));
+            return;
+        }
+        let trashed = match fileops::trash_path(&path) {
+            Ok(trashed) => trashed,
+            Err(e) => {
+                self.status = Some(format!("Not deleting: {}", e));
+                return;
+            }
+        };
+        let is_dir = fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.is_dir());
+        let op = FileOp::Delete { path, trashed };
+        let mut lines = vec![op.to_string()];
+        if is_dir {
+            lines.push(String::new());
+            lines.push("Everything in the directory goes with it.".to_string());
+        }
+        self.confirm = Some(Confirm {
+            title: "Move to the trash?".to_string(),
+            lines,
+            pending: Pending::File(op),
+        });
+    }
+
+    fn apply_file_op(&mut self, op: &FileOp) {
+        let show = |path: &Path| scan::display_path(path);
+        if let Err(e) = op.apply() {
+            let what = match op {
+                FileOp::CreateFile(path) | FileOp::CreateDir(path) => {
+                    format!("create {}", show(path))
+                }
+                FileOp::Rename { from, .. } => format!("rename {}", show(from)),
+                FileOp::Duplicate { from, .. } => format!("copy {}", show(from)),
+                FileOp::Delete { path, .. } => format!("delete {}", show(path)),
+            };
+            self.status = Some(format!("Could not {}: {}", what, e));
+            return;
+        }
+        let (status, select) = match op {
+            FileOp::CreateFile(path) | FileOp::CreateDir(path) => {
+                (format!("Created {}", show(path)), Some(path))
+            }
+            FileOp::Rename { from, to } => {
+                (format!("Renamed {} to {}", show(from), show(to)), Some(to))
+            }
+            FileOp::Duplicate { from, to } => {
+                (format!("Copied {} to {}", show(from), show(to)), Some(to))
+            }
+            FileOp::Delete { path, trashed } => {
+                let slot = trashed.parent().unwrap_or(trashed);
+                (
+                    format!("Moved {} to the trash in {}", show(path), show(slot)),
+                    None,
+                )
+            }
+        };
+        self.status = Some(status);
+        let action = match op {
+            FileOp::CreateFile(_) => "create",
+            FileOp::CreateDir(_) => "mkdir",
+            FileOp::Rename { .. } => "rename",
+            FileOp::Duplicate { .. } => "copy",
+            FileOp::Delete { .. } => "delete",
+        };
+        self.journal_record(action, vec![Step::for_file_op(op)]);
+
+        self.index_file_op(op);
+        let paths = op.changed_paths();
+        for path in &paths {
+            self.preview.invalidate(path);
+        }
+        let mut dirs: Vec<&Path> = paths.iter().filter_map(|path| path.parent()).collect();
+        dirs.sort();
+        dirs.dedup();
+        self.reload_dirs(&dirs);
+        if let Some(row) = select.and_then(|path| self.dotfiles.reveal(path)) {
+            self.select(row);
+        }
+        self.git_changed();
+    }
+
+    // Keep the index and an open filter in step with a change made here,
+    // without scanning again
+    fn index_file_op(&mut self, op: &FileOp) {
+        // A running scan will still come across it
+        if self.scan.is_some() {
+            return;
+        }
+        let mut added = Vec::new();
+        match op {
+            FileOp::CreateFile(path) | FileOp::CreateDir(path) => added.push(path.clone()),
+            FileOp::Rename { from, to } | FileOp::Duplicate { from, to } => {
+                added = self
+                    .index
+                    .iter()
+                    .filter_map(|entry| {
+                        let rest = entry.strip_prefix(from).ok()?;
+                        Some(if rest.as_os_str().is_empty() {
+                            to.clone()
+                        } else {
+                            to.join(rest)
+                        })
+                    })
+                    .collect();
+                if let FileOp::Rename { .. } = op {
+                    self.index.retain(|entry| !entry.starts_with(from));
+                }
+            }
+            FileOp::Delete { path, .. } => self.index.retain(|entry| !entry.starts_with(path)),
+        }
+        self.index.extend(added.iter().cloned());
+        if let Some(filter) = &mut self.filter {
+            filter.extend(&added);
+        }
+    }
+
+    // Add a change to the undo journal. Failing to does not undo the change.
+    fn journal_record(&mut self, action: &str, steps: Vec<Step>) {
+        if let Err(e) = journal::record(action, steps) {
+            let status = self.status.take().unwrap_or_default();
+            self.status = Some(format!("{} (not in the undo journal: {})", status, e));
+        }
+        if let Some(view) = &mut self.journal {
+            if let Err(e) = view.refresh() {
+                self.status = Some(e.to_string());
+            }
+        }
+    }
+
+    pub fn open_journal(&mut self) {
+        match JournalView::new() {
+            Ok(view) if view.journal.records.is_empty() => {
+                self.status = Some("Nothing in the undo journal yet".into())
+            }
+            Ok(view) => {
+                self.journal = Some(view);
+                self.focus = Focus::List;
+                self.preview_scroll = 0;
+                self.preview_highlight = None;
+            }
+            Err(e) => self.status = Some(e.to_string()),
+        }
+    }
+
+    pub fn close_journal(&mut self) {
+        self.journal = None;
+        self.preview_scroll = 0;
+    }
+
+    pub fn move_journal_selection(&mut self, delta: isize) {
+        if let Some(view) = &mut self.journal {
+            view.move_selection(delta);
+            self.preview_scroll = 0;
+        }
+    }
+
+    // Ask before taking back the last change in the journal
+    pub fn plan_undo(&mut self) {
+        let journal = match Journal::load() {
+            Ok(journal) => journal,
+            Err(e) => {
+                self.status = Some(e.to_string());
+                return;
+            }
+        };
+        let Some(record) = journal.last_done() else {
+            self.status = Some("Nothing to undo".to_string());
+            return;
+        };
+        self.confirm = Some(Confirm {
+            title: format!("Undo {} from {}?", record.action, record.time),
+            lines: record.steps.iter().rev().map(Step::undo_line).collect(),
+            pending: Pending::Undo,
+        });
+    }
+
+    // Ask before doing the last undone change again
+    pub fn plan_redo(&mut self) {
+        let journal = match Journal::load() {
+            Ok(journal) => journal,
+            Err(e) => {
+                self.status = Some(e.to_string());
+                return;
+            }
+        };
+        let Some(record) = journal.last_undone() else {
+            self.status = Some("Nothing to redo".to_string());
+            return;
+        };
+        self.confirm = Some(Confirm {
+            title: format!("Redo {} from {}?", record.action, record.time),
+            lines: record.steps.iter().map(Step::to_string).collect(),
+            pending: Pending::Redo,
+        });
+    }
+
+    fn undo(&mut self) {
+        match Journal::load().and_then(|mut journal| journal.undo()) {
+            Ok(record) => {
+                self.status = Some(format!("Undid {} from {}", record.action, record.time));
+                self.journal_applied(&record.steps);
+            }
+            Err(e) => self.status = Some(format!("Could not undo: {}", e)),
+        }
+    }
+
+    fn redo(&mut self) {
+        match Journal::load().and_then(|mut journal| journal.redo()) {
+            Ok(record) => {
+                self.status = Some(format!("Redid {} from {}", record.action, record.time));
+                self.journal_applied(&record.steps);
+            }
+            Err(e) => self.status = Some(format!("Could not redo: {}", e)),
+        }
+    }
+
+    // Bring every view up to date after an undo or redo
+    fn journal_applied(&mut self, steps: &[Step]) {
+        let views = [
+            self.journal.as_mut().map(|view| view.refresh()),
+            self.links.as_mut().map(|view| view.refresh()),
+            self.backups.as_mut().map(|view| view.refresh()),
+        ];
+        if let Some(Err(e)) = views.into_iter().flatten().find(Result::is_err) {
+            self.status = Some(e.to_string());
+        }
+        let mut paths: Vec<&Path> = steps.iter().flat_map(Step::changed_paths).collect();
+        paths.sort();
+        paths.dedup();
+        self.paths_changed(&paths);
+        self.git_changed();
+    }
+
+    // Recent commits touching the selected path
+    pub fn open_log(&mut self) {
+        let Some(path) = self.selected_path().map(|path| path.to_path_buf()) else {
+            return;
+        };
+        let Some(repo) = self.git.repo_for(&path).cloned() else {
+            self.status = Some("Not in a git repository".to_string());
+            return;
+        };
+        match LogView::new(repo, path) {
+            Ok(view) if view.commits.is_empty() => {
+                self.status = Some(format!(
+                    "No commits touch {}",
+                    scan::display_path(&view.path)
+                ))
+            }
+            Ok(view) => {
+                self.log = Some(view);
+                self.focus = Focus::List;
+                self.preview_scroll = 0;
+                self.preview_highlight = None;
+            }
+            Err(e) => self.status = Some(format!("git log: {}", e)),
+        }
+    }
+
+    pub fn close_log(&mut self) {
+        self.log = None;
+        self.preview_scroll = 0;
+    }
+
+    pub fn move_log_selection(&mut self, delta: isize) {
+        if let Some(log) = &mut self.log {
+            log.move_selection(delta);
+            self.preview_scroll = 0;
+        }
+    }
+
+    // Re-read directories in the tree, keeping the selection on the same path
+    pub fn reload_dirs(&mut self, dirs: &[&Path]) {
+        let selected = self.selected_node().map(|node| node.path.clone());
+        for dir in dirs {
+            self.dotfiles.reload_dir(dir);
+        }
+        self.dir_sizes.invalidate(dirs);
+        self.preview.recheck();
+        let row = selected
+            .and_then(|path| self.dotfiles.row_of(&path))
+            .or(self.list_state.selected())
+            .map(|row| row.min(self.dotfiles.len().saturating_sub(1)));
+        self.list_state.select(row);
+    }
+
+    pub fn update_filter(&mut self, update: impl FnOnce(&mut Filter)) {
+        if let Some(filter) = &mut self.filter {
+            update(filter);
+            self.preview_scroll = 0;
+        }
+    }
+
+    pub fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Focus::List => Focus::Preview,
+            Focus::Preview => Focus::List,
+        };
+    }
+
+    // Select a row, starting the preview of the new entry at the top
+    fn select(&mut self, row: usize) {
+        if self.list_state.selected() != Some(row) {
+            self.preview_scroll = 0;
+            self.preview_highlight = None;
+        }
+        self.list_state.select(Some(row));
+    }
+
+    // Move the selection by `delta` rows, stopping at either end
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.dotfiles.len() == 0 {
+            return;
+        }
+        let selected = self.list_state.selected().unwrap_or(0);
+        let last = self.dotfiles.len() - 1;
+        self.select(selected.saturating_add_signed(delta).min(last));
+    }
+
+    pub fn select_next(&mut self) {
+        self.move_selection(1);
+    }
+
+    pub fn select_previous(&mut self) {
+        self.move_selection(-1);
+    }
+
+    pub fn select_first(&mut self) {
+        self.move_selection(isize::MIN);
+    }
+
+    pub fn select_last(&mut self) {
+        self.move_selection(isize::MAX);
+    }
+
+    pub fn list_page(&self) -> isize {
+        self.list_height.max(1) as isize
+    }
+
+    // Scroll the preview by `delta` lines, keeping the last page in view
+    pub fn scroll_preview(&mut self, delta: isize) {
+        let max = self.preview_lines.saturating_sub(self.preview_height);
+        self.preview_scroll = self.preview_scroll.saturating_add_signed(delta).min(max);
+    }
+
+    pub fn preview_page(&self) -> isize {
+        self.preview_height.max(1) as isize
+    }
+
+    // Switch the preview between the file and its diff against HEAD
+    pub fn toggle_diff(&mut self) {
+        self.diff_base = match self.diff_base {
+            Some(_) => None,
+            None => Some(DiffBase::Head),
+        };
+        self.preview_scroll = 0;
+        self.preview_highlight = None;
+    }
+
+    // Compare with the index instead of HEAD, or back
+    pub fn switch_diff_base(&mut self) {
+        self.diff_base = Some(self.diff_base.map_or(DiffBase::Index, DiffBase::other));
+        self.preview_scroll = 0;
+        self.preview_highlight = None;
+    }
+
+    // Diff of the selected path, running git only when it is not cached yet
+    pub fn current_diff(&mut self) -> Option<&io::Result<Diff>> {
+        let base = self.diff_base?;
+        let path = self.selected_path()?.to_path_buf();
+        let cached = self
+            .diff
+            .as_ref()
+            .is_some_and(|(diff_path, diff_base, _)| *diff_path == path && *diff_base == base);
+        if !cached {
+            let untracked = self.git.status(&path).is_none_or(|status| status.untracked);
+            let diff = match self.git.repo_for(&path) {
+                Some(_) if untracked => Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "Not tracked by git.",
+                )),
+                Some(repo) => repo.diff(&path, base),
+                None => Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "Not in a git repository.",
+                )),
+            };
+            self.diff = Some((path, base, diff));
+        }
+        self.diff.as_ref().map(|(_, _, diff)| diff)
+    }
+
+    // Scroll the diff to the next or previous hunk and highlight its header
+    pub fn jump_to_hunk(&mut self, forward: bool) {
+        let diff = match &mut self.backups {
+            Some(view) => view.selected_diff(),
+            None => self.current_diff(),
+        };
+        let hunks = match diff {
+            Some(Ok(diff)) => diff.hunks.clone(),
+            _ => return,
+        };
+        let current = self.preview_highlight.map(|line| line - 1);
+        let target = if forward {
+            hunks
+                .iter()
+                .position(|&hunk| current.is_none_or(|line| hunk > line))
+        } else {
+            hunks
+                .iter()
+                .rposition(|&hunk| current.is_none_or(|line| hunk < line))
+        };
+        let Some(index) = target else {
+            self.status = Some(if hunks.is_empty() {
+                "No changes".to_string()
+            } else {
+                format!("No {} hunk", if forward { "next" } else { "previous" })
+            });
+            return;
+        };
+        self.preview_highlight = Some(hunks[index] + 1);
+        self.preview_scroll = hunks[index];
+        self.scroll_preview(0);
+        self.status = Some(format!("Hunk {} of {}", index + 1, hunks.len()));
+    }
+
+    pub fn expand_selected(&mut self) {
+        if let Some(selected) = self.list_state.selected() {
+            self.dotfiles.expand(selected);
+        }
+    }
+
+    pub fn collapse_selected(&mut self) {
+        if let Some(selected) = self.list_state.selected() {
+            let selected = self.dotfiles.collapse(selected);
+            self.select(selected);
+        }
+    }
+
+    // Pick up changes to the selected entry, e.g. after it was edited
+    pub fn refresh_selected(&mut self) {
+        self.git_changed();
+        self.preview.recheck();
+        if self.filter.is_some() || self.grep.is_some() || self.links.is_some() {
+            return;
+        }
+        let Some(selected) = self.list_state.selected() else {
+            return;
+        };
+        let path = self.selected_node().map(|node| node.path.clone());
+
+        self.dotfiles.reload_parent(selected);
+        if let Some(path) = &path {
+            self.dir_sizes.invalidate(&[path]);
+        }
+
+        let row = path
+            .and_then(|path| self.dotfiles.row_of(&path))
+            .unwrap_or(selected.min(self.dotfiles.len().saturating_sub(1)));
+        self.list_state.select(Some(row));
+    }
+
+    pub fn toggle_selected(&mut self) {
+        if let Some(selected) = self.list_state.selected() {
+            self.dotfiles.toggle(selected);
+        }
+    }
+
+    // Select what was clicked, focusing its pane. Pressing on the divider
+    // starts resizing; clicking the selected directory again toggles it.
+    pub fn click(&mut self, column: u16, row: u16) {
+        let divider = self.list_area.right();
+        if self.list_area.height > 0 && (divider.saturating_sub(1)..=divider).contains(&column) {
+            self.dragging = true;
+        } else if contains(self.list_area, column, row) {
+            self.focus = Focus::List;
+            // The first row is the border
+            let (Some((offset, selected)), Some(row)) =
+                (self.list_window(), row.checked_sub(self.list_area.y + 1))
+            else {
+                return;
+            };
+            let clicked = offset + row as usize;
+            if clicked == selected && self.list_view_is_tree() {
+                self.toggle_selected();
+            } else {
+                self.move_list_selection(clicked as isize - selected as isize);
+            }
+        } else if contains(self.preview_area, column, row) {
+            self.focus = Focus::Preview;
+        }
+    }
+
+    // Move the divider while it is being dragged
+    pub fn drag(&mut self, column: u16) {
+        if !self.dragging || self.body_area.width == 0 {
+            return;
+        }
+        let offset = column.saturating_sub(self.body_area.x) as u32 + 1;
+        let split = offset * 100 / self.body_area.width as u32;
+        self.split = (split as u16).clamp(MIN_SPLIT, MAX_SPLIT);
+    }
+
+    // Stop resizing and remember the new split for the next run
+    pub fn release(&mut self) {
+        if !self.dragging {
+            return;
+        }
+        self.dragging = false;
+        let state = State { split: self.split };
+        if let Err(e) = state.save() {
+            self.status = Some(format!("Could not save the layout: {}", e));
+        }
+    }
+
+    // Move the list selection or scroll the preview under the pointer
+    pub fn wheel(&mut self, column: u16, row: u16, delta: isize) {
+        if contains(self.list_area, column, row) {
+            self.move_list_selection(delta);
+        } else if contains(self.preview_area, column, row) {
+            self.scroll_preview(delta);
+        }
+    }
+
+    // The list in the left pane: no view or filter is shown over the tree
+    fn list_view_is_tree(&self) -> bool {
+        self.log.is_none()
+            && self.backups.is_none()
+            && self.journal.is_none()
+            && self.links.is_none()
+            && self.grep.is_none()
+            && self.filter.is_none()
+    }
+
+    // First visible and selected row of the list in the left pane, in the
+    // order ui::draw_list picks it
+    fn list_window(&self) -> Option<(usize, usize)> {
+        let state = if let Some(log) = &self.log {
+            &log.list_state
+        } else if let Some(view) = &self.backups {
+            &view.list_state
+        } else if let Some(view) = &self.journal {
+            &view.list_state
+        } else if let Some(view) = &self.links {
+            &view.list_state
+        } else if let Some(grep) = &self.grep {
+            &grep.list_state
+        } else if let Some(filter) = &self.filter {
+            &filter.list_state
+        } else {
+            &self.list_state
+        };
+        Some((state.offset(), state.selected()?))
+    }
+
+    fn move_list_selection(&mut self, delta: isize) {
+        if self.log.is_some() {
+            self.move_log_selection(delta);
+        } else if self.backups.is_some() {
+            self.move_backups_selection(delta);
+        } else if self.journal.is_some() {
+            self.move_journal_selection(delta);
+        } else if self.links.is_some() {
+            self.move_links_selection(delta);
+        } else if self.grep.is_some() {
+            self.move_grep_selection(delta);
+        } else if self.filter.is_some() {
+            self.update_filter(|filter| filter.move_selection(delta));
+        } else {
+            self.move_selection(delta);
+        }
+    }
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+    (area.x..area.right()).contains(&column) && (area.y..area.bottom()).contains(&row)
+}
+
+// "1 file", "2 files"
+fn count(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("{} {}", n, noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
@@ -0,0 +1,566 @@
+use std::{
+    fs,
+    io::{self, Error, ErrorKind, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use ratatui::widgets::ListState;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config,
+    git::{self, Diff},
+    links,
+    metadata::{self, FileInfo, Kind},
+    scan,
+};
+
+// Snapshots live under $XDG_DATA_HOME/dotfiles-tui/backups:
+//
+//     objects/ab/cdef…            file contents, named by their SHA-256
+//     snapshots/<id>.toml         what was backed up and when
+//
+// A file that did not change between snapshots is stored once.
+#[derive(Debug, Clone)]
+pub struct Store {
+    root: PathBuf,
+}
+
+// One backup run. The id is its UTC creation time, e.g. "2024-05-01_13-37-00".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub id: String,
+    pub created: String,
+    // Why it was taken, e.g. "manual" or "before restore"
+    pub label: String,
+    #[serde(default, rename = "file")]
+    pub entries: Vec<Entry>,
+}
+
+// A backed up file or symlink
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub path: PathBuf,
+    // Content hash for files, target for symlinks
+    pub hash: Option<String>,
+    pub link: Option<PathBuf>,
+    pub mode: u32,
+    pub size: u64,
+}
+
+// How the live path compares with a snapshot entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveState {
+    Unchanged,
+    Changed,
+    Missing,
+}
+
+impl LiveState {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Unchanged => "same",
+            Self::Changed => "changed",
+            Self::Missing => "missing",
+        }
+    }
+}
+
+impl Store {
+    pub fn open() -> io::Result<Self> {
+        Ok(Self::at(config::data_dir()?.join("backups")))
+    }
+
+    pub fn at(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.root.join("objects").join(&hash[..2]).join(&hash[2..])
+    }
+
+    fn manifest_path(&self, id: &str) -> PathBuf {
+        self.root.join("snapshots").join(format!("{}.toml", id))
+    }
+
+    // Copy the files and symlinks among `paths` into a new snapshot.
+    // Directories and unreadable files are left out and reported.
+    pub fn snapshot(&self, paths: &[PathBuf], label: &str) -> io::Result<(Snapshot, Vec<String>)> {
+        let mut entries = Vec::new();
+        let mut skipped = Vec::new();
+        for path in paths {
+            match self.store_entry(path) {
+                Ok(Some(entry)) => entries.push(entry),
+                Ok(None) => {}
+                Err(e) => skipped.push(format!("{}: {}", scan::display_path(path), e)),
+            }
+        }
+        if entries.is_empty() {
+            let reason = skipped
+                .first()
+                .map_or("no files to back up", String::as_str);
+            return Err(Error::new(ErrorKind::InvalidInput, reason.to_string()));
+        }
+
+        let now = SystemTime::now();
+        let created = metadata::format_time(now);
+        let stamp = metadata::format_stamp(now);
+        let mut id = stamp.clone();
+        let mut n = 1;
+        while self.manifest_path(&id).exists() {
+            n += 1;
+            id = format!("{}.{}", stamp, n);
+        }
+
+        let snapshot = Snapshot {
+            id,
+            created,
+            label: label.to_string(),
+            entries,
+        };
+        let text = toml::to_string(&snapshot).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        write_atomic(&self.manifest_path(&snapshot.id), text.as_bytes())?;
+        Ok((snapshot, skipped))
+    }
+
+    fn store_entry(&self, path: &Path) -> io::Result<Option<Entry>> {
+        let info = FileInfo::read(path)?;
+        let (hash, link) = match info.kind {
+            Kind::File => {
+                let mut hash = hash_file(path)?;
+                if !self.object_path(&hash).exists() {
+                    hash = self.store_object(path)?;
+                }
+                (Some(hash), None)
+            }
+            Kind::Symlink => (None, info.link_target.clone()),
+            Kind::Dir | Kind::Other => return Ok(None),
+        };
+        Ok(Some(Entry {
+            path: path.to_path_buf(),
+            hash,
+            link,
+            mode: info.mode,
+            size: info.size,
+        }))
+    }
+
+    // Copy a file into the objects, hashing what is copied in case the file
+    // changed since it was hashed
+    fn store_object(&self, path: &Path) -> io::Result<String> {
+        let objects = self.root.join("objects");
+        fs::create_dir_all(&objects)?;
+        let temp = objects.join(format!("incoming.{}", std::process::id()));
+        let mut writer = HashingWriter {
+            file: fs::File::create(&temp)?,
+            hasher: Sha256::new(),
+        };
+        io::copy(&mut fs::File::open(path)?, &mut writer)?;
+        let hash = format!("{:x}", writer.hasher.finalize());
+
+        let object = self.object_path(&hash);
+        if let Some(parent) = object.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::rename(&temp, &object)?;
+        Ok(hash)
+    }
+
+    // Every snapshot, newest first
+    pub fn list(&self) -> io::Result<Vec<Snapshot>> {
+        let dir = self.root.join("snapshots");
+        let read_dir = match fs::read_dir(&dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        let mut snapshots = Vec::new();
+        for dir_entry in read_dir {
+            let path = dir_entry?.path();
+            if path.extension().is_none_or(|ext| ext != "toml") {
+                continue;
+            }
+            let text = fs::read_to_string(&path)?;
+            let snapshot: Snapshot = toml::from_str(&text).map_err(|e| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{}: {}", scan::display_path(&path), e.message()),
+                )
+            })?;
+            snapshots.push(snapshot);
+        }
+        snapshots.sort_by(|a, b| id_order(&b.id).cmp(&id_order(&a.id)));
+        Ok(snapshots)
+    }
+
+    pub fn live_state(&self, entry: &Entry) -> LiveState {
+        let Ok(info) = FileInfo::read(&entry.path) else {
+            return LiveState::Missing;
+        };
+        let same = match (&entry.hash, &entry.link) {
+            (Some(hash), _) => {
+                info.kind == Kind::File && hash_file(&entry.path).is_ok_and(|live| live == *hash)
+            }
+            (None, Some(link)) => info.link_target.as_ref() == Some(link),
+            (None, None) => false,
+        };
+        if same {
+            LiveState::Unchanged
+        } else {
+            LiveState::Changed
+        }
+    }
+
+    // Unified diff from the backed up file to the live one
+    pub fn diff(&self, snapshot: &Snapshot, entry: &Entry) -> io::Result<Diff> {
+        let Some(hash) = &entry.hash else {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "symlinks have no contents to compare",
+            ));
+        };
+        // A missing live file diffs as deleted
+        let live = if entry.path.is_file() {
+            entry.path.clone()
+        } else {
+            PathBuf::from("/dev/null")
+        };
+        git::diff_files(
+            &self.object_path(hash),
+            &live,
+            &format!("{} @ {}", scan::display_path(&entry.path), snapshot.id),
+            &format!("{} (live)", scan::display_path(&entry.path)),
+        )
+    }
+
+    // Put the backed up file or symlink back at its path, replacing whatever is there
+    // unless it is a directory
+    pub fn restore(&self, entry: &Entry) -> io::Result<()> {
+        if let Ok(existing) = fs::symlink_metadata(&entry.path) {
+            if existing.is_dir() {
+                return Err(Error::new(
+                    ErrorKind::AlreadyExists,
+                    format!("{} is a directory", scan::display_path(&entry.path)),
+                ));
+            }
+            // Writing through a symlink would change the file it points to
+            if existing.file_type().is_symlink() || entry.link.is_some() {
+                fs::remove_file(&entry.path)?;
+            }
+        }
+        if let Some(parent) = entry.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        match (&entry.hash, &entry.link) {
+            (Some(hash), _) => {
+                copy_atomic(&self.object_path(hash), &entry.path)?;
+                set_mode(&entry.path, entry.mode)
+            }
+            (None, Some(link)) => links::symlink(link, &entry.path),
+            (None, None) => Err(Error::new(ErrorKind::InvalidData, "empty snapshot entry")),
+        }
+    }
+}
+
+// Snapshot ids in the order they were taken: "<stamp>" is the first of its
+// second and "<stamp>.2" the next, so "<stamp>.10" comes after "<stamp>.9"
+fn id_order(id: &str) -> (&str, u64) {
+    match id.split_once('.') {
+        Some((stamp, n)) => (stamp, n.parse().unwrap_or(u64::MAX)),
+        None => (id, 1),
+    }
+}
+
+// Writes to a file and hashes what was written
+struct HashingWriter {
+    file: fs::File,
+    hasher: Sha256,
+}
+
+impl Write for HashingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+// SHA-256 of a file, read a buffer at a time
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+// Write to a temporary file next to `path`, then rename it into place
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    fs::write(&temp, content)?;
+    fs::rename(&temp, path)
+}
+
+// Same as write_atomic, streaming the content from another file
+fn copy_atomic(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut temp = to.as_os_str().to_owned();
+    temp.push(".tmp");
+    io::copy(&mut fs::File::open(from)?, &mut fs::File::create(&temp)?)?;
+    fs::rename(&temp, to)
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> io::Result<()> {
+    Ok(())
+}
+
+// The snapshots view: a list of snapshots, or the files of one of them
+#[derive(Debug)]
+pub struct BackupView {
+    pub store: Store,
+    pub snapshots: Vec<Snapshot>,
+    pub list_state: ListState,
+    // Snapshot whose files are listed, with the state of each live path
+    pub open: Option<(usize, Vec<LiveState>)>,
+    // Row to return to when going back to the snapshot list
+    snapshot_row: usize,
+    // Diff of the selected file, computed once per selection
+    diff: Option<(usize, usize, io::Result<Diff>)>,
+}
+
+impl BackupView {
+    pub fn new(store: Store) -> io::Result<Self> {
+        let mut view = Self {
+            store,
+            snapshots: Vec::new(),
+            list_state: ListState::default(),
+            open: None,
+            snapshot_row: 0,
+            diff: None,
+        };
+        view.refresh()?;
+        Ok(view)
+    }
+
+    // Re-read the snapshot list and go back to it
+    pub fn refresh(&mut self) -> io::Result<()> {
+        self.snapshots = self.store.list()?;
+        self.open = None;
+        self.diff = None;
+        self.snapshot_row = 0;
+        self.list_state
+            .select((!self.snapshots.is_empty()).then_some(0));
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        match &self.open {
+            Some((snapshot, _)) => self.snapshots[*snapshot].entries.len(),
+            None => self.snapshots.len(),
+        }
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.len() == 0 {
+            return;
+        }
+        let selected = self.list_state.selected().unwrap_or(0);
+        let last = self.len() - 1;
+        self.list_state
+            .select(Some(selected.saturating_add_signed(delta).min(last)));
+    }
+
+    // The highlighted snapshot, or the one whose files are listed
+    pub fn selected_snapshot(&self) -> Option<&Snapshot> {
+        match &self.open {
+            Some((snapshot, _)) => self.snapshots.get(*snapshot),
+            None => self.snapshots.get(self.list_state.selected()?),
+        }
+    }
+
+    pub fn selected_entry(&self) -> Option<&Entry> {
+        let (snapshot, _) = self.open.as_ref()?;
+        self.snapshots[*snapshot]
+            .entries
+            .get(self.list_state.selected()?)
+    }
+
+    // List the files of the highlighted snapshot
+    pub fn open_selected(&mut self) {
+        if self.open.is_some() {
+            return;
+        }
+        let Some(row) = self.list_state.selected() else {
+            return;
+        };
+        let states = self.snapshots[row]
+            .entries
+            .iter()
+            .map(|entry| self.store.live_state(entry))
+            .collect();
+        self.open = Some((row, states));
+        self.snapshot_row = row;
+        self.list_state
+            .select((!self.snapshots[row].entries.is_empty()).then_some(0));
+    }
+
+    // Back to the snapshot list; false if it was already shown
+    pub fn close_snapshot(&mut self) -> bool {
+        if self.open.take().is_none() {
+            return false;
+        }
+        self.list_state.select(Some(self.snapshot_row));
+        true
+    }
+
+    pub fn selected_diff(&mut self) -> Option<&io::Result<Diff>> {
+        let snapshot = self.open.as_ref()?.0;
+        let row = self.list_state.selected()?;
+        if self
+            .diff
+            .as_ref()
+            .is_none_or(|(s, r, _)| (*s, *r) != (snapshot, row))
+        {
+            let snap = &self.snapshots[snapshot];
+            let diff = self.store.diff(snap, snap.entries.get(row)?);
+            self.diff = Some((snapshot, row, diff));
+        }
+        self.diff.as_ref().map(|(_, _, diff)| diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+
+    fn object_count(store: &Store) -> usize {
+        walkdir::WalkDir::new(store.root.join("objects"))
+            .into_iter()
+            .filter(|entry| entry.as_ref().unwrap().file_type().is_file())
+            .count()
+    }
+
+    #[test]
+    fn restore_brings_back_the_snapshot() {
+        let dir = TestDir::new("backup-restore");
+        let store = Store::at(dir.path("backups"));
+        let file = dir.write("home/.bashrc", "alias ll='ls -l'\n");
+        let gone = dir.write("home/.profile", "export EDITOR=vi\n");
+
+        let (snapshot, skipped) = store
+            .snapshot(&[file.clone(), gone.clone(), dir.path("home")], "manual")
+            .unwrap();
+        assert!(skipped.is_empty());
+        assert_eq!(snapshot.entries.len(), 2);
+        assert_eq!(store.list().unwrap()[0].id, snapshot.id);
+
+        fs::write(&file, "alias ll='ls -la'\n").unwrap();
+        fs::remove_file(&gone).unwrap();
+        for entry in &snapshot.entries {
+            store.restore(entry).unwrap();
+        }
+        assert_eq!(fs::read_to_string(&file).unwrap(), "alias ll='ls -l'\n");
+        assert_eq!(fs::read_to_string(&gone).unwrap(), "export EDITOR=vi\n");
+    }
+
+    #[test]
+    fn identical_contents_are_stored_once() {
+        let dir = TestDir::new("backup-dedup");
+        let store = Store::at(dir.path("backups"));
+        let a = dir.write("a.conf", "same");
+        let b = dir.write("b.conf", "same");
+
+        store.snapshot(&[a.clone(), b], "manual").unwrap();
+        store.snapshot(std::slice::from_ref(&a), "manual").unwrap();
+        assert_eq!(object_count(&store), 1);
+        assert_eq!(store.list().unwrap().len(), 2);
+
+        fs::write(&a, "different").unwrap();
+        store.snapshot(&[a], "manual").unwrap();
+        assert_eq!(object_count(&store), 2);
+    }
+
+    #[test]
+    fn live_state_and_diff_follow_the_live_file() {
+        let dir = TestDir::new("backup-live");
+        let store = Store::at(dir.path("backups"));
+        let file = dir.write("a.conf", "one\ntwo\n");
+        let (snapshot, _) = store
+            .snapshot(std::slice::from_ref(&file), "manual")
+            .unwrap();
+        let entry = &snapshot.entries[0];
+        assert_eq!(store.live_state(entry), LiveState::Unchanged);
+        assert!(store.diff(&snapshot, entry).unwrap().lines.is_empty());
+
+        fs::write(&file, "one\nthree\n").unwrap();
+        assert_eq!(store.live_state(entry), LiveState::Changed);
+        let lines = store.diff(&snapshot, entry).unwrap().lines;
+        assert!(lines.iter().any(|line| line == "-two"));
+        assert!(lines.iter().any(|line| line == "+three"));
+
+        fs::remove_file(&file).unwrap();
+        assert_eq!(store.live_state(entry), LiveState::Missing);
+        let lines = store.diff(&snapshot, entry).unwrap().lines;
+        assert!(lines.iter().any(|line| line == "-one"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restore_puts_back_symlinks() {
+        let dir = TestDir::new("backup-symlink");
+        let store = Store::at(dir.path("backups"));
+        let link = dir.path("link");
+        links::symlink(Path::new("old"), &link).unwrap();
+        let (snapshot, _) = store
+            .snapshot(std::slice::from_ref(&link), "manual")
+            .unwrap();
+
+        fs::remove_file(&link).unwrap();
+        dir.write("link", "a file now");
+        assert_eq!(store.live_state(&snapshot.entries[0]), LiveState::Changed);
+        store.restore(&snapshot.entries[0]).unwrap();
+        assert_eq!(fs::read_link(&link).unwrap(), Path::new("old"));
+    }
+
+    #[test]
+    fn snapshot_ids_sort_by_time_then_number() {
+        let mut ids = vec![
+            "2024-05-01_13-37-00.10",
+            "2024-05-01_13-37-00.2",
+            "2024-05-02_08-00-00",
+            "2024-05-01_13-37-00",
+            "2024-05-01_13-37-00.9",
+        ];
+        ids.sort_by_key(|id| id_order(id));
+        assert_eq!(
+            ids,
+            [
+                "2024-05-01_13-37-00",
+                "2024-05-01_13-37-00.2",
+                "2024-05-01_13-37-00.9",
+                "2024-05-01_13-37-00.10",
+                "2024-05-02_08-00-00",
+            ]
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use std::{
+    fs,
+    io::{self, BufWriter, Error, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    backup::Store,
+    config::Config,
+    export,
+    git::Git,
+    grep,
+    links::{self, LinkAction, LinkView},
+    scan::{self, ScanRoot},
+};
+
+const USAGE: &str = "\
+Usage: dotfiles-tui [command]
+
+Commands:
+  tui                  Browse dotfiles interactively (the default)
+  list                 Print every path found under the scan roots
+  show <path>          Print a file, or the entries of a directory
+  search <pattern>     Print matching lines as path:line:text
+  status               Print git changes as badges and path:
+                       U conflicted, + staged, M modified, ? untracked
+  backup [path...]     Snapshot the given paths, or everything found
+  link [--dry-run]     Symlink the files of the managed repo into HOME
+  export [--ndjson]    Print every path found with its kind, size, mtime,
+                       permissions, symlink target and SHA-256 as one
+                       JSON document, or as one JSON record per line
+  help                 Show this message
+
+search exits with status 1 when nothing matches.
+
+export follows schema 1: {\"schema\": 1, \"entries\": [record, ...]}, or
+with --ndjson a {\"schema\": 1} line followed by one record per line. Each
+record has path, kind (file, dir, symlink or other), size in bytes, mtime in
+seconds since the Unix epoch, mode (permission bits), permissions (like
+-rw-r--r--), target (symlinks only) and sha256 (files only), with null where
+a field does not apply. Paths and targets that are not valid UTF-8 are
+written lossily, with their exact bytes in hex as path_hex and target_hex.";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Tui,
+    Help,
+    List,
+    Show(String),
+    Search(String),
+    Status,
+    Backup(Vec<String>),
+    Link { dry_run: bool },
+    Export { ndjson: bool },
+}
+
+pub fn parse(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    let Some(name) = args.next() else {
+        return Ok(Command::Tui);
+    };
+    let rest: Vec<String> = args.collect();
+    let no_args = |command: Command| match rest.first() {
+        Some(arg) => Err(format!("unexpected argument \"{}\" to {}", arg, name)),
+        None => Ok(command),
+    };
+    let one_arg = |what: &str| match rest.as_slice() {
+        [arg] => Ok(arg.clone()),
+        [] => Err(format!("{} needs a {}", name, what)),
+        [_, extra, ..] => Err(format!("unexpected argument \"{}\" to {}", extra, name)),
+    };
+
+    match name.as_str() {
+        "tui" => no_args(Command::Tui),
+        "help" | "-h" | "--help" => Ok(Command::Help),
+        "list" => no_args(Command::List),
+        "show" => one_arg("path").map(Command::Show),
+        "search" => one_arg("pattern").map(Command::Search),
+        "status" => no_args(Command::Status),
+        "backup" => Ok(Command::Backup(rest)),
+        "link" => match rest.as_slice() {
+            [] => Ok(Command::Link { dry_run: false }),
+            [flag] if flag == "--dry-run" || flag == "-n" => Ok(Command::Link { dry_run: true }),
+            [arg, ..] => Err(format!("unexpected argument \"{}\" to link", arg)),
+        },
+        "export" => match rest.as_slice() {
+            [] => Ok(Command::Export { ndjson: false }),
+            [flag] if flag == "--ndjson" => Ok(Command::Export { ndjson: true }),
+            [arg, ..] => Err(format!("unexpected argument \"{}\" to export", arg)),
+        },
+        _ => Err(format!(
+            "unknown command \"{}\", see dotfiles-tui --help",
+            name
+        )),
+    }
+}
+
+// Run a non-interactive command, returning the exit status
+pub fn run(command: Command) -> io::Result<i32> {
+    let mut out = BufWriter::new(io::stdout().lock());
+    let status = match command {
+        Command::Tui => 0,
+        Command::Help => {
+            writeln!(out, "{}", USAGE)?;
+            0
+        }
+        Command::List => {
+            let config = Config::load()?;
+            for path in scan::find_dotfiles(&config.roots) {
+                writeln!(out, "{}", path.display())?;
+            }
+            0
+        }
+        Command::Show(path) => {
+            show(&mut out, &resolve(&path)?)?;
+            0
+        }
+        Command::Search(pattern) => {
+            let config = Config::load()?;
+            let index = scan::find_dotfiles(&config.roots);
+            let (matches, _) = grep::search(&index, &pattern);
+            for found in &matches {
+                writeln!(
+                    out,
+                    "{}:{}:{}",
+                    found.path.display(),
+                    found.line,
+                    found.text
+                )?;
+            }
+            if matches.is_empty() {
+                1
+            } else {
+                0
+            }
+        }
+        Command::Status => {
+            let config = Config::load()?;
+            let mut git = Git::for_config(&config)?;
+            status(&mut out, &mut git, &scan::find_dotfiles(&config.roots))?;
+            0
+        }
+        Command::Backup(paths) => {
+            backup(&mut out, &paths)?;
+            0
+        }
+        Command::Link { dry_run } => {
+            link(&mut out, dry_run)?;
+            0
+        }
+        Command::Export { ndjson } => {
+            let config = Config::load()?;
+            let paths = scan::find_dotfiles(&config.roots);
+            for reason in export::write(&mut out, &paths, ndjson)? {
+                eprintln!("dotfiles-tui: skipped {}", reason);
+            }
+            0
+        }
+    };
+    out.flush()?;
+    Ok(status)
+}
+
+// Badges and path of every changed file among `paths`
+fn status(out: &mut impl Write, git: &mut Git, paths: &[PathBuf]) -> io::Result<()> {
+    git.wait_for_status();
+    if let Some(e) = &git.error {
+        return Err(Error::other(e.clone()));
+    }
+    for path in paths {
+        let Some(status) = git.status(path) else {
+            continue;
+        };
+        let badges: String = [
+            (status.conflicted, 'U'),
+            (status.staged, '+'),
+            (status.modified, 'M'),
+            (status.untracked, '?'),
+        ]
+        .iter()
+        .filter(|(set, _)| *set)
+        .map(|(_, badge)| *badge)
+        .collect();
+        if !badges.is_empty() && !path.is_dir() {
+            writeln!(out, "{:<3} {}", badges, path.display())?;
+        }
+    }
+    Ok(())
+}
+
+// A command line path: ~ expanded, relative to the current directory
+fn resolve(arg: &str) -> io::Result<PathBuf> {
+    std::path::absolute(scan::expand_tilde(arg)?)
+}
+
+fn show(out: &mut impl Write, path: &Path) -> io::Result<()> {
+    let metadata = fs::metadata(path).map_err(|e| with_path(path, e))?;
+    if !metadata.is_dir() {
+        let mut file = fs::File::open(path).map_err(|e| with_path(path, e))?;
+        io::copy(&mut file, out)?;
+        return Ok(());
+    }
+
+    let mut entries: Vec<(bool, String)> = fs::read_dir(path)
+        .map_err(|e| with_path(path, e))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let is_dir = entry.path().is_dir();
+            (is_dir, entry.file_name().to_string_lossy().to_string())
+        })
+        .collect();
+    entries.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+    for (is_dir, name) in entries {
+        writeln!(out, "{}{}", name, if is_dir { "/" } else { "" })?;
+    }
+    Ok(())
+}
+
+fn backup(out: &mut impl Write, args: &[String]) -> io::Result<()> {
+    let paths = if args.is_empty() {
+        scan::find_dotfiles(&Config::load()?.roots)
+    } else {
+        let mut paths = Vec::new();
+        for arg in args {
+            let path = resolve(arg)?;
+            if path.is_dir() {
+                // Same ignore rules and cache excludes as the scan roots
+                paths.extend(scan::find_dotfiles(&[ScanRoot::new(path, None, false)]));
+            } else {
+                paths.push(path);
+            }
+        }
+        paths
+    };
+
+    let (snapshot, skipped) = Store::open()?.snapshot(&paths, "manual")?;
+    for reason in &skipped {
+        writeln!(out, "skipped {}", reason)?;
+    }
+    writeln!(
+        out,
+        "Backed up {} {} to snapshot {}",
+        snapshot.entries.len(),
+        if snapshot.entries.len() == 1 {
+            "file"
+        } else {
+            "files"
+        },
+        snapshot.id
+    )?;
+    Ok(())
+}
+
+fn link(out: &mut impl Write, dry_run: bool) -> io::Result<()> {
+    let Some(repo) = Config::load()?.repo else {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            "no managed repo: set repo = \"~/dotfiles\" in config.toml",
+        ));
+    };
+    let view = LinkView::new(repo)?;
+    let entries: Vec<_> = view.entries.iter().collect();
+    let (operations, skipped) = links::plan(LinkAction::Link, &entries);
+
+    for operation in &operations {
+        writeln!(out, "{}", operation)?;
+    }
+    for reason in &skipped {
+        writeln!(out, "skip    {}", reason)?;
+    }
+    if operations.is_empty() {
+        writeln!(out, "Nothing to link")?;
+        return Ok(());
+    }
+    if dry_run {
+        return Ok(());
+    }
+    match links::apply(&operations) {
+        Ok(done) => writeln!(out, "Applied {} changes", done),
+        Err((done, e)) => Err(Error::other(format!(
+            "applied {} of {} changes, then failed: {}",
+            done,
+            operations.len(),
+            e
+        ))),
+    }
+}
+
+fn with_path(path: &Path, e: io::Error) -> io::Error {
+    Error::new(e.kind(), format!("{}: {}", scan::display_path(path), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::{self, TestDir};
+
+    #[test]
+    fn status_waits_for_git() {
+        let dir = TestDir::new("cli-status");
+        let repo = dir.mkdir(".config");
+        testdir::git(&repo, &["init", "--quiet"]);
+        let modified = dir.write(".config/foo/x", "one");
+        let clean = dir.write(".config/foo/clean", "same");
+        testdir::git(&repo, &["add", "."]);
+        testdir::git(&repo, &["commit", "--quiet", "--message", "initial"]);
+        fs::write(&modified, "two").unwrap();
+        let untracked = dir.write(".config/foo/y", "new");
+        let staged = dir.write(".config/foo/z", "new");
+        testdir::git(&repo, &["add", "foo/z"]);
+
+        let mut git = Git::discover(&[repo.as_path()], None);
+        let paths = [clean, modified.clone(), untracked.clone(), staged.clone()];
+        let mut out = Vec::new();
+        status(&mut out, &mut git, &paths).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "M   {}\n?   {}\n+   {}\n",
+                modified.display(),
+                untracked.display(),
+                staged.display()
+            )
+        );
+    }
+}
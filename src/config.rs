@@ -0,0 +1,348 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::Deserialize;
+
+use crate::{
+    highlight::SyntaxTheme,
+    keymap::Keymap,
+    scan::{self, ScanRoot},
+    theme::{ColorDepth, Theme},
+};
+
+// Settings from $XDG_CONFIG_HOME/dotfiles-tui/config.toml, e.g.
+//
+//     # "default", "light", "high-contrast", or a file in themes/ (see
+//     # theme.rs). Colors are reduced to 256 or 16 to suit the terminal,
+//     # and left out when NO_COLOR is set.
+//     theme = "light"
+//     # Syntax colors only, instead of the theme's
+//     syntax_theme = "monochrome"
+//     # Dotfiles repo mirroring HOME, managed with symlinks
+//     repo = "~/dotfiles"
+//     # Bare repo used as `git --git-dir=~/.dotfiles --work-tree=~`. Found
+//     # automatically under ~/.dotfiles, ~/.cfg, ~/.dotfiles.git or ~/.dots.
+//     git_dir = "~/.dotfiles"
+//     work_tree = "~"
+//     # Key bindings: "default", or "vim" or "emacs" on top of the defaults
+//     keymap = "vim"
+//
+//     # Per view (global, tree, log, backups, links, grep), key sequences
+//     # in vim notation ("gg", "<C-n>", "<A-lt>") to actions as listed by
+//     # `?`, e.g. "search-contents", or "none" to unbind
+//     [keys.tree]
+//     "<C-f>" = "search"
+//     "s" = "none"
+//
+//     [[root]]
+//     path = "~"
+//     max_depth = 1
+//     hidden_only = true
+//
+//     [[root]]
+//     path = "~/.config"
+//     exclude = ["*.bak", "**/history"]
+//     # .gitignore, .ignore and .dotfilesignore files are honored and
+//     # cache directories skipped unless these are turned off
+//     ignore_files = true
+//     default_excludes = true
+#[derive(Debug)]
+pub struct Config {
+    pub roots: Vec<ScanRoot>,
+    pub theme: Theme,
+    pub repo: Option<PathBuf>,
+    // Explicit bare git repo and its work tree
+    pub git_dir: Option<PathBuf>,
+    pub work_tree: Option<PathBuf>,
+    pub keymap: Keymap,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    theme: Option<String>,
+    syntax_theme: Option<String>,
+    repo: Option<String>,
+    git_dir: Option<String>,
+    work_tree: Option<String>,
+    keymap: Option<String>,
+    #[serde(default)]
+    keys: HashMap<String, HashMap<String, String>>,
+    root: Option<Vec<RootConfig>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RootConfig {
+    path: String,
+    max_depth: Option<usize>,
+    #[serde(default)]
+    hidden_only: bool,
+    #[serde(default)]
+    follow_symlinks: bool,
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default = "enabled")]
+    ignore_files: bool,
+    #[serde(default = "enabled")]
+    default_excludes: bool,
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl Config {
+    // Load the config file, falling back to the defaults when it does not exist
+    pub fn load() -> io::Result<Self> {
+        let path = config_dir()?.join("config.toml");
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(invalid(&path, e)),
+        };
+        Self::parse(&text).map_err(|e| invalid(&path, e))
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let file: ConfigFile = toml::from_str(text).map_err(|e| toml_error(text, e))?;
+
+        let roots = match file.root {
+            Some(roots) if roots.is_empty() => {
+                return Err("at least one [[root]] is required".to_string())
+            }
+            Some(roots) => roots
+                .into_iter()
+                .map(RootConfig::into_scan_root)
+                .collect::<Result<_, _>>()?,
+            None => scan::default_roots().map_err(|e| e.to_string())?,
+        };
+
+        let themes_dir = config_dir().map_err(|e| e.to_string())?.join("themes");
+        let mut theme = Theme::load(file.theme.as_deref().unwrap_or("default"), &themes_dir)?;
+        if let Some(name) = file.syntax_theme {
+            theme.syntax = SyntaxTheme::named(&name).ok_or_else(|| {
+                format!(
+                    "unknown syntax_theme \"{}\" (expected \"default\" or \"monochrome\")",
+                    name
+                )
+            })?;
+        }
+        let theme = theme.fit(ColorDepth::detect());
+
+        let repo = absolute_path(file.repo, "repo")?;
+        let git_dir = absolute_path(file.git_dir, "git_dir")?;
+        let work_tree = absolute_path(file.work_tree, "work_tree")?;
+        if work_tree.is_some() && git_dir.is_none() {
+            return Err("work_tree needs a git_dir".to_string());
+        }
+
+        let keymap = Keymap::new(file.keymap.as_deref().unwrap_or("default"), &file.keys)?;
+
+        Ok(Self {
+            roots,
+            theme,
+            repo,
+            git_dir,
+            work_tree,
+            keymap,
+        })
+    }
+}
+
+impl RootConfig {
+    fn into_scan_root(self) -> Result<ScanRoot, String> {
+        let path = scan::expand_tilde(&self.path).map_err(|e| e.to_string())?;
+        if !path.is_absolute() {
+            return Err(format!(
+                "root path \"{}\" must be absolute or start with ~",
+                self.path
+            ));
+        }
+        if self.max_depth == Some(0) {
+            return Err(format!(
+                "max_depth of root \"{}\" must be at least 1",
+                self.path
+            ));
+        }
+
+        let include = if self.include.is_empty() {
+            None
+        } else {
+            Some(glob_set(&self.include, &self.path, "include")?)
+        };
+
+        Ok(ScanRoot {
+            path,
+            max_depth: self.max_depth,
+            hidden_only: self.hidden_only,
+            follow_symlinks: self.follow_symlinks,
+            include,
+            exclude: glob_set(&self.exclude, &self.path, "exclude")?,
+            ignore_files: self.ignore_files,
+            default_excludes: self.default_excludes,
+            ignores: Default::default(),
+        })
+    }
+}
+
+// An optional path setting, with ~ expanded
+fn absolute_path(value: Option<String>, field: &str) -> Result<Option<PathBuf>, String> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let path = scan::expand_tilde(&value).map_err(|e| e.to_string())?;
+    if !path.is_absolute() {
+        return Err(format!(
+            "{} \"{}\" must be absolute or start with ~",
+            field, value
+        ));
+    }
+    Ok(Some(path))
+}
+
+fn glob_set(patterns: &[String], root: &str, field: &str) -> Result<GlobSet, String> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let glob = Glob::new(pattern).map_err(|e| {
+            format!(
+                "bad {} pattern \"{}\" in root \"{}\": {}",
+                field,
+                pattern,
+                root,
+                e.kind()
+            )
+        })?;
+        builder.add(glob);
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+// $XDG_CONFIG_HOME/dotfiles-tui, or ~/.config/dotfiles-tui
+pub fn config_dir() -> io::Result<PathBuf> {
+    let base = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => scan::home_dir()?.join(".config"),
+    };
+    Ok(base.join("dotfiles-tui"))
+}
+
+// A TOML error with the line it is on
+pub fn toml_error(text: &str, e: toml::de::Error) -> String {
+    match e.span() {
+        Some(span) => {
+            let line = text[..span.start].matches('\n').count() + 1;
+            format!("line {}: {}", line, e.message())
+        }
+        None => e.message().to_string(),
+    }
+}
+
+// $XDG_DATA_HOME/dotfiles-tui, or ~/.local/share/dotfiles-tui
+pub fn data_dir() -> io::Result<PathBuf> {
+    let base = match std::env::var("XDG_DATA_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => scan::home_dir()?.join(".local/share"),
+    };
+    Ok(base.join("dotfiles-tui"))
+}
+
+fn invalid(path: &Path, e: impl std::fmt::Display) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid config {}: {}", scan::display_path(path), e),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> String {
+        Config::parse(text).unwrap_err()
+    }
+
+    #[test]
+    fn parse_settings_and_roots() {
+        let config = Config::parse(
+            r#"
+            repo = "/srv/dotfiles"
+            git_dir = "/srv/bare"
+            work_tree = "/srv"
+
+            [[root]]
+            path = "/etc"
+            max_depth = 2
+            hidden_only = true
+            exclude = ["*.bak"]
+            ignore_files = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.repo, Some(PathBuf::from("/srv/dotfiles")));
+        assert_eq!(config.git_dir, Some(PathBuf::from("/srv/bare")));
+        assert_eq!(config.work_tree, Some(PathBuf::from("/srv")));
+        let [root] = config.roots.as_slice() else {
+            panic!("expected one root");
+        };
+        assert_eq!(root.path, Path::new("/etc"));
+        assert_eq!(root.max_depth, Some(2));
+        assert!(root.hidden_only);
+        assert!(root.include.is_none());
+        assert!(root.exclude.is_match("hosts.bak"));
+        assert!(!root.ignore_files);
+        assert!(root.default_excludes);
+    }
+
+    #[test]
+    fn parse_reports_unknown_keys_with_their_line() {
+        let e = error("theme = \"default\"\ncolour = \"red\"\n");
+        assert!(e.starts_with("line 2: unknown field `colour`"), "{}", e);
+        let e = error("[[root]]\npath = \"/etc\"\ndepth = 1\n");
+        assert!(e.starts_with("line 3: unknown field `depth`"), "{}", e);
+        let e = error("theme = \n");
+        assert!(e.starts_with("line 1: "), "{}", e);
+    }
+
+    #[test]
+    fn parse_rejects_bad_roots() {
+        assert_eq!(error("root = []"), "at least one [[root]] is required");
+        assert_eq!(
+            error("[[root]]\npath = \"dotfiles\""),
+            "root path \"dotfiles\" must be absolute or start with ~"
+        );
+        assert_eq!(
+            error("[[root]]\npath = \"/etc\"\nmax_depth = 0"),
+            "max_depth of root \"/etc\" must be at least 1"
+        );
+        let e = error("[[root]]\npath = \"/etc\"\ninclude = [\"[a\"]");
+        assert!(
+            e.starts_with("bad include pattern \"[a\" in root \"/etc\": "),
+            "{}",
+            e
+        );
+    }
+
+    #[test]
+    fn parse_rejects_bad_settings() {
+        assert_eq!(
+            error("repo = \"dotfiles\"\n[[root]]\npath = \"/etc\""),
+            "repo \"dotfiles\" must be absolute or start with ~"
+        );
+        assert_eq!(
+            error("work_tree = \"/srv\"\n[[root]]\npath = \"/etc\""),
+            "work_tree needs a git_dir"
+        );
+        assert_eq!(
+            error("syntax_theme = \"neon\"\n[[root]]\npath = \"/etc\""),
+            "unknown syntax_theme \"neon\" (expected \"default\" or \"monochrome\")"
+        );
+    }
+}
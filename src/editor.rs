@@ -0,0 +1,30 @@
+use std::{
+    io,
+    path::Path,
+    process::{Command, ExitStatus},
+};
+
+// The user's editor command: $VISUAL, then $EDITOR, then vi.
+// The variables may carry arguments, e.g. EDITOR="code --wait".
+pub fn editor_command() -> Vec<String> {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .map(|value| {
+            value
+                .split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .find(|command| !command.is_empty())
+        .unwrap_or_else(|| vec!["vi".to_string()])
+}
+
+// Run the editor on `path` and wait for it to exit
+pub fn open(path: &Path) -> io::Result<ExitStatus> {
+    let command = editor_command();
+    Command::new(&command[0])
+        .args(&command[1..])
+        .arg(path)
+        .status()
+}
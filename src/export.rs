@@ -0,0 +1,172 @@
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::{
+    backup,
+    metadata::{self, FileInfo, Kind},
+};
+
+// Version of the record layout below. Within a version, fields may be added
+// but never renamed, removed or changed in meaning; any such change bumps it.
+pub const SCHEMA_VERSION: u32 = 1;
+
+// One discovered path. As JSON:
+//
+//     {
+//       "path": "/home/me/.bashrc",        absolute
+//       "kind": "file",                    "file", "dir", "symlink" or "other"
+//       "size": 3771,                      bytes; for symlinks the link itself
+//       "mtime": 1714570620,               seconds since the Unix epoch, or null
+//       "mode": 420,                       permission bits, 0o644 here
+//       "permissions": "-rw-r--r--",
+//       "target": null,                    symlink target as written, else null
+//       "sha256": "9f86d0…"                hex digest of file contents, else null
+//     }
+//
+// Symlinks are described, not followed. A path or target that is not valid
+// UTF-8 is written lossily, with its exact bytes in hex as "path_hex" or
+// "target_hex"; these fields are left out otherwise.
+#[derive(Debug, Serialize)]
+pub struct Record {
+    pub path: String,
+    pub kind: &'static str,
+    pub size: u64,
+    pub mtime: Option<i64>,
+    pub mode: u32,
+    pub permissions: String,
+    pub target: Option<String>,
+    pub sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_hex: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_hex: Option<String>,
+}
+
+// The pretty JSON document: {"schema": 1, "entries": [...]}
+#[derive(Debug, Serialize)]
+struct Inventory {
+    schema: u32,
+    entries: Vec<Record>,
+}
+
+// First line of NDJSON output: {"schema": 1}
+#[derive(Debug, Serialize)]
+struct Header {
+    schema: u32,
+}
+
+impl Record {
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let info = FileInfo::read(path)?;
+        let kind = match info.kind {
+            Kind::File => "file",
+            Kind::Dir => "dir",
+            Kind::Symlink => "symlink",
+            Kind::Other => "other",
+        };
+        let mtime = info.modified.map(metadata::unix_seconds);
+        let sha256 = match info.kind {
+            Kind::File => Some(backup::hash_file(path)?),
+            _ => None,
+        };
+
+        let target = info.link_target.as_deref();
+        Ok(Self {
+            path: path.to_string_lossy().to_string(),
+            kind,
+            size: info.size,
+            mtime,
+            mode: info.mode,
+            permissions: info.permissions(),
+            target: target.map(|target| target.to_string_lossy().to_string()),
+            sha256,
+            path_hex: non_utf8_hex(path),
+            target_hex: target.and_then(non_utf8_hex),
+        })
+    }
+}
+
+// The bytes of a path that is not valid UTF-8, as lowercase hex
+fn non_utf8_hex(path: &Path) -> Option<String> {
+    if path.to_str().is_some() {
+        return None;
+    }
+    Some(
+        path.as_os_str()
+            .as_encoded_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect(),
+    )
+}
+
+// Write every path as one pretty JSON document, or as NDJSON: a header line
+// with the schema version, then one record per line written as soon as it is
+// read. Paths that vanish or cannot be read are skipped and returned.
+pub fn write(out: &mut impl Write, paths: &[PathBuf], ndjson: bool) -> io::Result<Vec<String>> {
+    let mut skipped = Vec::new();
+    let mut entries = Vec::new();
+    if ndjson {
+        let header = Header {
+            schema: SCHEMA_VERSION,
+        };
+        serde_json::to_writer(&mut *out, &header)?;
+        writeln!(out)?;
+    }
+    for path in paths {
+        let record = match Record::read(path) {
+            Ok(record) => record,
+            Err(e) => {
+                skipped.push(format!("{}: {}", path.display(), e));
+                continue;
+            }
+        };
+        if ndjson {
+            serde_json::to_writer(&mut *out, &record)?;
+            writeln!(out)?;
+        } else {
+            entries.push(record);
+        }
+    }
+
+    if !ndjson {
+        let inventory = Inventory {
+            schema: SCHEMA_VERSION,
+            entries,
+        };
+        serde_json::to_writer_pretty(&mut *out, &inventory)?;
+        writeln!(out)?;
+    }
+    Ok(skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ndjson_starts_with_the_schema() {
+        let mut out = Vec::new();
+        let skipped = write(&mut out, &[PathBuf::from("/nonexistent/x")], true).unwrap();
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(String::from_utf8(out).unwrap(), "{\"schema\":1}\n");
+    }
+
+    #[test]
+    fn only_non_utf8_paths_get_hex() {
+        assert_eq!(non_utf8_hex(Path::new("/home/me/.bashrc")), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths_are_written_as_hex() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        let path = Path::new(OsStr::from_bytes(b"/tmp/caf\xe9"));
+        assert_eq!(non_utf8_hex(path).as_deref(), Some("2f746d702f636166e9"));
+    }
+}
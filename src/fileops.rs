@@ -0,0 +1,288 @@
+use std::{
+    fmt, fs,
+    io::{self, Error, ErrorKind},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::Serialize;
+
+use crate::{backup, config, links, metadata, scan};
+
+// A change to the entries in the list, shown in a dialog before it is applied
+#[derive(Debug, Clone)]
+pub enum FileOp {
+    CreateFile(PathBuf),
+    CreateDir(PathBuf),
+    Rename { from: PathBuf, to: PathBuf },
+    Duplicate { from: PathBuf, to: PathBuf },
+    // Move into the trash, at `trashed`
+    Delete { path: PathBuf, trashed: PathBuf },
+}
+
+impl fmt::Display for FileOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let show = |path: &Path| scan::display_path(path);
+        match self {
+            Self::CreateFile(path) => write!(f, "create  {}", show(path)),
+            Self::CreateDir(path) => write!(f, "mkdir   {}", show(path)),
+            Self::Rename { from, to } => write!(f, "rename  {} -> {}", show(from), show(to)),
+            Self::Duplicate { from, to } => write!(f, "copy    {} -> {}", show(from), show(to)),
+            Self::Delete { path, trashed } => {
+                write!(f, "trash   {} -> {}", show(path), show(trashed))
+            }
+        }
+    }
+}
+
+impl FileOp {
+    // Never replaces an existing entry
+    pub fn apply(&self) -> io::Result<()> {
+        match self {
+            Self::CreateFile(path) => {
+                create_parent(path)?;
+                fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(path)
+                    .map(|_| ())
+            }
+            Self::CreateDir(path) => {
+                refuse_existing(path)?;
+                fs::create_dir_all(path)
+            }
+            Self::Rename { from, to } => {
+                refuse_existing(to)?;
+                create_parent(to)?;
+                move_path(from, to)
+            }
+            Self::Duplicate { from, to } => {
+                refuse_existing(to)?;
+                if to.starts_with(from) {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "cannot copy a directory into itself",
+                    ));
+                }
+                create_parent(to)?;
+                copy_path(from, to)
+            }
+            Self::Delete { path, trashed } => trash(path, trashed),
+        }
+    }
+
+    // Entries that appeared or went away
+    pub fn changed_paths(&self) -> Vec<&Path> {
+        match self {
+            Self::CreateFile(path) | Self::CreateDir(path) => vec![path],
+            Self::Rename { from, to } => vec![from, to],
+            Self::Duplicate { to, .. } => vec![to],
+            Self::Delete { path, .. } => vec![path],
+        }
+    }
+}
+
+// Where `path` goes when deleted: $XDG_DATA_HOME/dotfiles-tui/trash/<time>/<name>,
+// with <time>.toml next to it recording where it came from
+pub fn trash_path(path: &Path) -> io::Result<PathBuf> {
+    let Some(name) = path.file_name() else {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("cannot delete {}", scan::display_path(path)),
+        ));
+    };
+    let root = config::data_dir()?.join("trash");
+    let stamp = metadata::format_stamp(SystemTime::now());
+    let mut id = stamp.clone();
+    let mut n = 1;
+    while root.join(&id).exists() {
+        n += 1;
+        id = format!("{}.{}", stamp, n);
+    }
+    let trashed = root.join(id).join(name);
+    if trashed.starts_with(path) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("the trash is inside {}", scan::display_path(path)),
+        ));
+    }
+    Ok(trashed)
+}
+
+#[derive(Debug, Serialize)]
+struct TrashInfo<'a> {
+    original: &'a Path,
+    deleted: String,
+}
+
+pub fn trash(path: &Path, trashed: &Path) -> io::Result<()> {
+    // Fail before writing anything if it is already gone
+    fs::symlink_metadata(path)?;
+    let Some(slot) = trashed.parent() else {
+        return Err(Error::new(ErrorKind::InvalidInput, "no trash directory"));
+    };
+    let info = TrashInfo {
+        original: path,
+        deleted: metadata::format_time(SystemTime::now()),
+    };
+    let text = toml::to_string(&info).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    let mut info_path = slot.as_os_str().to_owned();
+    info_path.push(".toml");
+    backup::write_atomic(Path::new(&info_path), text.as_bytes())?;
+
+    let moved = fs::create_dir_all(slot).and_then(|_| move_path(path, trashed));
+    if moved.is_err() {
+        // Leave no trace of a delete that did not happen
+        let _ = fs::remove_dir(slot);
+        let _ = fs::remove_file(&info_path);
+    }
+    moved
+}
+
+// Put a trashed entry back at `path` and drop its trash slot once empty
+pub fn untrash(trashed: &Path, path: &Path) -> io::Result<()> {
+    refuse_existing(path)?;
+    create_parent(path)?;
+    move_path(trashed, path)?;
+    if let Some(slot) = trashed.parent() {
+        if fs::remove_dir(slot).is_ok() {
+            let mut info_path = slot.as_os_str().to_owned();
+            info_path.push(".toml");
+            let _ = fs::remove_file(info_path);
+        }
+    }
+    Ok(())
+}
+
+pub fn refuse_existing(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path).is_ok() {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("{} already exists", scan::display_path(path)),
+        ));
+    }
+    Ok(())
+}
+
+pub fn create_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) => fs::create_dir_all(parent),
+        None => Ok(()),
+    }
+}
+
+// Rename, or copy and remove when `to` is on another filesystem
+pub fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => copy_and_remove(from, to),
+        result => result,
+    }
+}
+
+fn copy_and_remove(from: &Path, to: &Path) -> io::Result<()> {
+    copy_path(from, to)?;
+    if fs::symlink_metadata(from)?.is_dir() {
+        fs::remove_dir_all(from)
+    } else {
+        fs::remove_file(from)
+    }
+}
+
+// Copy a file, a symlink as a symlink, or a directory with everything in it
+pub fn copy_path(from: &Path, to: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(from)?;
+    let file_type = metadata.file_type();
+    if file_type.is_symlink() {
+        links::symlink(&fs::read_link(from)?, to)
+    } else if file_type.is_dir() {
+        fs::create_dir(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        fs::set_permissions(to, metadata.permissions())
+    } else {
+        fs::copy(from, to).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn move_path_renames_files_and_directories() {
+        let dir = TestDir::new("fileops-move");
+        let file = dir.write("a.conf", "a");
+        dir.write("tree/sub/b.conf", "b");
+
+        move_path(&file, &dir.path("moved.conf")).unwrap();
+        assert!(!file.exists());
+        assert_eq!(read(&dir.path("moved.conf")), "a");
+
+        move_path(&dir.path("tree"), &dir.path("moved")).unwrap();
+        assert!(!dir.path("tree").exists());
+        assert_eq!(read(&dir.path("moved/sub/b.conf")), "b");
+
+        assert!(move_path(&dir.path("missing"), &dir.path("x")).is_err());
+    }
+
+    // The fallback move_path takes across filesystems
+    #[cfg(unix)]
+    #[test]
+    fn copy_and_remove_keeps_contents_and_links() {
+        let dir = TestDir::new("fileops-copy-remove");
+        dir.write("tree/sub/b.conf", "b");
+        links::symlink(Path::new("sub/b.conf"), &dir.path("tree/link")).unwrap();
+        let file = dir.write("c.conf", "c");
+
+        copy_and_remove(&dir.path("tree"), &dir.path("moved")).unwrap();
+        assert!(!dir.path("tree").exists());
+        assert_eq!(read(&dir.path("moved/sub/b.conf")), "b");
+        assert_eq!(
+            fs::read_link(dir.path("moved/link")).unwrap(),
+            Path::new("sub/b.conf")
+        );
+
+        copy_and_remove(&file, &dir.path("d.conf")).unwrap();
+        assert!(!file.exists());
+        assert_eq!(read(&dir.path("d.conf")), "c");
+    }
+
+    #[test]
+    fn trash_moves_the_entry_and_records_where_it_was() {
+        let dir = TestDir::new("fileops-trash");
+        let path = dir.write("doomed.conf", "x");
+        let trashed = dir.path("trash/1/doomed.conf");
+
+        trash(&path, &trashed).unwrap();
+        assert!(!path.exists());
+        assert_eq!(read(&trashed), "x");
+        let info: toml::Table = toml::from_str(&read(&dir.path("trash/1.toml"))).unwrap();
+        assert_eq!(info["original"].as_str(), path.to_str());
+
+        untrash(&trashed, &path).unwrap();
+        assert_eq!(read(&path), "x");
+        assert!(!dir.path("trash/1").exists());
+        assert!(!dir.path("trash/1.toml").exists());
+    }
+
+    #[test]
+    fn failed_trash_leaves_no_info_file() {
+        let dir = TestDir::new("fileops-trash-fail");
+        assert!(trash(&dir.path("missing"), &dir.path("trash/1/missing")).is_err());
+        assert!(!dir.path("trash/1.toml").exists());
+
+        // The slot cannot be created, after the info file was written
+        let path = dir.write("doomed.conf", "x");
+        dir.write("trash/2", "in the way");
+        assert!(trash(&path, &dir.path("trash/2/doomed.conf")).is_err());
+        assert_eq!(read(&path), "x");
+        assert!(!dir.path("trash/2.toml").exists());
+    }
+}
@@ -0,0 +1,325 @@
+use std::path::PathBuf;
+
+use ratatui::widgets::ListState;
+
+use crate::scan;
+
+const MATCH: i64 = 16;
+const CONSECUTIVE: i64 = 12;
+// Matching right after '/', '.', '-', '_' or at the very start
+const WORD_START: i64 = 10;
+// Matching inside the file name rather than a parent directory
+const FILE_NAME: i64 = 4;
+const GAP_START: i64 = -3;
+const GAP_EXTENSION: i64 = -1;
+
+#[derive(Debug)]
+pub struct Match {
+    pub score: i64,
+    // Char indices of the matched characters in the candidate
+    pub positions: Vec<usize>,
+}
+
+// Case-insensitive subsequence match, scored so that compact matches at word
+// boundaries and in the file name rank first. None if not all of `query` matches.
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<Match> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let chars: Vec<char> = candidate.chars().collect();
+    if query.is_empty() {
+        return Some(Match {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+    if query.len() > chars.len() {
+        return None;
+    }
+
+    let file_name_start = chars.iter().rposition(|&c| c == '/').map_or(0, |i| i + 1);
+    let bonus = |j: usize| {
+        let word_start = j == 0 || matches!(chars[j - 1], '/' | '.' | '-' | '_' | ' ');
+        MATCH
+            + if word_start { WORD_START } else { 0 }
+            + if j >= file_name_start { FILE_NAME } else { 0 }
+    };
+    let lower: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+
+    // best[i][j]: best score with query[i] matched at candidate[j]
+    // from[i][j]: where query[i - 1] was matched on that best path
+    let n = chars.len();
+    let mut best = vec![vec![None; n]; query.len()];
+    let mut from = vec![vec![0; n]; query.len()];
+
+    for j in 0..n {
+        if lower[j] == query[0] {
+            best[0][j] = Some(bonus(j));
+        }
+    }
+
+    for i in 1..query.len() {
+        // Best of best[i - 1][k] + k over k < j - 1, for matches with a gap.
+        // Adding k ranks earlier matches correctly because GAP_EXTENSION is -1.
+        let mut gap_best: Option<(i64, usize)> = None;
+        for j in 1..n {
+            if j >= 2 {
+                if let Some(score) = best[i - 1][j - 2] {
+                    let candidate = (score + (j - 2) as i64, j - 2);
+                    if gap_best.is_none_or(|(best, _)| candidate.0 > best) {
+                        gap_best = Some(candidate);
+                    }
+                }
+            }
+            if lower[j] != query[i] {
+                continue;
+            }
+
+            let consecutive = best[i - 1][j - 1].map(|score| (score + CONSECUTIVE, j - 1));
+            let gapped = gap_best.map(|(score, k)| {
+                // Undo the +k above and charge for the skipped characters
+                let skipped = (j - k - 1) as i64;
+                (
+                    score - k as i64 + GAP_START + GAP_EXTENSION * (skipped - 1),
+                    k,
+                )
+            });
+            let previous = match (consecutive, gapped) {
+                (Some(a), Some(b)) => Some(if a.0 >= b.0 { a } else { b }),
+                (a, b) => a.or(b),
+            };
+            if let Some((score, k)) = previous {
+                best[i][j] = Some(score + bonus(j));
+                from[i][j] = k;
+            }
+        }
+    }
+
+    let last = query.len() - 1;
+    let (mut j, score) = best[last]
+        .iter()
+        .enumerate()
+        .filter_map(|(j, score)| score.map(|score| (j, score)))
+        .max_by_key(|&(j, score)| (score, std::cmp::Reverse(j)))?;
+
+    let mut positions = vec![0; query.len()];
+    for i in (0..query.len()).rev() {
+        positions[i] = j;
+        j = from[i][j];
+    }
+
+    Some(Match { score, positions })
+}
+
+#[derive(Debug)]
+pub struct FilterResult {
+    pub path: PathBuf,
+    pub display: String,
+    pub score: i64,
+    pub positions: Vec<usize>,
+}
+
+// State of the "/" search over every discovered dotfile
+#[derive(Debug)]
+pub struct Filter {
+    pub query: String,
+    pub results: Vec<FilterResult>,
+    pub list_state: ListState,
+    candidates: Vec<(PathBuf, String)>,
+}
+
+impl Filter {
+    pub fn new(index: &[PathBuf]) -> Self {
+        let candidates = index
+            .iter()
+            .map(|path| (path.clone(), scan::display_path(path)))
+            .collect();
+
+        let mut filter = Self {
+            query: String::new(),
+            results: Vec::new(),
+            list_state: ListState::default(),
+            candidates,
+        };
+        filter.update();
+        filter
+    }
+
+    // Add paths found after the search started, ranking only those
+    pub fn extend(&mut self, paths: &[PathBuf]) {
+        let selected = self.selected().map(|result| result.path.clone());
+        let start = self.candidates.len();
+        self.candidates.extend(
+            paths
+                .iter()
+                .map(|path| (path.clone(), scan::display_path(path))),
+        );
+        let found = rank(&self.query, &self.candidates[start..]);
+        self.results.extend(found);
+        self.sort_and_select(selected);
+    }
+
+    pub fn candidate_count(&self) -> usize {
+        self.candidates.len()
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.update();
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.update();
+    }
+
+    pub fn selected(&self) -> Option<&FilterResult> {
+        self.results.get(self.list_state.selected()?)
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.results.is_empty() {
+            return;
+        }
+        let selected = self.list_state.selected().unwrap_or(0);
+        let last = self.results.len() - 1;
+        self.list_state
+            .select(Some(selected.saturating_add_signed(delta).min(last)));
+    }
+
+    // Re-rank against the current query, keeping the same entry selected if it still matches
+    fn update(&mut self) {
+        let selected = self.selected().map(|result| result.path.clone());
+        self.results = rank(&self.query, &self.candidates);
+        self.sort_and_select(selected);
+    }
+
+    fn sort_and_select(&mut self, selected: Option<PathBuf>) {
+        // An empty query keeps the scan order
+        if !self.query.is_empty() {
+            self.results.sort_by(|a, b| {
+                b.score
+                    .cmp(&a.score)
+                    .then_with(|| a.display.len().cmp(&b.display.len()))
+                    .then_with(|| a.display.cmp(&b.display))
+            });
+        }
+
+        let row = selected
+            .and_then(|path| self.results.iter().position(|result| result.path == path))
+            .unwrap_or(0);
+        self.list_state
+            .select((!self.results.is_empty()).then_some(row));
+    }
+}
+
+// The candidates matching `query`, unsorted
+fn rank(query: &str, candidates: &[(PathBuf, String)]) -> Vec<FilterResult> {
+    candidates
+        .iter()
+        .filter_map(|(path, display)| {
+            let found = fuzzy_match(query, display)?;
+            Some(FilterResult {
+                path: path.clone(),
+                display: display.clone(),
+                score: found.score,
+                positions: found.positions,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(query: &str, candidate: &str) -> i64 {
+        fuzzy_match(query, candidate).expect("should match").score
+    }
+
+    #[test]
+    fn matches_subsequences_only() {
+        assert!(fuzzy_match("vrc", ".vimrc").is_some());
+        assert!(fuzzy_match("crv", ".vimrc").is_none());
+        assert!(fuzzy_match("vimrcx", ".vimrc").is_none());
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        let found = fuzzy_match("", ".bashrc").unwrap();
+        assert_eq!(found.score, 0);
+        assert!(found.positions.is_empty());
+        assert!(fuzzy_match("", "").is_some());
+    }
+
+    #[test]
+    fn positions_are_char_indices() {
+        let found = fuzzy_match("rc", "~/.zshrc").unwrap();
+        assert_eq!(found.positions, vec![6, 7]);
+        let found = fuzzy_match("é", "~/café.conf").unwrap();
+        assert_eq!(found.positions, vec![5]);
+    }
+
+    #[test]
+    fn ignores_case_including_unicode() {
+        assert!(fuzzy_match("VIMRC", ".vimrc").is_some());
+        assert!(fuzzy_match("vimrc", ".VimRC").is_some());
+        assert!(fuzzy_match("ÉTÉ", "~/été.toml").is_some());
+        assert!(fuzzy_match("straße", "~/STRAßE").is_some());
+    }
+
+    #[test]
+    fn consecutive_beats_scattered() {
+        assert!(score("conf", "~/.config/x") > score("conf", "~/c/o/n/f"));
+    }
+
+    #[test]
+    fn word_start_beats_middle_of_word() {
+        assert!(score("rc", "~/.config/rc") > score("rc", "~/.config/src"));
+    }
+
+    #[test]
+    fn file_name_beats_parent_directory() {
+        assert!(score("git", "~/.config/x/gitconfig") > score("git", "~/.config/git/x"));
+    }
+
+    #[test]
+    fn prefers_the_earlier_of_equal_matches() {
+        let found = fuzzy_match("a", "~/a_a").unwrap();
+        assert_eq!(found.positions, vec![2]);
+    }
+
+    #[test]
+    fn filter_ranks_best_first_then_shortest() {
+        let paths: Vec<PathBuf> = [
+            "/x/src/vimrc.bak",
+            "/x/.vimrc",
+            "/x/v/i/m/r/c",
+            "/x/.vimrc2",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+        let mut filter = Filter::new(&paths);
+        // Scan order while the query is empty
+        assert_eq!(filter.results.len(), 4);
+        assert_eq!(filter.results[0].path, paths[0]);
+
+        for c in "vimrc".chars() {
+            filter.push(c);
+        }
+        let order: Vec<&str> = filter.results.iter().map(|r| r.display.as_str()).collect();
+        assert_eq!(order[..2], ["/x/.vimrc", "/x/.vimrc2"]);
+        assert_eq!(order.last(), Some(&"/x/v/i/m/r/c"));
+        // The entry selected before still matches, so it stays selected
+        assert_eq!(
+            filter.selected().map(|r| r.path.clone()),
+            Some(paths[0].clone())
+        );
+
+        filter.push('z');
+        assert!(filter.results.is_empty());
+        assert!(filter.selected().is_none());
+    }
+}
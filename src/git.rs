@@ -0,0 +1,865 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, Error, Write},
+    path::{Path, PathBuf},
+    process::{Command, Output, Stdio},
+    sync::mpsc::{self, Receiver, Sender},
+    thread,
+};
+
+use ratatui::widgets::ListState;
+
+use crate::{config::Config, scan};
+
+// Commits listed in the log view
+const LOG_LIMIT: &str = "200";
+
+// Common names for a bare dotfiles repo whose work tree is HOME
+const BARE_CANDIDATES: [&str; 4] = [".dotfiles", ".cfg", ".dotfiles.git", ".dots"];
+
+// A git repository and the directory it tracks. For a bare dotfiles repo
+// these are e.g. ~/.dotfiles and ~.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repo {
+    pub git_dir: PathBuf,
+    pub work_tree: PathBuf,
+}
+
+impl Repo {
+    // The repository containing `path`, if any
+    pub fn discover(path: &Path) -> Option<Self> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(path)
+            .args(["rev-parse", "--absolute-git-dir", "--show-toplevel"])
+            .output()
+            .ok()?;
+        if !output.status.success() {
+            return None;
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.lines();
+        Some(Self {
+            git_dir: PathBuf::from(lines.next()?),
+            work_tree: PathBuf::from(lines.next()?),
+        })
+    }
+
+    // A bare repo under one of the usual names in HOME, used with --work-tree=$HOME
+    pub fn find_bare_in_home() -> Option<Self> {
+        let home = scan::home_dir().ok()?;
+        let git_dir = BARE_CANDIDATES
+            .iter()
+            .map(|name| home.join(name))
+            .filter(|dir| dir.join("HEAD").is_file())
+            .find(|dir| is_bare(dir))?;
+        Some(Self {
+            git_dir,
+            work_tree: home,
+        })
+    }
+
+    // `git` with this repo's git dir and work tree, run from the work tree
+    pub fn command(&self) -> Command {
+        let mut command = Command::new("git");
+        command
+            .arg("--git-dir")
+            .arg(&self.git_dir)
+            .arg("--work-tree")
+            .arg(&self.work_tree)
+            .current_dir(&self.work_tree);
+        command
+    }
+
+    // Run git and return stdout, turning a failure into an error with git's message
+    pub fn run(&self, args: &[&str]) -> io::Result<Vec<u8>> {
+        let output = self.command().args(args).output()?;
+        check(output)
+    }
+
+    pub fn status(&self) -> io::Result<GitStatus> {
+        // Untracked files follow the repo's own status.showUntrackedFiles, which
+        // bare HOME repos usually set to "no". Ignored files are left out, as
+        // listing them walks everything below the work tree; see `ignored`.
+        let stdout = self.run(&["status", "--porcelain=v1", "-z"])?;
+        let mut status = GitStatus::parse(&self.work_tree, &stdout);
+        status.hides_untracked = self
+            .run(&["config", "--get", "status.showUntrackedFiles"])
+            .is_ok_and(|value| String::from_utf8_lossy(&value).trim() == "no");
+        for file in self.run(&["ls-files", "-z"])?.split(|&b| b == 0) {
+            if file.is_empty() {
+                continue;
+            }
+            let path = self.work_tree.join(String::from_utf8_lossy(file).as_ref());
+            for tracked in path.ancestors() {
+                if !tracked.starts_with(&self.work_tree)
+                    || !status.tracked.insert(tracked.to_path_buf())
+                {
+                    break;
+                }
+            }
+        }
+        Ok(status)
+    }
+
+    // Which of `paths` the ignore rules match
+    pub fn ignored(&self, paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+        if paths.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut input = Vec::new();
+        for path in paths {
+            input.extend_from_slice(path.to_string_lossy().as_bytes());
+            input.push(0);
+        }
+        let mut child = self
+            .command()
+            .args(["check-ignore", "-z", "--stdin"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        // Write from another thread, as git answers while it reads
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| Error::other("no pipe to git"))?;
+        let writer = thread::spawn(move || stdin.write_all(&input));
+        let output = child.wait_with_output()?;
+        let written = writer.join();
+        // Exit status 1 only means none of them are ignored
+        let stdout = if output.status.code() == Some(1) {
+            output.stdout
+        } else {
+            check(output)?
+        };
+        written.map_err(|_| Error::other("writing to git failed"))??;
+        let ignored: HashSet<&[u8]> = stdout.split(|&b| b == 0).collect();
+        Ok(paths
+            .iter()
+            .filter(|path| ignored.contains(path.to_string_lossy().as_bytes()))
+            .cloned()
+            .collect())
+    }
+
+    pub fn diff(&self, path: &Path, base: DiffBase) -> io::Result<Diff> {
+        let path_arg = path.to_string_lossy();
+        let mut args = vec!["diff", "--no-color", "--no-ext-diff"];
+        if base == DiffBase::Head {
+            args.push("HEAD");
+        }
+        args.extend(["--", path_arg.as_ref()]);
+        Ok(Diff::parse(&self.run(&args)?))
+    }
+
+    // Stage a file, or the changes to tracked files below a directory so
+    // staging ~ in a bare HOME repo does not add the whole home directory
+    pub fn stage(&self, path: &Path) -> io::Result<()> {
+        let path_arg = path.to_string_lossy();
+        let mut args = vec!["add"];
+        if path.is_dir() {
+            args.push("--update");
+        }
+        args.extend(["--", path_arg.as_ref()]);
+        self.run(&args).map(drop)
+    }
+
+    pub fn unstage(&self, path: &Path) -> io::Result<()> {
+        let path_arg = path.to_string_lossy();
+        self.run(&["restore", "--staged", "--", path_arg.as_ref()])
+            .map(drop)
+    }
+
+    // Commit what is staged and return the new commit's short hash
+    pub fn commit(&self, message: &str) -> io::Result<String> {
+        self.run(&["commit", "--quiet", "--message", message])?;
+        let hash = self.run(&["rev-parse", "--short", "HEAD"])?;
+        Ok(String::from_utf8_lossy(&hash).trim().to_string())
+    }
+
+    // Recent commits touching `path`, newest first
+    pub fn log(&self, path: &Path) -> io::Result<Vec<Commit>> {
+        let path_arg = path.to_string_lossy();
+        let stdout = self.run(&[
+            "log",
+            "--max-count",
+            LOG_LIMIT,
+            "--date=short",
+            "--format=%h%x00%ad%x00%an%x00%s",
+            "--",
+            path_arg.as_ref(),
+        ])?;
+        Ok(String::from_utf8_lossy(&stdout)
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(4, '\0');
+                Some(Commit {
+                    hash: fields.next()?.to_string(),
+                    date: fields.next()?.to_string(),
+                    author: fields.next()?.to_string(),
+                    subject: fields.next()?.to_string(),
+                })
+            })
+            .collect())
+    }
+
+    // Contents of `path` as of `revision`
+    pub fn show(&self, revision: &str, path: &Path) -> io::Result<String> {
+        let relative = path.strip_prefix(&self.work_tree).unwrap_or(path);
+        let object = format!("{}:{}", revision, relative.to_string_lossy());
+        let stdout = self.run(&["show", &object])?;
+        Ok(String::from_utf8_lossy(&stdout).to_string())
+    }
+}
+
+// What the working file is compared with in the diff view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffBase {
+    Head,
+    // Only the changes not staged yet
+    Index,
+}
+
+impl DiffBase {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Head => "HEAD",
+            Self::Index => "index",
+        }
+    }
+
+    pub fn other(self) -> Self {
+        match self {
+            Self::Head => Self::Index,
+            Self::Index => Self::Head,
+        }
+    }
+}
+
+// Unified diff of one path, as printed by `git diff`
+#[derive(Debug)]
+pub struct Diff {
+    pub lines: Vec<String>,
+    // Index of each "@@" hunk header in `lines`
+    pub hunks: Vec<usize>,
+}
+
+impl Diff {
+    fn parse(output: &[u8]) -> Self {
+        Self::from_lines(
+            String::from_utf8_lossy(output)
+                .lines()
+                .map(|line| line.replace('\t', "    "))
+                .collect(),
+        )
+    }
+
+    fn from_lines(lines: Vec<String>) -> Self {
+        let hunks = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.starts_with("@@"))
+            .map(|(i, _)| i)
+            .collect();
+        Self { lines, hunks }
+    }
+}
+
+// Diff of two files outside any repository. The header names them
+// `old_name` and `new_name` instead of their paths.
+pub fn diff_files(old: &Path, new: &Path, old_name: &str, new_name: &str) -> io::Result<Diff> {
+    let output = Command::new("git")
+        .args(["diff", "--no-index", "--no-color", "--no-ext-diff", "--"])
+        .arg(old)
+        .arg(new)
+        .output()?;
+    // Exit status 1 only means the files differ
+    let stdout = if output.status.code() == Some(1) {
+        output.stdout
+    } else {
+        check(output)?
+    };
+
+    let diff = Diff::parse(&stdout);
+    let Some(&first_hunk) = diff.hunks.first() else {
+        return Ok(diff);
+    };
+    let mut lines = vec![format!("--- {}", old_name), format!("+++ {}", new_name)];
+    lines.extend(diff.lines.into_iter().skip(first_hunk));
+    Ok(Diff::from_lines(lines))
+}
+
+fn is_bare(git_dir: &Path) -> bool {
+    Command::new("git")
+        .arg("--git-dir")
+        .arg(git_dir)
+        .args(["rev-parse", "--is-bare-repository"])
+        .output()
+        .is_ok_and(|output| {
+            output.status.success() && String::from_utf8_lossy(&output.stdout).trim() == "true"
+        })
+}
+
+fn check(output: Output) -> io::Result<Vec<u8>> {
+    if output.status.success() {
+        return Ok(output.stdout);
+    }
+    // `git commit` explains "nothing to commit" on stdout
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let message = stderr
+        .lines()
+        .chain(stdout.lines())
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("git failed")
+        .trim()
+        .to_string();
+    Err(Error::other(message))
+}
+
+// What git says about one path. Directories combine the flags of everything below them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FileStatus {
+    pub staged: bool,
+    pub modified: bool,
+    pub untracked: bool,
+    pub ignored: bool,
+    pub conflicted: bool,
+}
+
+impl FileStatus {
+    // "staged, modified" for the info panel
+    pub fn describe(self) -> String {
+        let flags = [
+            (self.conflicted, "conflicted"),
+            (self.staged, "staged"),
+            (self.modified, "modified"),
+            (self.untracked, "untracked"),
+            (self.ignored, "ignored"),
+        ];
+        let words: Vec<&str> = flags
+            .iter()
+            .filter(|(set, _)| *set)
+            .map(|(_, word)| *word)
+            .collect();
+        if words.is_empty() {
+            "clean".to_string()
+        } else {
+            words.join(", ")
+        }
+    }
+
+    fn merge(&mut self, other: FileStatus) {
+        self.staged |= other.staged;
+        self.modified |= other.modified;
+        self.untracked |= other.untracked;
+        self.conflicted |= other.conflicted;
+    }
+
+    fn from_code(x: u8, y: u8) -> Self {
+        match (x, y) {
+            (b'?', b'?') => Self {
+                untracked: true,
+                ..Self::default()
+            },
+            (b'!', b'!') => Self {
+                ignored: true,
+                ..Self::default()
+            },
+            (b'U', _) | (_, b'U') | (b'A', b'A') | (b'D', b'D') => Self {
+                conflicted: true,
+                ..Self::default()
+            },
+            _ => Self {
+                staged: x != b' ',
+                modified: y != b' ',
+                ..Self::default()
+            },
+        }
+    }
+}
+
+// `git status` of one repository, keyed by absolute path
+#[derive(Debug, Default)]
+pub struct GitStatus {
+    files: HashMap<PathBuf, FileStatus>,
+    // Every ancestor directory of a changed file, with the combined flags
+    dirs: HashMap<PathBuf, FileStatus>,
+    // Directories git reports as wholly ignored or untracked
+    whole_dirs: Vec<(PathBuf, FileStatus)>,
+    // Tracked files and the directories holding them
+    tracked: HashSet<PathBuf>,
+    // status.showUntrackedFiles is "no", as usual for a bare HOME repo
+    hides_untracked: bool,
+}
+
+impl GitStatus {
+    fn parse(work_tree: &Path, output: &[u8]) -> Self {
+        let mut status = Self::default();
+        let mut entries = output.split(|&b| b == 0);
+
+        while let Some(entry) = entries.next() {
+            if entry.len() < 4 {
+                continue;
+            }
+            let file_status = FileStatus::from_code(entry[0], entry[1]);
+            let relative = String::from_utf8_lossy(&entry[3..]).to_string();
+            // Renames and copies are followed by the original path
+            if matches!(entry[0], b'R' | b'C') {
+                entries.next();
+            }
+
+            let path = work_tree.join(relative.trim_end_matches('/'));
+            if relative.ends_with('/') {
+                status.whole_dirs.push((path.clone(), file_status));
+            } else {
+                status.files.insert(path.clone(), file_status);
+            }
+            if file_status.ignored {
+                continue;
+            }
+            for dir in path.ancestors().skip(1) {
+                if !dir.starts_with(work_tree) {
+                    break;
+                }
+                status
+                    .dirs
+                    .entry(dir.to_path_buf())
+                    .or_default()
+                    .merge(file_status);
+            }
+        }
+
+        status
+    }
+
+    // None for paths git neither tracks nor reports, e.g. untracked files
+    // when status.showUntrackedFiles is "no"
+    pub fn get(&self, path: &Path) -> Option<FileStatus> {
+        if let Some(status) = self.files.get(path) {
+            return Some(*status);
+        }
+        if let Some(status) = self.dirs.get(path) {
+            return Some(*status);
+        }
+        if let Some((_, status)) = self
+            .whole_dirs
+            .iter()
+            .find(|(dir, _)| path.starts_with(dir))
+        {
+            return Some(*status);
+        }
+        self.tracked.contains(path).then(FileStatus::default)
+    }
+}
+
+// What a background git job found out
+#[derive(Debug)]
+enum Update {
+    // `git status` of every repository, in order
+    Status(Vec<io::Result<GitStatus>>),
+    // Paths checked against the ignore rules, and those that matched
+    Ignored(Vec<PathBuf>, Vec<PathBuf>),
+}
+
+// Every repository relevant to the scan roots, with its last known status.
+// Git runs on background threads; `poll` takes in what it reported.
+#[derive(Debug)]
+pub struct Git {
+    repos: Vec<(Repo, GitStatus)>,
+    pub error: Option<String>,
+    // Shown paths that git ignores, and every shown path already checked
+    ignored: HashSet<PathBuf>,
+    checked: HashSet<PathBuf>,
+    // Whether `git status` is running, and whether to run it again afterwards
+    refreshing: bool,
+    stale: bool,
+    sender: Sender<Update>,
+    receiver: Receiver<Update>,
+}
+
+impl Git {
+    // Repositories holding the scan roots and the managed repo, plus the
+    // configured or detected bare dotfiles repo
+    pub fn for_config(config: &Config) -> io::Result<Self> {
+        let bare = match &config.git_dir {
+            Some(git_dir) => Some(Repo {
+                git_dir: git_dir.clone(),
+                work_tree: match &config.work_tree {
+                    Some(work_tree) => work_tree.clone(),
+                    None => scan::home_dir()?,
+                },
+            }),
+            None => Repo::find_bare_in_home(),
+        };
+        let mut roots: Vec<&Path> = config
+            .roots
+            .iter()
+            .map(|root| root.path.as_path())
+            .collect();
+        roots.extend(config.repo.as_deref());
+        Ok(Self::discover(&roots, bare))
+    }
+
+    // Find the repositories containing `roots`, plus the bare dotfiles repo
+    pub fn discover(roots: &[&Path], bare: Option<Repo>) -> Self {
+        let mut repos: Vec<Repo> = bare.into_iter().collect();
+        for root in roots {
+            if let Some(repo) = Repo::discover(root) {
+                if !repos.contains(&repo) {
+                    repos.push(repo);
+                }
+            }
+        }
+        // Nested repos first, so ~/.config/.git wins over a bare repo for ~
+        repos.sort_by_key(|repo| std::cmp::Reverse(repo.work_tree.components().count()));
+
+        let (sender, receiver) = mpsc::channel();
+        let mut git = Self {
+            repos: repos
+                .into_iter()
+                .map(|repo| (repo, GitStatus::default()))
+                .collect(),
+            error: None,
+            ignored: HashSet::new(),
+            checked: HashSet::new(),
+            refreshing: false,
+            stale: false,
+            sender,
+            receiver,
+        };
+        git.refresh();
+        git
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.repos.is_empty()
+    }
+
+    // Run `git status` again in every repository, in the background. A
+    // refresh asked for while one runs happens once that one is done.
+    pub fn refresh(&mut self) {
+        if self.refreshing {
+            self.stale = true;
+            return;
+        }
+        self.refreshing = true;
+        let repos: Vec<Repo> = self.repos.iter().map(|(repo, _)| repo.clone()).collect();
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            let statuses = repos.iter().map(Repo::status).collect();
+            // The app has quit if nobody receives
+            let _ = sender.send(Update::Status(statuses));
+        });
+    }
+
+    // Check the shown paths git has nothing to say about against the
+    // ignore rules, in the background. Each path is checked once per refresh.
+    pub fn check_ignored(&mut self, paths: &[&Path]) {
+        if self.refreshing {
+            return;
+        }
+        let mut unchecked: Vec<(Repo, Vec<PathBuf>)> = Vec::new();
+        for &path in paths {
+            if !self.checked.insert(path.to_path_buf()) || self.is_ignored(path) {
+                continue;
+            }
+            let Some((repo, status)) = self.find(path) else {
+                continue;
+            };
+            if status.get(path).is_some() {
+                continue;
+            }
+            match unchecked.iter_mut().find(|(other, _)| other == repo) {
+                Some((_, paths)) => paths.push(path.to_path_buf()),
+                None => unchecked.push((repo.clone(), vec![path.to_path_buf()])),
+            }
+        }
+        if unchecked.is_empty() {
+            return;
+        }
+
+        let sender = self.sender.clone();
+        thread::spawn(move || {
+            for (repo, paths) in unchecked {
+                // Paths git fails to check are shown as before
+                let ignored = repo.ignored(&paths).unwrap_or_default();
+                if sender.send(Update::Ignored(paths, ignored)).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    // Take in what git reported since the last frame. True when a refresh finished.
+    pub fn poll(&mut self) -> bool {
+        let mut refreshed = false;
+        while let Ok(update) = self.receiver.try_recv() {
+            refreshed |= self.receive(update);
+        }
+        refreshed
+    }
+
+    // Block until the running refresh is done, for callers without a UI
+    // loop such as the command line
+    pub fn wait_for_status(&mut self) {
+        while self.refreshing {
+            let Ok(update) = self.receiver.recv() else {
+                return;
+            };
+            self.receive(update);
+        }
+    }
+
+    // True for a finished refresh
+    fn receive(&mut self, update: Update) -> bool {
+        match update {
+            Update::Status(statuses) => {
+                self.error = None;
+                for ((repo, status), fresh) in self.repos.iter_mut().zip(statuses) {
+                    match fresh {
+                        Ok(fresh) => *status = fresh,
+                        Err(e) => {
+                            *status = GitStatus::default();
+                            self.error = Some(format!(
+                                "git status in {}: {}",
+                                scan::display_path(&repo.work_tree),
+                                e
+                            ));
+                        }
+                    }
+                }
+                // Check again what is shown, as the ignore rules may have changed too
+                self.checked.clear();
+                self.refreshing = false;
+                if self.stale {
+                    self.stale = false;
+                    self.refresh();
+                }
+                true
+            }
+            Update::Ignored(checked, ignored) => {
+                for path in &checked {
+                    self.ignored.remove(path);
+                }
+                self.ignored.extend(ignored);
+                false
+            }
+        }
+    }
+
+    // The innermost repository whose work tree holds `path`
+    pub fn repo_for(&self, path: &Path) -> Option<&Repo> {
+        self.find(path).map(|(repo, _)| repo)
+    }
+
+    pub fn status(&self, path: &Path) -> Option<FileStatus> {
+        let (_, status) = self.find(path)?;
+        status.get(path).or_else(|| {
+            self.is_ignored(path).then_some(FileStatus {
+                ignored: true,
+                ..FileStatus::default()
+            })
+        })
+    }
+
+    // Whether a change to `path` can change the status: it is in a work
+    // tree, and git tracks or reports it or shows untracked files there
+    pub fn affected_by(&self, path: &Path) -> bool {
+        self.find(path)
+            .is_some_and(|(_, status)| !status.hides_untracked || status.get(path).is_some())
+    }
+
+    // Whether `path` or a directory holding it is known to be ignored
+    fn is_ignored(&self, path: &Path) -> bool {
+        path.ancestors().any(|dir| self.ignored.contains(dir))
+    }
+
+    fn find(&self, path: &Path) -> Option<&(Repo, GitStatus)> {
+        self.repos
+            .iter()
+            .find(|(repo, _)| path.starts_with(&repo.work_tree) && !path.starts_with(&repo.git_dir))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Commit {
+    pub hash: String,
+    pub date: String,
+    pub author: String,
+    pub subject: String,
+}
+
+// History of one path, shown in place of the tree
+#[derive(Debug)]
+pub struct LogView {
+    pub repo: Repo,
+    pub path: PathBuf,
+    pub commits: Vec<Commit>,
+    pub list_state: ListState,
+    // The file at the selected commit, read once per selection
+    revision: Option<(String, io::Result<String>)>,
+}
+
+impl LogView {
+    pub fn new(repo: Repo, path: PathBuf) -> io::Result<Self> {
+        let commits = repo.log(&path)?;
+        let mut list_state = ListState::default();
+        list_state.select((!commits.is_empty()).then_some(0));
+        Ok(Self {
+            repo,
+            path,
+            commits,
+            list_state,
+            revision: None,
+        })
+    }
+
+    pub fn selected(&self) -> Option<&Commit> {
+        self.commits.get(self.list_state.selected()?)
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.commits.is_empty() {
+            return;
+        }
+        let selected = self.list_state.selected().unwrap_or(0);
+        let last = self.commits.len() - 1;
+        self.list_state
+            .select(Some(selected.saturating_add_signed(delta).min(last)));
+    }
+
+    // Contents of the file at the selected commit
+    pub fn revision(&mut self) -> Option<&io::Result<String>> {
+        let hash = self.selected()?.hash.clone();
+        if self
+            .revision
+            .as_ref()
+            .is_none_or(|(shown, _)| *shown != hash)
+        {
+            let content = self.repo.show(&hash, &self.path);
+            self.revision = Some((hash, content));
+        }
+        self.revision.as_ref().map(|(_, content)| content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::{self, TestDir};
+
+    fn parse(output: &str) -> GitStatus {
+        GitStatus::parse(Path::new("/home/u"), output.as_bytes())
+    }
+
+    fn flags(untracked: bool, ignored: bool) -> FileStatus {
+        FileStatus {
+            untracked,
+            ignored,
+            ..FileStatus::default()
+        }
+    }
+
+    #[test]
+    fn parses_staged_and_modified_codes() {
+        let status = parse("M  .bashrc\0 M .vimrc\0MM .zshrc\0");
+        let get = |name: &str| status.get(&Path::new("/home/u").join(name)).unwrap();
+        assert!(get(".bashrc").staged && !get(".bashrc").modified);
+        assert!(!get(".vimrc").staged && get(".vimrc").modified);
+        assert!(get(".zshrc").staged && get(".zshrc").modified);
+    }
+
+    #[test]
+    fn rename_uses_new_path_and_skips_the_original() {
+        let status = parse("R  .config/new.conf\0.config/old.conf\0 M .vimrc\0");
+        let renamed = status.get(Path::new("/home/u/.config/new.conf")).unwrap();
+        assert!(renamed.staged);
+        assert_eq!(status.get(Path::new("/home/u/.config/old.conf")), None);
+        // The entry after the original path is still read
+        assert!(status.get(Path::new("/home/u/.vimrc")).unwrap().modified);
+    }
+
+    #[test]
+    fn copy_is_followed_by_its_source_too() {
+        let status = parse("C  b.conf\0a.conf\0?? c.conf\0");
+        assert!(status.get(Path::new("/home/u/b.conf")).unwrap().staged);
+        assert_eq!(status.get(Path::new("/home/u/a.conf")), None);
+        assert_eq!(
+            status.get(Path::new("/home/u/c.conf")),
+            Some(flags(true, false))
+        );
+    }
+
+    #[test]
+    fn untracked_and_ignored_directories_cover_their_contents() {
+        let status = parse("?? .config/nvim/\0!! .cache/\0!! .npmrc\0");
+        let untracked = Some(flags(true, false));
+        let ignored = Some(flags(false, true));
+        assert_eq!(status.get(Path::new("/home/u/.config/nvim")), untracked);
+        assert_eq!(
+            status.get(Path::new("/home/u/.config/nvim/init.lua")),
+            untracked
+        );
+        assert_eq!(status.get(Path::new("/home/u/.cache/x/y")), ignored);
+        assert_eq!(status.get(Path::new("/home/u/.npmrc")), ignored);
+    }
+
+    #[test]
+    fn directories_combine_changes_below_them_except_ignored() {
+        let status = parse("?? .config/nvim/\0 M .config/git/config\0!! .config/x.log\0");
+        let config = status.get(Path::new("/home/u/.config")).unwrap();
+        assert!(config.untracked && config.modified && !config.ignored);
+        let home = status.get(Path::new("/home/u")).unwrap();
+        assert!(home.untracked && home.modified);
+        assert_eq!(status.get(Path::new("/home")), None);
+    }
+
+    #[test]
+    fn conflicts_and_short_entries() {
+        let status = parse("UU a\0AA b\0DD c\0\0x\0");
+        for name in ["a", "b", "c"] {
+            let conflicted = status.get(&Path::new("/home/u").join(name)).unwrap();
+            assert!(conflicted.conflicted && !conflicted.staged);
+        }
+        assert_eq!(status.get(Path::new("/home/u/x")), None);
+    }
+
+    #[test]
+    fn tracked_paths_are_clean_and_others_unknown() {
+        let mut status = parse("");
+        status.tracked.insert(PathBuf::from("/home/u/.bashrc"));
+        assert_eq!(
+            status.get(Path::new("/home/u/.bashrc")),
+            Some(FileStatus::default())
+        );
+        assert_eq!(status.get(Path::new("/home/u/.profile")), None);
+    }
+
+    #[test]
+    fn new_untracked_files_change_the_status() {
+        let dir = TestDir::new("git-untracked");
+        let repo = dir.mkdir("repo");
+        testdir::git(&repo, &["init", "--quiet"]);
+        let tracked = dir.write("repo/a/tracked", "x");
+        testdir::git(&repo, &["add", "."]);
+        testdir::git(&repo, &["commit", "--quiet", "--message", "initial"]);
+        let mut git = Git::discover(&[repo.as_path()], None);
+        git.wait_for_status();
+
+        let created = dir.write("repo/a/new", "y");
+        assert!(git.affected_by(&created));
+        assert!(!git.affected_by(&dir.write("outside", "z")));
+        git.refresh();
+        git.wait_for_status();
+        assert!(git.status(&created).unwrap().untracked);
+        assert!(git.status(&repo.join("a")).unwrap().untracked);
+
+        // Untracked files never show, so creating one changes nothing
+        testdir::git(&repo, &["config", "status.showUntrackedFiles", "no"]);
+        git.refresh();
+        git.wait_for_status();
+        assert!(!git.affected_by(&dir.write("repo/a/other", "z")));
+        assert!(git.affected_by(&tracked));
+    }
+}
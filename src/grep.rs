@@ -0,0 +1,158 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use ratatui::widgets::ListState;
+
+// Files larger than this are not searched
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+// Stop collecting after this many matching lines
+const MAX_RESULTS: usize = 5000;
+
+#[derive(Debug)]
+pub struct GrepMatch {
+    pub path: PathBuf,
+    // 1-based line number
+    pub line: usize,
+    // The matching line with leading whitespace removed
+    pub text: String,
+    // Byte ranges of each occurrence in `text`
+    pub ranges: Vec<(usize, usize)>,
+}
+
+// State of the content search: the query being typed, then its results
+#[derive(Debug)]
+pub struct Grep {
+    pub query: String,
+    // Still typing the query; results are from the previous search
+    pub editing: bool,
+    pub results: Vec<GrepMatch>,
+    pub list_state: ListState,
+    pub files_searched: usize,
+    pub truncated: bool,
+    // The index was still being built, so some files were not searched
+    pub incomplete: bool,
+}
+
+impl Grep {
+    pub fn new() -> Self {
+        Self {
+            query: String::new(),
+            editing: true,
+            results: Vec::new(),
+            list_state: ListState::default(),
+            files_searched: 0,
+            truncated: false,
+            incomplete: false,
+        }
+    }
+
+    pub fn run(&mut self, paths: &[PathBuf], incomplete: bool) {
+        self.editing = false;
+        self.incomplete = incomplete;
+        let (results, files_searched) = search(paths, &self.query);
+        self.truncated = results.len() >= MAX_RESULTS;
+        self.results = results;
+        self.files_searched = files_searched;
+        self.list_state
+            .select((!self.results.is_empty()).then_some(0));
+    }
+
+    pub fn selected(&self) -> Option<&GrepMatch> {
+        self.results.get(self.list_state.selected()?)
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.results.is_empty() {
+            return;
+        }
+        let selected = self.list_state.selected().unwrap_or(0);
+        let last = self.results.len() - 1;
+        self.list_state
+            .select(Some(selected.saturating_add_signed(delta).min(last)));
+    }
+}
+
+// Search the contents of every regular, non-binary file in `paths`, following
+// symlinks and searching each file once however many links lead to it.
+// Case-insensitive unless the pattern contains an uppercase letter.
+// Returns the matches and the number of files that were searched.
+pub fn search(paths: &[PathBuf], pattern: &str) -> (Vec<GrepMatch>, usize) {
+    let mut results = Vec::new();
+    let mut files_searched = 0;
+    if pattern.is_empty() {
+        return (results, files_searched);
+    }
+    let ignore_case = !pattern.chars().any(char::is_uppercase);
+    let mut seen = HashSet::new();
+
+    for path in paths {
+        let Ok(canonical) = fs::canonicalize(path) else {
+            continue;
+        };
+        if !seen.insert(canonical) {
+            continue;
+        }
+        let Some(content) = read_text(path) else {
+            continue;
+        };
+        files_searched += 1;
+
+        for (i, line) in content.lines().enumerate() {
+            let text = line.trim_start();
+            let ranges = find_all(text, pattern, ignore_case);
+            if ranges.is_empty() {
+                continue;
+            }
+            results.push(GrepMatch {
+                path: path.clone(),
+                line: i + 1,
+                text: text.to_string(),
+                ranges,
+            });
+            if results.len() >= MAX_RESULTS {
+                return (results, files_searched);
+            }
+        }
+    }
+
+    (results, files_searched)
+}
+
+// Contents of a small text file; None for directories, large files and binaries
+fn read_text(path: &Path) -> Option<String> {
+    // Managed dotfiles are symlinks into the repo
+    let metadata = fs::metadata(path).ok()?;
+    if !metadata.is_file() || metadata.len() > MAX_FILE_SIZE {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(metadata.len() as usize);
+    fs::File::open(path).ok()?.read_to_end(&mut bytes).ok()?;
+    // A NUL byte near the start is a reliable sign of a binary file
+    if bytes.iter().take(8192).any(|&b| b == 0) {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+// Byte ranges of every non-overlapping occurrence of `needle`.
+// Only ASCII is folded when ignoring case, so offsets stay valid.
+pub fn find_all(haystack: &str, needle: &str, ignore_case: bool) -> Vec<(usize, usize)> {
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    let (haystack, needle) = if ignore_case {
+        (haystack.to_ascii_lowercase(), needle.to_ascii_lowercase())
+    } else {
+        (haystack.to_string(), needle.to_string())
+    };
+
+    haystack
+        .match_indices(&needle)
+        .map(|(start, found)| (start, start + found.len()))
+        .collect()
+}
@@ -0,0 +1,577 @@
+use std::path::Path;
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Shell,
+    Toml,
+    Yaml,
+    Json,
+    Lua,
+    Ini,
+    Plain,
+}
+
+impl Language {
+    // Guess the language from the file name, then the extension, then a shebang
+    pub fn detect(path: &Path, content: &str) -> Self {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let parent = path
+            .parent()
+            .and_then(|parent| parent.file_name())
+            .map(|name| name.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+
+        let by_name = match name.as_str() {
+            ".bashrc" | ".bash_profile" | ".bash_login" | ".bash_logout" | ".bash_aliases"
+            | ".profile" | ".zshrc" | ".zshenv" | ".zprofile" | ".zlogin" | ".zlogout"
+            | ".xinitrc" | ".xprofile" | ".xsession" | ".envrc" | ".aliases" | ".functions"
+            | ".exports" => Some(Self::Shell),
+            ".gitconfig" | ".gitmodules" | ".editorconfig" | ".npmrc" | ".pypirc" | ".wgetrc"
+            | ".mbsyncrc" => Some(Self::Ini),
+            ".clang-format" | ".clang-tidy" | ".yamllint" => Some(Self::Yaml),
+            // ~/.config/git/config and .git/config use INI syntax
+            "config" if parent == "git" || parent == ".git" => Some(Self::Ini),
+            _ => None,
+        };
+        if let Some(language) = by_name {
+            return language;
+        }
+
+        let extension = name.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+        match extension {
+            "sh" | "bash" | "zsh" | "ksh" | "fish" => return Self::Shell,
+            "toml" => return Self::Toml,
+            "yaml" | "yml" => return Self::Yaml,
+            "json" | "jsonc" | "json5" => return Self::Json,
+            "lua" => return Self::Lua,
+            "ini" | "conf" | "cfg" | "desktop" | "service" | "timer" | "mount" => return Self::Ini,
+            _ => {}
+        }
+
+        let first_line = content.lines().next().unwrap_or("");
+        if let Some(shebang) = first_line.strip_prefix("#!") {
+            // "#!/usr/bin/env bash" and "#!/bin/sh -e" both name the interpreter
+            let interpreter = shebang
+                .split_whitespace()
+                .map(|word| word.rsplit('/').next().unwrap_or(word))
+                .find(|word| *word != "env" && !word.starts_with('-'))
+                .unwrap_or("");
+            match interpreter {
+                "sh" | "bash" | "zsh" | "dash" | "ksh" | "fish" => return Self::Shell,
+                "lua" | "luajit" => return Self::Lua,
+                _ => {}
+            }
+        }
+
+        Self::Plain
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Shell => "Shell",
+            Self::Toml => "TOML",
+            Self::Yaml => "YAML",
+            Self::Json => "JSON",
+            Self::Lua => "Lua",
+            Self::Ini => "INI",
+            Self::Plain => "Plain text",
+        }
+    }
+
+    fn syntax(self) -> Option<&'static Syntax> {
+        match self {
+            Self::Shell => Some(&SHELL),
+            Self::Toml => Some(&TOML),
+            Self::Yaml => Some(&YAML),
+            Self::Json => Some(&JSON),
+            Self::Lua => Some(&LUA),
+            Self::Ini => Some(&INI),
+            Self::Plain => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Plain,
+    Comment,
+    String,
+    Number,
+    Keyword,
+    Key,
+    Section,
+    Variable,
+    Punctuation,
+}
+
+// Styles for each kind of token
+#[derive(Debug, Clone)]
+pub struct SyntaxTheme {
+    pub plain: Style,
+    pub comment: Style,
+    pub string: Style,
+    pub number: Style,
+    pub keyword: Style,
+    pub key: Style,
+    pub section: Style,
+    pub variable: Style,
+    pub punctuation: Style,
+}
+
+impl Default for SyntaxTheme {
+    fn default() -> Self {
+        Self {
+            plain: Style::default(),
+            comment: Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::ITALIC),
+            string: Style::default().fg(Color::Green),
+            number: Style::default().fg(Color::Magenta),
+            keyword: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            key: Style::default().fg(Color::Cyan),
+            section: Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+            variable: Style::default().fg(Color::Red),
+            punctuation: Style::default().fg(Color::Gray),
+        }
+    }
+}
+
+impl SyntaxTheme {
+    // Built-in themes by name
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::default()),
+            "monochrome" => Some(Self {
+                plain: Style::default(),
+                comment: Style::default().add_modifier(Modifier::DIM | Modifier::ITALIC),
+                string: Style::default().add_modifier(Modifier::ITALIC),
+                number: Style::default(),
+                keyword: Style::default().add_modifier(Modifier::BOLD),
+                key: Style::default().add_modifier(Modifier::BOLD),
+                section: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                variable: Style::default().add_modifier(Modifier::UNDERLINED),
+                punctuation: Style::default().add_modifier(Modifier::DIM),
+            }),
+            _ => None,
+        }
+    }
+
+    // Every style by name for theme files, e.g. "syntax.comment"
+    pub fn styles_mut(&mut self) -> [(&'static str, &mut Style); 9] {
+        [
+            ("syntax.plain", &mut self.plain),
+            ("syntax.comment", &mut self.comment),
+            ("syntax.string", &mut self.string),
+            ("syntax.number", &mut self.number),
+            ("syntax.keyword", &mut self.keyword),
+            ("syntax.key", &mut self.key),
+            ("syntax.section", &mut self.section),
+            ("syntax.variable", &mut self.variable),
+            ("syntax.punctuation", &mut self.punctuation),
+        ]
+    }
+
+    fn style(&self, token: Token) -> Style {
+        match token {
+            Token::Plain => self.plain,
+            Token::Comment => self.comment,
+            Token::String => self.string,
+            Token::Number => self.number,
+            Token::Keyword => self.keyword,
+            Token::Key => self.key,
+            Token::Section => self.section,
+            Token::Variable => self.variable,
+            Token::Punctuation => self.punctuation,
+        }
+    }
+}
+
+// What the generic line lexer needs to know about a language
+struct Syntax {
+    line_comments: &'static [&'static str],
+    // '#' only starts a comment at the start of a word (shell, YAML)
+    comment_after_space: bool,
+    // Multi-line comments and strings: (open, close)
+    block_comment: Option<(&'static str, &'static str)>,
+    long_string: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    keywords: &'static [&'static str],
+    punctuation: &'static str,
+    // "[section]" header lines
+    sections: bool,
+    // Separator after a key at the start of a line, e.g. '=' or ':'
+    key_separator: Option<char>,
+    // Strings followed by ':' are object keys
+    string_keys: bool,
+    // $VAR, ${VAR} and NAME=value assignments
+    variables: bool,
+}
+
+const SHELL: Syntax = Syntax {
+    line_comments: &["#"],
+    comment_after_space: true,
+    block_comment: None,
+    long_string: None,
+    quotes: &['"', '\'', '`'],
+    keywords: &[
+        "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case", "esac",
+        "in", "function", "return", "export", "alias", "local", "source", "unset", "readonly",
+        "declare", "typeset", "eval", "exec", "set", "shift", "break", "continue",
+    ],
+    punctuation: "|&;<>(){}[]=",
+    sections: false,
+    key_separator: None,
+    string_keys: false,
+    variables: true,
+};
+
+const TOML: Syntax = Syntax {
+    line_comments: &["#"],
+    comment_after_space: false,
+    block_comment: None,
+    long_string: Some(("\"\"\"", "\"\"\"")),
+    quotes: &['"', '\''],
+    keywords: &["true", "false", "inf", "nan"],
+    punctuation: "=[]{},",
+    sections: true,
+    key_separator: Some('='),
+    string_keys: false,
+    variables: false,
+};
+
+const YAML: Syntax = Syntax {
+    line_comments: &["#"],
+    comment_after_space: true,
+    block_comment: None,
+    long_string: None,
+    quotes: &['"', '\''],
+    keywords: &["true", "false", "yes", "no", "on", "off", "null"],
+    punctuation: "-:{}[],|>&*~",
+    sections: false,
+    key_separator: Some(':'),
+    string_keys: false,
+    variables: false,
+};
+
+const JSON: Syntax = Syntax {
+    line_comments: &["//"],
+    comment_after_space: false,
+    block_comment: Some(("/*", "*/")),
+    long_string: None,
+    quotes: &['"'],
+    keywords: &["true", "false", "null"],
+    punctuation: "{}[],:",
+    sections: false,
+    key_separator: None,
+    string_keys: true,
+    variables: false,
+};
+
+const LUA: Syntax = Syntax {
+    line_comments: &["--"],
+    comment_after_space: false,
+    block_comment: Some(("--[[", "]]")),
+    long_string: Some(("[[", "]]")),
+    quotes: &['"', '\''],
+    keywords: &[
+        "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if",
+        "in", "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+    ],
+    punctuation: "=(){}[],;.+-*/<>~#",
+    sections: false,
+    key_separator: None,
+    string_keys: false,
+    variables: false,
+};
+
+const INI: Syntax = Syntax {
+    line_comments: &["#", ";"],
+    comment_after_space: true,
+    block_comment: None,
+    long_string: None,
+    quotes: &['"'],
+    keywords: &["true", "false", "yes", "no", "on", "off"],
+    punctuation: "=[]",
+    sections: true,
+    key_separator: Some('='),
+    string_keys: false,
+    variables: false,
+};
+
+// Comment or string that is still open at the end of a line
+#[derive(Debug, Clone, Copy)]
+struct OpenBlock {
+    close: &'static str,
+    token: Token,
+}
+
+// Turn file contents into styled lines. Unknown languages come back as plain text.
+pub fn highlight(content: &str, language: Language, theme: &SyntaxTheme) -> Vec<Line<'static>> {
+    let mut open = None;
+    content
+        .lines()
+        .map(|line| {
+            // Tabs would otherwise render with zero width
+            let line = line.replace('\t', "    ");
+            match language.syntax() {
+                Some(syntax) => highlight_line(&line, syntax, &mut open, theme),
+                None => Line::from(Span::styled(line, theme.plain)),
+            }
+        })
+        .collect()
+}
+
+struct LineBuilder<'t> {
+    spans: Vec<Span<'static>>,
+    plain: String,
+    theme: &'t SyntaxTheme,
+}
+
+impl LineBuilder<'_> {
+    fn push(&mut self, text: &str, token: Token) {
+        if text.is_empty() {
+            return;
+        }
+        if token == Token::Plain {
+            self.plain.push_str(text);
+            return;
+        }
+        self.flush();
+        self.spans
+            .push(Span::styled(text.to_string(), self.theme.style(token)));
+    }
+
+    fn flush(&mut self) {
+        if !self.plain.is_empty() {
+            let plain = std::mem::take(&mut self.plain);
+            self.spans.push(Span::styled(plain, self.theme.plain));
+        }
+    }
+
+    fn finish(mut self) -> Line<'static> {
+        self.flush();
+        Line::from(self.spans)
+    }
+}
+
+fn highlight_line(
+    line: &str,
+    syntax: &Syntax,
+    open: &mut Option<OpenBlock>,
+    theme: &SyntaxTheme,
+) -> Line<'static> {
+    let mut out = LineBuilder {
+        spans: Vec::new(),
+        plain: String::new(),
+        theme,
+    };
+    let mut i = 0;
+
+    // Finish a comment or string carried over from the previous line
+    if let Some(block) = *open {
+        match line.find(block.close) {
+            Some(end) => {
+                i = end + block.close.len();
+                out.push(&line[..i], block.token);
+                *open = None;
+            }
+            None => {
+                out.push(line, block.token);
+                return out.finish();
+            }
+        }
+    }
+
+    if i == 0 {
+        i = line_start(line, syntax, &mut out);
+    }
+
+    while i < line.len() {
+        let rest = &line[i..];
+        let at_word_start = line[..i]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace);
+
+        // Block comments and long strings, which may run past this line
+        let block = [
+            syntax.block_comment.map(|b| (b, Token::Comment)),
+            syntax.long_string.map(|b| (b, Token::String)),
+        ]
+        .into_iter()
+        .flatten()
+        .find(|((start, _), _)| rest.starts_with(start));
+        if let Some(((start, close), token)) = block {
+            match rest[start.len()..].find(close) {
+                Some(end) => {
+                    let end = start.len() + end + close.len();
+                    out.push(&rest[..end], token);
+                    i += end;
+                }
+                None => {
+                    out.push(rest, token);
+                    *open = Some(OpenBlock { close, token });
+                    break;
+                }
+            }
+            continue;
+        }
+
+        let is_comment = syntax
+            .line_comments
+            .iter()
+            .any(|prefix| rest.starts_with(prefix))
+            && (!syntax.comment_after_space || at_word_start);
+        if is_comment {
+            out.push(rest, Token::Comment);
+            break;
+        }
+
+        let c = rest.chars().next().unwrap_or_default();
+
+        if syntax.quotes.contains(&c) {
+            let end = string_end(rest, c);
+            let is_key = syntax.string_keys && rest[end..].trim_start().starts_with(':');
+            out.push(
+                &rest[..end],
+                if is_key { Token::Key } else { Token::String },
+            );
+            i += end;
+            continue;
+        }
+
+        if syntax.variables && c == '$' {
+            let end = variable_end(rest);
+            out.push(&rest[..end], Token::Variable);
+            i += end;
+            continue;
+        }
+
+        if c.is_ascii_digit() || (c == '-' && next_is_digit(rest)) {
+            let end = 1 + rest[1..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '_'))
+                .unwrap_or(rest.len() - 1);
+            out.push(&rest[..end], Token::Number);
+            i += end;
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            let token = if syntax.keywords.contains(&word) {
+                Token::Keyword
+            } else if syntax.variables && rest[end..].starts_with('=') {
+                Token::Key
+            } else {
+                Token::Plain
+            };
+            out.push(word, token);
+            i += end;
+            continue;
+        }
+
+        let token = if syntax.punctuation.contains(c) {
+            Token::Punctuation
+        } else {
+            Token::Plain
+        };
+        out.push(&rest[..c.len_utf8()], token);
+        i += c.len_utf8();
+    }
+
+    out.finish()
+}
+
+// Section headers and "key =" prefixes. Returns where normal lexing resumes.
+fn line_start(line: &str, syntax: &Syntax, out: &mut LineBuilder) -> usize {
+    let body = line.trim_start();
+    let indent = line.len() - body.len();
+
+    if syntax.sections && body.starts_with('[') {
+        if let Some(end) = body.rfind(']') {
+            out.push(&line[..indent], Token::Plain);
+            out.push(&body[..=end], Token::Section);
+            return indent + end + 1;
+        }
+    }
+
+    let Some(separator) = syntax.key_separator else {
+        return 0;
+    };
+    // YAML list items can start with a key too: "- name: value"
+    let (dash, body) = match body.strip_prefix("- ") {
+        Some(after) if separator == ':' => (2, after),
+        _ => (0, body),
+    };
+    let Some(end) = body.find(separator) else {
+        return 0;
+    };
+    let key = &body[..end];
+    let after = &body[end + 1..];
+    let valid = !key.trim().is_empty()
+        && !syntax
+            .line_comments
+            .iter()
+            .any(|prefix| key.contains(prefix))
+        && !key.starts_with(syntax.quotes)
+        && (separator != ':' || after.is_empty() || after.starts_with(' '));
+    if !valid {
+        return 0;
+    }
+
+    out.push(&line[..indent], Token::Plain);
+    out.push(&line[indent..indent + dash], Token::Punctuation);
+    out.push(key, Token::Key);
+    indent + dash + end
+}
+
+// Byte length of a quoted string including both quotes, or the rest of the line
+fn string_end(rest: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in rest.char_indices().skip(1) {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' && quote != '\'' {
+            escaped = true;
+        } else if c == quote {
+            return i + c.len_utf8();
+        }
+    }
+    rest.len()
+}
+
+// Byte length of $NAME, ${...} or a special parameter like $? or $1
+fn variable_end(rest: &str) -> usize {
+    let after = &rest[1..];
+    if after.starts_with('{') {
+        return after.find('}').map_or(rest.len(), |end| end + 2);
+    }
+    match after.chars().next() {
+        Some(c) if c.is_alphabetic() || c == '_' => {
+            1 + after
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(after.len())
+        }
+        Some(c) if c.is_ascii_digit() || "?!#@*$-".contains(c) => 2,
+        _ => 1,
+    }
+}
+
+fn next_is_digit(rest: &str) -> bool {
+    rest[1..].starts_with(|c: char| c.is_ascii_digit())
+}
@@ -0,0 +1,107 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use ignore::{
+    gitignore::{Gitignore, GitignoreBuilder},
+    Match,
+};
+
+// Ignore files read in every directory. Later files take precedence.
+const IGNORE_FILES: [&str; 3] = [".gitignore", ".ignore", ".dotfilesignore"];
+
+// Directory names that hold caches, browser profiles and other application
+// state rather than configuration
+const DEFAULT_EXCLUDES: &[&str] = &[
+    ".cache",
+    ".git",
+    ".hg",
+    ".svn",
+    "node_modules",
+    "__pycache__",
+    "Cache",
+    "cache",
+    "Caches",
+    "CachedData",
+    "CachedExtensionVSIXs",
+    "Code Cache",
+    "GPUCache",
+    "DawnCache",
+    "DawnGraphiteCache",
+    "DawnWebGPUCache",
+    "GrShaderCache",
+    "ShaderCache",
+    "Service Worker",
+    "IndexedDB",
+    "Local Storage",
+    "Session Storage",
+    "blob_storage",
+    "Crashpad",
+    "Crash Reports",
+    "logs",
+    "google-chrome",
+    "google-chrome-beta",
+    "chromium",
+    "BraveSoftware",
+    "microsoft-edge",
+    "vivaldi",
+];
+
+pub fn is_default_exclude(name: &str) -> bool {
+    DEFAULT_EXCLUDES.contains(&name)
+}
+
+// Parsed ignore files, cached per directory
+#[derive(Debug, Default)]
+pub struct IgnoreFiles {
+    cache: Mutex<HashMap<PathBuf, Option<Gitignore>>>,
+}
+
+impl IgnoreFiles {
+    // Whether an ignore file in `root` or a directory between it and `path`
+    // excludes `path`. The deepest file with a matching rule decides.
+    pub fn is_ignored(&self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+
+        for dir in path.ancestors().skip(1) {
+            if !dir.starts_with(root) {
+                break;
+            }
+            let rules = cache.entry(dir.to_path_buf()).or_insert_with(|| load(dir));
+            match rules.as_ref().map(|rules| rules.matched(path, is_dir)) {
+                Some(Match::Ignore(_)) => return true,
+                Some(Match::Whitelist(_)) => return false,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    // Drop the cached rules of `dir` so they are read again on next use
+    pub fn forget(&self, dir: &Path) {
+        self.cache
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(dir);
+    }
+}
+
+// Rules from the ignore files in `dir`, or None if it has none
+fn load(dir: &Path) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(dir);
+    let mut found = false;
+    for name in IGNORE_FILES {
+        let file = dir.join(name);
+        if file.is_file() {
+            // Unreadable files and bad lines are skipped, as git does
+            let _ = builder.add(file);
+            found = true;
+        }
+    }
+    if !found {
+        return None;
+    }
+    builder.build().ok()
+}
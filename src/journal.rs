@@ -0,0 +1,822 @@
+use std::{
+    fmt, fs,
+    io::{self, Error, ErrorKind},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use ratatui::widgets::ListState;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backup::{self, Entry, LiveState, Store},
+    config,
+    fileops::{self, FileOp},
+    links::{self, Operation},
+    metadata, scan,
+};
+
+// Older records are dropped when the journal grows past this
+const MAX_RECORDS: usize = 200;
+
+// One change as it was applied, with what is needed to take it back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Step {
+    // A file or directory that did not exist before
+    Create {
+        path: PathBuf,
+        dir: bool,
+    },
+    // A rename, or a HOME file adopted into the repo over `replaced`, which
+    // is kept in a snapshot
+    Move {
+        from: PathBuf,
+        to: PathBuf,
+        replaced: Option<Entry>,
+    },
+    Copy {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    // A delete, into the trash at `trashed`
+    Trash {
+        path: PathBuf,
+        trashed: PathBuf,
+    },
+    // A link into HOME, in place of a broken link to `replaced`
+    Symlink {
+        source: PathBuf,
+        target: PathBuf,
+        replaced: Option<PathBuf>,
+    },
+    RemoveLink {
+        path: PathBuf,
+        source: PathBuf,
+    },
+    // A file put back from a snapshot over `previous`, kept in the
+    // "before restore" snapshot, or over nothing
+    Restore {
+        entry: Entry,
+        previous: Option<Entry>,
+    },
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let show = |path: &Path| scan::display_path(path);
+        match self {
+            Self::Create { path, dir: true } => write!(f, "mkdir   {}", show(path)),
+            Self::Create { path, dir: false } => write!(f, "create  {}", show(path)),
+            Self::Move { from, to, .. } => write!(f, "move    {} -> {}", show(from), show(to)),
+            Self::Copy { from, to } => write!(f, "copy    {} -> {}", show(from), show(to)),
+            Self::Trash { path, trashed } => {
+                write!(f, "trash   {} -> {}", show(path), show(trashed))
+            }
+            Self::Symlink { source, target, .. } => {
+                write!(f, "link    {} -> {}", show(target), show(source))
+            }
+            Self::RemoveLink { path, .. } => write!(f, "unlink  {}", show(path)),
+            Self::Restore { entry, .. } => write!(f, "restore {}", show(&entry.path)),
+        }
+    }
+}
+
+impl Step {
+    pub fn for_file_op(op: &FileOp) -> Self {
+        match op {
+            FileOp::CreateFile(path) => Self::Create {
+                path: path.clone(),
+                dir: false,
+            },
+            FileOp::CreateDir(path) => Self::Create {
+                path: path.clone(),
+                dir: true,
+            },
+            FileOp::Rename { from, to } => Self::Move {
+                from: from.clone(),
+                to: to.clone(),
+                replaced: None,
+            },
+            FileOp::Duplicate { from, to } => Self::Copy {
+                from: from.clone(),
+                to: to.clone(),
+            },
+            FileOp::Delete { path, trashed } => Self::Trash {
+                path: path.clone(),
+                trashed: trashed.clone(),
+            },
+        }
+    }
+
+    // Recorded before `operation` is applied, while the links it replaces
+    // can still be read. `backed_up` has the repo files adopting overwrites.
+    pub fn for_link(operation: &Operation, backed_up: &[Entry]) -> Self {
+        match operation {
+            Operation::CreateDir(path) => Self::Create {
+                path: path.clone(),
+                dir: true,
+            },
+            Operation::Symlink { source, target } => Self::Symlink {
+                source: source.clone(),
+                target: target.clone(),
+                replaced: fs::read_link(target).ok(),
+            },
+            Operation::RemoveLink(path) => Self::RemoveLink {
+                path: path.clone(),
+                source: fs::read_link(path).unwrap_or_default(),
+            },
+            Operation::Move { from, to } => Self::Move {
+                from: from.clone(),
+                to: to.clone(),
+                replaced: backed_up.iter().find(|entry| entry.path == *to).cloned(),
+            },
+        }
+    }
+
+    // Where the change shows in the list
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Create { path, .. }
+            | Self::Trash { path, .. }
+            | Self::RemoveLink { path, .. } => path,
+            Self::Move { from, .. } => from,
+            Self::Copy { to, .. } => to,
+            Self::Symlink { target, .. } => target,
+            Self::Restore { entry, .. } => &entry.path,
+        }
+    }
+
+    // Entries that appear or go away when the step is done or undone
+    pub fn changed_paths(&self) -> Vec<&Path> {
+        match self {
+            Self::Move { from, to, .. }
+            | Self::Trash {
+                path: from,
+                trashed: to,
+            } => {
+                vec![from, to]
+            }
+            step => vec![step.path()],
+        }
+    }
+
+    // The step taking this one back, as a dry run line
+    pub fn undo_line(&self) -> String {
+        let show = |path: &Path| scan::display_path(path);
+        match self {
+            Self::Create { path, .. } => format!("remove  {}", show(path)),
+            Self::Move {
+                from,
+                to,
+                replaced: None,
+            } => format!("move    {} -> {}", show(to), show(from)),
+            Self::Move { from, to, .. } => format!(
+                "move    {} -> {}, then restore the old {}",
+                show(to),
+                show(from),
+                show(to)
+            ),
+            Self::Copy { to, .. } => format!("remove  {}", show(to)),
+            Self::Trash { path, trashed } => {
+                format!("move    {} -> {}", show(trashed), show(path))
+            }
+            Self::Symlink {
+                target,
+                replaced: None,
+                ..
+            } => format!("unlink  {}", show(target)),
+            Self::Symlink {
+                target,
+                replaced: Some(old),
+                ..
+            } => format!("link    {} -> {}", show(target), show(old)),
+            Self::RemoveLink { path, source } => {
+                format!("link    {} -> {}", show(path), show(source))
+            }
+            Self::Restore {
+                entry,
+                previous: Some(_),
+            } => format!("restore {} as it was", show(&entry.path)),
+            Self::Restore { entry, .. } => format!("remove  {}", show(&entry.path)),
+        }
+    }
+
+    // Each direction first checks that nothing changed the entries since, so
+    // undoing never overwrites work done elsewhere
+    fn undo(&self, store: &Store) -> io::Result<()> {
+        match self {
+            Self::Create { path, .. } | Self::Copy { to: path, .. } => discard(path),
+            Self::Move { from, to, replaced } => {
+                fileops::refuse_existing(from)?;
+                fileops::create_parent(from)?;
+                fileops::move_path(to, from)?;
+                match replaced {
+                    Some(entry) => store.restore(entry),
+                    None => Ok(()),
+                }
+            }
+            Self::Trash { path, trashed } => fileops::untrash(trashed, path),
+            Self::Symlink {
+                source,
+                target,
+                replaced,
+            } => {
+                expect_link(target, source)?;
+                fs::remove_file(target)?;
+                match replaced {
+                    Some(old) => links::symlink(old, target),
+                    None => Ok(()),
+                }
+            }
+            Self::RemoveLink { path, source } => {
+                fileops::refuse_existing(path)?;
+                links::symlink(source, path)
+            }
+            Self::Restore { entry, previous } => {
+                expect_unchanged(store, entry)?;
+                match previous {
+                    Some(previous) => store.restore(previous),
+                    None => discard(&entry.path),
+                }
+            }
+        }
+    }
+
+    fn redo(&self, store: &Store) -> io::Result<()> {
+        match self {
+            Self::Create { path, dir: true } => FileOp::CreateDir(path.clone()).apply(),
+            Self::Create { path, dir: false } => FileOp::CreateFile(path.clone()).apply(),
+            Self::Move {
+                from,
+                to,
+                replaced: None,
+            } => FileOp::Rename {
+                from: from.clone(),
+                to: to.clone(),
+            }
+            .apply(),
+            Self::Move {
+                from,
+                to,
+                replaced: Some(entry),
+            } => {
+                expect_unchanged(store, entry)?;
+                fileops::create_parent(to)?;
+                fileops::move_path(from, to)
+            }
+            Self::Copy { from, to } => FileOp::Duplicate {
+                from: from.clone(),
+                to: to.clone(),
+            }
+            .apply(),
+            Self::Trash { path, trashed } => {
+                fileops::refuse_existing(trashed)?;
+                fileops::trash(path, trashed)
+            }
+            Self::Symlink {
+                source,
+                target,
+                replaced,
+            } => {
+                match replaced {
+                    Some(old) => expect_link(target, old)?,
+                    None => fileops::refuse_existing(target)?,
+                }
+                Operation::Symlink {
+                    source: source.clone(),
+                    target: target.clone(),
+                }
+                .apply()
+            }
+            Self::RemoveLink { path, source } => {
+                expect_link(path, source)?;
+                fs::remove_file(path)
+            }
+            Self::Restore { entry, previous } => {
+                match previous {
+                    Some(previous) => expect_unchanged(store, previous)?,
+                    None => fileops::refuse_existing(&entry.path)?,
+                }
+                store.restore(entry)
+            }
+        }
+    }
+}
+
+// Take away an entry an undo removes: symlinks, empty files and empty
+// directories are deleted, anything else is moved to the trash
+fn discard(path: &Path) -> io::Result<()> {
+    let metadata = fs::symlink_metadata(path)?;
+    if metadata.file_type().is_symlink() || (metadata.is_file() && metadata.len() == 0) {
+        return fs::remove_file(path);
+    }
+    if metadata.is_dir() && fs::read_dir(path)?.next().is_none() {
+        return fs::remove_dir(path);
+    }
+    fileops::trash(path, &fileops::trash_path(path)?)
+}
+
+fn expect_link(path: &Path, source: &Path) -> io::Result<()> {
+    if fs::read_link(path).ok().as_deref() != Some(source) {
+        return Err(changed_since(path));
+    }
+    Ok(())
+}
+
+fn expect_unchanged(store: &Store, entry: &Entry) -> io::Result<()> {
+    if store.live_state(entry) != LiveState::Unchanged {
+        return Err(changed_since(&entry.path));
+    }
+    Ok(())
+}
+
+fn changed_since(path: &Path) -> Error {
+    Error::other(format!("{} was changed since", scan::display_path(path)))
+}
+
+// Something done from the interface, e.g. a rename or linking several files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Record {
+    pub time: String,
+    // What it was, e.g. "rename" or "link"
+    pub action: String,
+    #[serde(default, rename = "step")]
+    pub steps: Vec<Step>,
+}
+
+impl Record {
+    // Steps are undone last first. If one fails, the ones already undone are
+    // done again so the record stays whole.
+    fn undo(&self, store: &Store) -> io::Result<()> {
+        for (i, step) in self.steps.iter().enumerate().rev() {
+            if let Err(e) = step.undo(store) {
+                for step in &self.steps[i + 1..] {
+                    let _ = step.redo(store);
+                }
+                return Err(Error::new(e.kind(), format!("{}: {}", step.undo_line(), e)));
+            }
+        }
+        Ok(())
+    }
+
+    fn redo(&self, store: &Store) -> io::Result<()> {
+        for (i, step) in self.steps.iter().enumerate() {
+            if let Err(e) = step.redo(store) {
+                for step in self.steps[..i].iter().rev() {
+                    let _ = step.undo(store);
+                }
+                return Err(Error::new(e.kind(), format!("{}: {}", step, e)));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Changes made from the interface, oldest first, in
+// $XDG_DATA_HOME/dotfiles-tui/journal.toml. The last `undone` records were
+// undone and can be redone until something new is recorded.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Journal {
+    pub undone: usize,
+    #[serde(rename = "record")]
+    pub records: Vec<Record>,
+}
+
+impl Journal {
+    // Read from disk each time, so several instances share one history
+    pub fn load() -> io::Result<Self> {
+        Self::load_from(&path()?)
+    }
+
+    fn load_from(path: &Path) -> io::Result<Self> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+        let mut journal: Self = toml::from_str(&text).map_err(|e| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{}: {}",
+                    scan::display_path(path),
+                    config::toml_error(&text, e)
+                ),
+            )
+        })?;
+        journal.undone = journal.undone.min(journal.records.len());
+        Ok(journal)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        self.save_to(&path()?)
+    }
+
+    fn save_to(&self, path: &Path) -> io::Result<()> {
+        let text = toml::to_string(self).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        backup::write_atomic(path, text.as_bytes())
+    }
+
+    // The record the next undo takes back
+    pub fn last_done(&self) -> Option<&Record> {
+        let done = self.records.len() - self.undone;
+        done.checked_sub(1).map(|i| &self.records[i])
+    }
+
+    // The record the next redo applies again
+    pub fn last_undone(&self) -> Option<&Record> {
+        self.records.get(self.records.len() - self.undone)
+    }
+
+    pub fn undo(&mut self) -> io::Result<Record> {
+        self.undo_with(&Store::open()?, &path()?)
+    }
+
+    pub fn redo(&mut self) -> io::Result<Record> {
+        self.redo_with(&Store::open()?, &path()?)
+    }
+
+    // Undo with the snapshots in `store`, keeping the journal at `path`
+    fn undo_with(&mut self, store: &Store, path: &Path) -> io::Result<Record> {
+        let Some(record) = self.last_done().cloned() else {
+            return Err(Error::other("nothing to undo"));
+        };
+        self.save_and_apply(path, self.undone + 1, || record.undo(store))?;
+        Ok(record)
+    }
+
+    fn redo_with(&mut self, store: &Store, path: &Path) -> io::Result<Record> {
+        let Some(record) = self.last_undone().cloned() else {
+            return Err(Error::other("nothing to redo"));
+        };
+        self.save_and_apply(path, self.undone - 1, || record.redo(store))?;
+        Ok(record)
+    }
+
+    // Save the journal with `undone` records undone, then make the change.
+    // Saving first means the files never change without the journal knowing;
+    // if the change fails, the journal is saved as it was again.
+    fn save_and_apply(
+        &mut self,
+        path: &Path,
+        undone: usize,
+        change: impl FnOnce() -> io::Result<()>,
+    ) -> io::Result<()> {
+        let previous = self.undone;
+        self.undone = undone;
+        if let Err(e) = self.save_to(path) {
+            self.undone = previous;
+            return Err(e);
+        }
+        if let Err(e) = change() {
+            self.undone = previous;
+            // The failed change is what the user needs to hear about
+            let _ = self.save_to(path);
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+// Add what was just done, dropping whatever was undone before it
+pub fn record(action: &str, steps: Vec<Step>) -> io::Result<()> {
+    if steps.is_empty() {
+        return Ok(());
+    }
+    let mut journal = Journal::load()?;
+    let done = journal.records.len() - journal.undone;
+    journal.records.truncate(done);
+    journal.undone = 0;
+    journal.records.push(Record {
+        time: metadata::format_time(SystemTime::now()),
+        action: action.to_string(),
+        steps,
+    });
+    let excess = journal.records.len().saturating_sub(MAX_RECORDS);
+    journal.records.drain(..excess);
+    journal.save()
+}
+
+fn path() -> io::Result<PathBuf> {
+    Ok(config::data_dir()?.join("journal.toml"))
+}
+
+// The journal view, newest record first
+#[derive(Debug)]
+pub struct JournalView {
+    pub journal: Journal,
+    pub list_state: ListState,
+}
+
+impl JournalView {
+    pub fn new() -> io::Result<Self> {
+        let mut view = Self {
+            journal: Journal::default(),
+            list_state: ListState::default(),
+        };
+        view.refresh()?;
+        Ok(view)
+    }
+
+    // Re-read the journal, keeping the selected row
+    pub fn refresh(&mut self) -> io::Result<()> {
+        self.journal = Journal::load()?;
+        let len = self.journal.records.len();
+        let row = self
+            .list_state
+            .selected()
+            .unwrap_or(0)
+            .min(len.saturating_sub(1));
+        self.list_state.select((len > 0).then_some(row));
+        Ok(())
+    }
+
+    // Records in display order, with whether each one was undone
+    pub fn rows(&self) -> impl Iterator<Item = (&Record, bool)> {
+        let done = self.journal.records.len() - self.journal.undone;
+        self.journal
+            .records
+            .iter()
+            .enumerate()
+            .rev()
+            .map(move |(i, record)| (record, i >= done))
+    }
+
+    pub fn selected(&self) -> Option<(&Record, bool)> {
+        self.rows().nth(self.list_state.selected()?)
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        let len = self.journal.records.len();
+        if len == 0 {
+            return;
+        }
+        let selected = self.list_state.selected().unwrap_or(0);
+        self.list_state
+            .select(Some(selected.saturating_add_signed(delta).min(len - 1)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory under the system temp dir, holding the files, the
+    // snapshot store and the journal of one test
+    struct Sandbox {
+        root: PathBuf,
+        store: Store,
+        journal_path: PathBuf,
+    }
+
+    impl Sandbox {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!(
+                "dotfiles-tui-journal-{}-{}",
+                std::process::id(),
+                name
+            ));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            Self {
+                store: Store::at(root.join("backups")),
+                journal_path: root.join("journal.toml"),
+                root,
+            }
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.root.join(name)
+        }
+
+        fn write(&self, name: &str, content: &str) -> PathBuf {
+            let path = self.path(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, content).unwrap();
+            path
+        }
+
+        fn snapshot(&self, path: &Path, label: &str) -> Entry {
+            let (snapshot, _) = self.store.snapshot(&[path.to_path_buf()], label).unwrap();
+            snapshot.entries[0].clone()
+        }
+
+        fn journal(&self, steps: Vec<Step>) -> Journal {
+            Journal {
+                undone: 0,
+                records: vec![Record {
+                    time: "now".to_string(),
+                    action: "test".to_string(),
+                    steps,
+                }],
+            }
+        }
+
+        fn undo(&self, journal: &mut Journal) -> io::Result<Record> {
+            journal.undo_with(&self.store, &self.journal_path)
+        }
+
+        fn redo(&self, journal: &mut Journal) -> io::Result<Record> {
+            journal.redo_with(&self.store, &self.journal_path)
+        }
+
+        fn saved_undone(&self) -> usize {
+            Journal::load_from(&self.journal_path).unwrap().undone
+        }
+    }
+
+    impl Drop for Sandbox {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn undo_and_redo_create() {
+        let sandbox = Sandbox::new("create");
+        let file = sandbox.path("a/new.conf");
+        let dir = sandbox.path("a/newdir");
+        FileOp::CreateFile(file.clone()).apply().unwrap();
+        FileOp::CreateDir(dir.clone()).apply().unwrap();
+        let mut journal = sandbox.journal(vec![
+            Step::for_file_op(&FileOp::CreateFile(file.clone())),
+            Step::for_file_op(&FileOp::CreateDir(dir.clone())),
+        ]);
+
+        sandbox.undo(&mut journal).unwrap();
+        assert!(!file.exists() && !dir.exists());
+        assert_eq!((journal.undone, sandbox.saved_undone()), (1, 1));
+
+        sandbox.redo(&mut journal).unwrap();
+        assert!(file.is_file() && dir.is_dir());
+        assert_eq!((journal.undone, sandbox.saved_undone()), (0, 0));
+    }
+
+    #[test]
+    fn undo_and_redo_rename() {
+        let sandbox = Sandbox::new("rename");
+        let from = sandbox.write("old.conf", "x");
+        let to = sandbox.path("sub/new.conf");
+        let op = FileOp::Rename {
+            from: from.clone(),
+            to: to.clone(),
+        };
+        op.apply().unwrap();
+        let mut journal = sandbox.journal(vec![Step::for_file_op(&op)]);
+
+        sandbox.undo(&mut journal).unwrap();
+        assert_eq!(read(&from), "x");
+        assert!(!to.exists());
+
+        sandbox.redo(&mut journal).unwrap();
+        assert_eq!(read(&to), "x");
+        assert!(!from.exists());
+    }
+
+    #[test]
+    fn undo_and_redo_trash() {
+        let sandbox = Sandbox::new("trash");
+        let path = sandbox.write("doomed.conf", "x");
+        let slot = sandbox.path("trash/1");
+        let op = FileOp::Delete {
+            path: path.clone(),
+            trashed: slot.join("doomed.conf"),
+        };
+        op.apply().unwrap();
+        assert!(!path.exists());
+        let mut journal = sandbox.journal(vec![Step::for_file_op(&op)]);
+
+        sandbox.undo(&mut journal).unwrap();
+        assert_eq!(read(&path), "x");
+        // The emptied slot and its info file are gone
+        assert!(!slot.exists());
+        assert!(!sandbox.path("trash/1.toml").exists());
+
+        sandbox.redo(&mut journal).unwrap();
+        assert!(!path.exists());
+        assert_eq!(read(&slot.join("doomed.conf")), "x");
+    }
+
+    #[test]
+    fn undo_and_redo_adopt() {
+        let sandbox = Sandbox::new("adopt");
+        let home = sandbox.write("home/.vimrc", "mine");
+        let repo = sandbox.write("repo/.vimrc", "theirs");
+        let adopt = Operation::Move {
+            from: home.clone(),
+            to: repo.clone(),
+        };
+        let link = Operation::Symlink {
+            source: repo.clone(),
+            target: home.clone(),
+        };
+        let backed_up = vec![sandbox.snapshot(&repo, "before adopt")];
+        let steps = vec![
+            Step::for_link(&adopt, &backed_up),
+            Step::for_link(&link, &backed_up),
+        ];
+        adopt.apply().unwrap();
+        link.apply().unwrap();
+        let mut journal = sandbox.journal(steps);
+
+        sandbox.undo(&mut journal).unwrap();
+        assert!(!fs::symlink_metadata(&home).unwrap().is_symlink());
+        assert_eq!(read(&home), "mine");
+        assert_eq!(read(&repo), "theirs");
+
+        sandbox.redo(&mut journal).unwrap();
+        assert_eq!(fs::read_link(&home).unwrap(), repo);
+        assert_eq!(read(&repo), "mine");
+    }
+
+    #[test]
+    fn undo_and_redo_restore() {
+        let sandbox = Sandbox::new("restore");
+        let path = sandbox.write(".zshrc", "v1");
+        let entry = sandbox.snapshot(&path, "manual");
+        fs::write(&path, "v2").unwrap();
+        let previous = sandbox.snapshot(&path, "before restore");
+        sandbox.store.restore(&entry).unwrap();
+        assert_eq!(read(&path), "v1");
+        let mut journal = sandbox.journal(vec![Step::Restore {
+            entry,
+            previous: Some(previous),
+        }]);
+
+        sandbox.undo(&mut journal).unwrap();
+        assert_eq!(read(&path), "v2");
+
+        sandbox.redo(&mut journal).unwrap();
+        assert_eq!(read(&path), "v1");
+    }
+
+    #[test]
+    fn undo_refuses_to_overwrite_later_changes() {
+        let sandbox = Sandbox::new("changed");
+        let from = sandbox.write("a", "x");
+        let to = sandbox.path("b");
+        let op = FileOp::Rename {
+            from: from.clone(),
+            to: to.clone(),
+        };
+        op.apply().unwrap();
+        let mut journal = sandbox.journal(vec![Step::for_file_op(&op)]);
+        fs::write(&from, "new").unwrap();
+
+        assert!(sandbox.undo(&mut journal).is_err());
+        assert_eq!(read(&from), "new");
+        assert_eq!(read(&to), "x");
+        assert_eq!((journal.undone, sandbox.saved_undone()), (0, 0));
+    }
+
+    #[test]
+    fn nothing_changes_when_the_journal_cannot_be_saved() {
+        let sandbox = Sandbox::new("unsaved");
+        let from = sandbox.write("a", "x");
+        let to = sandbox.path("b");
+        let op = FileOp::Rename {
+            from: from.clone(),
+            to: to.clone(),
+        };
+        op.apply().unwrap();
+        let mut journal = sandbox.journal(vec![Step::for_file_op(&op)]);
+        // The journal's directory is a file
+        let blocked = sandbox.write("blocked", "");
+
+        let result = journal.undo_with(&sandbox.store, &blocked.join("journal.toml"));
+        assert!(result.is_err());
+        assert!(!from.exists());
+        assert_eq!(read(&to), "x");
+        assert_eq!(journal.undone, 0);
+    }
+
+    #[test]
+    fn failed_step_rolls_back_the_ones_before_it() {
+        let sandbox = Sandbox::new("rollback");
+        let file = sandbox.path("new.conf");
+        let from = sandbox.write("a", "x");
+        let to = sandbox.path("b");
+        let create = FileOp::CreateFile(file.clone());
+        let rename = FileOp::Rename {
+            from: from.clone(),
+            to: to.clone(),
+        };
+        create.apply().unwrap();
+        rename.apply().unwrap();
+        let mut journal =
+            sandbox.journal(vec![Step::for_file_op(&rename), Step::for_file_op(&create)]);
+        // Undoing the rename, the second step undone, fails
+        fs::write(&from, "new").unwrap();
+
+        assert!(sandbox.undo(&mut journal).is_err());
+        assert!(file.is_file());
+        assert_eq!(read(&to), "x");
+        assert_eq!(sandbox.saved_undone(), 0);
+    }
+}
@@ -0,0 +1,872 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+// What a key press asks for, independent of the key. Views ignore actions
+// that mean nothing to them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    Quit,
+    Back,
+    Help,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+    Left,
+    Right,
+    Open,
+    Toggle,
+    SwitchPane,
+    Edit,
+    Search,
+    SearchContents,
+    Links,
+    RefreshGit,
+    Diff,
+    DiffBase,
+    NextHunk,
+    PreviousHunk,
+    Stage,
+    Unstage,
+    Commit,
+    Log,
+    Backup,
+    BackupAll,
+    Backups,
+    NewFile,
+    NewDir,
+    Rename,
+    Duplicate,
+    Delete,
+    Undo,
+    Redo,
+    Journal,
+    Restore,
+    Link,
+    Unlink,
+    Adopt,
+    LinkAll,
+    UnlinkAll,
+    Refresh,
+}
+
+// Config name and help text of every action
+const ACTIONS: &[(Action, &str, &str)] = &[
+    (Action::Quit, "quit", "Quit"),
+    (Action::Back, "back", "Close the view, cancel the scan"),
+    (Action::Help, "help", "Show the key bindings"),
+    (Action::Up, "up", "Move up"),
+    (Action::Down, "down", "Move down"),
+    (Action::PageUp, "page-up", "Move up a page"),
+    (Action::PageDown, "page-down", "Move down a page"),
+    (Action::First, "first", "Go to the first entry"),
+    (Action::Last, "last", "Go to the last entry"),
+    (Action::Left, "left", "Collapse, go back"),
+    (Action::Right, "right", "Expand, open"),
+    (Action::Open, "open", "Open the selected entry"),
+    (Action::Toggle, "toggle", "Expand or collapse a directory"),
+    (
+        Action::SwitchPane,
+        "switch-pane",
+        "Switch between the list and the preview",
+    ),
+    (Action::Edit, "edit", "Edit in $EDITOR"),
+    (Action::Search, "search", "Search file names"),
+    (
+        Action::SearchContents,
+        "search-contents",
+        "Search file contents",
+    ),
+    (Action::Links, "links", "Show the managed repo"),
+    (Action::RefreshGit, "refresh-git", "Refresh git status"),
+    (Action::Diff, "diff", "Show the git diff"),
+    (
+        Action::DiffBase,
+        "diff-base",
+        "Diff against HEAD or the index",
+    ),
+    (Action::NextHunk, "next-hunk", "Jump to the next hunk"),
+    (
+        Action::PreviousHunk,
+        "previous-hunk",
+        "Jump to the previous hunk",
+    ),
+    (Action::Stage, "stage", "Stage"),
+    (Action::Unstage, "unstage", "Unstage"),
+    (Action::Commit, "commit", "Commit what is staged"),
+    (Action::Log, "log", "Show the git log"),
+    (Action::Backup, "backup", "Back up the selection"),
+    (Action::BackupAll, "backup-all", "Back up everything"),
+    (Action::Backups, "backups", "Show the snapshots"),
+    (Action::NewFile, "new-file", "Create a file"),
+    (Action::NewDir, "new-dir", "Create a directory"),
+    (Action::Rename, "rename", "Rename or move"),
+    (Action::Duplicate, "duplicate", "Copy under a new name"),
+    (Action::Delete, "delete", "Move to the trash"),
+    (Action::Undo, "undo", "Undo the last change"),
+    (Action::Redo, "redo", "Redo the last undone change"),
+    (Action::Journal, "journal", "Show the undo journal"),
+    (Action::Restore, "restore", "Restore from the snapshot"),
+    (Action::Link, "link", "Link into HOME"),
+    (Action::Unlink, "unlink", "Remove the link from HOME"),
+    (Action::Adopt, "adopt", "Move the HOME file into the repo"),
+    (Action::LinkAll, "link-all", "Link everything"),
+    (Action::UnlinkAll, "unlink-all", "Unlink everything"),
+    (Action::Refresh, "refresh", "Re-read the view"),
+];
+
+impl Action {
+    fn named(name: &str) -> Option<Self> {
+        ACTIONS
+            .iter()
+            .find(|(_, action_name, _)| *action_name == name)
+            .map(|(action, _, _)| *action)
+    }
+
+    // As written in the config, e.g. "search-contents"
+    pub fn name(self) -> &'static str {
+        ACTIONS
+            .iter()
+            .find(|(action, _, _)| *action == self)
+            .map_or("", |(_, name, _)| name)
+    }
+
+    pub fn description(self) -> &'static str {
+        ACTIONS
+            .iter()
+            .find(|(action, _, _)| *action == self)
+            .map_or("", |(_, _, description)| description)
+    }
+}
+
+// Where a binding applies. Global bindings work in every view unless the
+// view binds the same keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Global,
+    Tree,
+    Log,
+    Backups,
+    Links,
+    Journal,
+    Grep,
+}
+
+const MODES: [(Mode, &str); 7] = [
+    (Mode::Global, "global"),
+    (Mode::Tree, "tree"),
+    (Mode::Log, "log"),
+    (Mode::Backups, "backups"),
+    (Mode::Links, "links"),
+    (Mode::Journal, "journal"),
+    (Mode::Grep, "grep"),
+];
+
+impl Mode {
+    pub fn name(self) -> &'static str {
+        MODES
+            .iter()
+            .find(|(mode, _)| *mode == self)
+            .map_or("", |(_, name)| name)
+    }
+}
+
+// One key with its modifiers. Shift is part of the character for letters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    fn new(code: KeyCode, mut modifiers: KeyModifiers) -> Self {
+        if let KeyCode::Char(_) = code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Self { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(event: KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+}
+
+const NAMED_KEYS: [(KeyCode, &str); 16] = [
+    (KeyCode::Enter, "Enter"),
+    (KeyCode::Esc, "Esc"),
+    (KeyCode::Tab, "Tab"),
+    (KeyCode::BackTab, "BackTab"),
+    (KeyCode::Backspace, "Backspace"),
+    (KeyCode::Delete, "Del"),
+    (KeyCode::Insert, "Insert"),
+    (KeyCode::Up, "Up"),
+    (KeyCode::Down, "Down"),
+    (KeyCode::Left, "Left"),
+    (KeyCode::Right, "Right"),
+    (KeyCode::PageUp, "PageUp"),
+    (KeyCode::PageDown, "PageDown"),
+    (KeyCode::Home, "Home"),
+    (KeyCode::End, "End"),
+    (KeyCode::Char(' '), "Space"),
+];
+
+// Vim notation: "j", "G", "<C-n>", "<A-v>", "<PageDown>", "<lt>" for '<'
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self.code {
+            KeyCode::Char('<') => "lt".to_string(),
+            KeyCode::F(n) => format!("F{}", n),
+            KeyCode::Char(c) if c != ' ' && self.modifiers.is_empty() => return write!(f, "{}", c),
+            KeyCode::Char(c) if c != ' ' => c.to_string(),
+            code => NAMED_KEYS
+                .iter()
+                .find(|(named, _)| *named == code)
+                .map_or("?", |(_, name)| name)
+                .to_string(),
+        };
+        let mut prefix = String::new();
+        for (modifier, letter) in [
+            (KeyModifiers::CONTROL, "C-"),
+            (KeyModifiers::ALT, "A-"),
+            (KeyModifiers::SHIFT, "S-"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                prefix.push_str(letter);
+            }
+        }
+        write!(f, "<{}{}>", prefix, name)
+    }
+}
+
+// Keys pressed one after the other, e.g. "gg" or "<C-w>j"
+pub fn parse_keys(text: &str) -> Result<Vec<Key>, String> {
+    let mut keys = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c != '<' {
+            keys.push(Key::new(KeyCode::Char(c), KeyModifiers::NONE));
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let Some(mut end) = rest.find('>') else {
+            return Err(format!("missing > in \"{}\"", text));
+        };
+        // "<A->>" is Alt and '>'
+        if rest[..end].ends_with('-') && rest[end + 1..].starts_with('>') {
+            end += 1;
+        }
+        let key =
+            parse_key(&rest[1..end]).ok_or_else(|| format!("unknown key \"{}\"", &rest[..=end]))?;
+        keys.push(key);
+        rest = &rest[end + 1..];
+    }
+    if keys.is_empty() {
+        return Err("empty key".to_string());
+    }
+    Ok(keys)
+}
+
+// The inside of "<...>": modifiers, then a character or key name
+fn parse_key(text: &str) -> Option<Key> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = text;
+    while let Some((modifier, rest)) = name.split_once('-').filter(|(_, rest)| !rest.is_empty()) {
+        modifiers |= match modifier {
+            "C" | "c" => KeyModifiers::CONTROL,
+            "A" | "a" | "M" | "m" => KeyModifiers::ALT,
+            "S" | "s" => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+        name = rest;
+    }
+
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) if modifiers.contains(KeyModifiers::SHIFT) => {
+            KeyCode::Char(c.to_ascii_uppercase())
+        }
+        (Some(c), None) => KeyCode::Char(c),
+        _ if name.eq_ignore_ascii_case("lt") => KeyCode::Char('<'),
+        _ if name.eq_ignore_ascii_case("cr") => KeyCode::Enter,
+        _ => match name.strip_prefix('F').and_then(|n| n.parse().ok()) {
+            Some(n) => KeyCode::F(n),
+            None => {
+                NAMED_KEYS
+                    .iter()
+                    .find(|(_, named)| named.eq_ignore_ascii_case(name))?
+                    .0
+            }
+        },
+    };
+    Some(Key::new(code, modifiers))
+}
+
+// An unfinished sequence is dropped when the next key takes longer than this
+const SEQUENCE_TIMEOUT: Duration = Duration::from_secs(1);
+
+// Key sequences to actions per mode, and the keys of a sequence typed so far
+// with the time of the last one
+#[derive(Debug)]
+pub struct Keymap {
+    bindings: Vec<(Mode, Vec<Key>, Action)>,
+    pending: Vec<Key>,
+    pending_since: Option<Instant>,
+}
+
+const DEFAULT: &[(Mode, &str, Action)] = &[
+    (Mode::Global, "q", Action::Quit),
+    (Mode::Global, "<Esc>", Action::Back),
+    (Mode::Global, "?", Action::Help),
+    (Mode::Global, "<Up>", Action::Up),
+    (Mode::Global, "<Down>", Action::Down),
+    (Mode::Global, "<PageUp>", Action::PageUp),
+    (Mode::Global, "<PageDown>", Action::PageDown),
+    (Mode::Global, "<Home>", Action::First),
+    (Mode::Global, "<End>", Action::Last),
+    (Mode::Global, "<Left>", Action::Left),
+    (Mode::Global, "<Right>", Action::Right),
+    (Mode::Global, "<Enter>", Action::Open),
+    (Mode::Global, "<Tab>", Action::SwitchPane),
+    (Mode::Global, "e", Action::Edit),
+    (Mode::Global, "u", Action::Undo),
+    (Mode::Global, "<C-r>", Action::Redo),
+    (Mode::Tree, "<Space>", Action::Toggle),
+    (Mode::Tree, "/", Action::Search),
+    (Mode::Tree, "s", Action::SearchContents),
+    (Mode::Tree, "m", Action::Links),
+    (Mode::Tree, "R", Action::RefreshGit),
+    (Mode::Tree, "d", Action::Diff),
+    (Mode::Tree, "D", Action::DiffBase),
+    (Mode::Tree, "]", Action::NextHunk),
+    (Mode::Tree, "[", Action::PreviousHunk),
+    (Mode::Tree, "S", Action::Stage),
+    (Mode::Tree, "U", Action::Unstage),
+    (Mode::Tree, "c", Action::Commit),
+    (Mode::Tree, "L", Action::Log),
+    (Mode::Tree, "b", Action::Backup),
+    (Mode::Tree, "B", Action::BackupAll),
+    (Mode::Tree, "v", Action::Backups),
+    (Mode::Tree, "n", Action::NewFile),
+    (Mode::Tree, "N", Action::NewDir),
+    (Mode::Tree, "r", Action::Rename),
+    (Mode::Tree, "C", Action::Duplicate),
+    (Mode::Tree, "x", Action::Delete),
+    (Mode::Tree, "<Del>", Action::Delete),
+    (Mode::Tree, "J", Action::Journal),
+    (Mode::Log, "q", Action::Back),
+    (Mode::Log, "L", Action::Back),
+    (Mode::Backups, "q", Action::Back),
+    (Mode::Backups, "r", Action::Restore),
+    (Mode::Backups, "]", Action::NextHunk),
+    (Mode::Backups, "[", Action::PreviousHunk),
+    (Mode::Links, "q", Action::Back),
+    (Mode::Links, "m", Action::Back),
+    (Mode::Links, "l", Action::Link),
+    (Mode::Links, "u", Action::Unlink),
+    (Mode::Links, "a", Action::Adopt),
+    (Mode::Links, "L", Action::LinkAll),
+    (Mode::Links, "U", Action::UnlinkAll),
+    (Mode::Links, "r", Action::Refresh),
+    (Mode::Journal, "q", Action::Back),
+    (Mode::Journal, "J", Action::Back),
+    (Mode::Grep, "q", Action::Back),
+    (Mode::Grep, "s", Action::SearchContents),
+    (Mode::Grep, "/", Action::SearchContents),
+];
+
+// Added to the default bindings by the presets
+const VIM: &[(Mode, &str, Action)] = &[
+    (Mode::Global, "j", Action::Down),
+    (Mode::Global, "k", Action::Up),
+    (Mode::Global, "h", Action::Left),
+    (Mode::Global, "l", Action::Right),
+    (Mode::Global, "gg", Action::First),
+    (Mode::Global, "G", Action::Last),
+    (Mode::Global, "<C-d>", Action::PageDown),
+    (Mode::Global, "<C-u>", Action::PageUp),
+    (Mode::Global, "<C-f>", Action::PageDown),
+    (Mode::Global, "<C-b>", Action::PageUp),
+    (Mode::Global, "<C-w>w", Action::SwitchPane),
+];
+
+const EMACS: &[(Mode, &str, Action)] = &[
+    (Mode::Global, "<C-n>", Action::Down),
+    (Mode::Global, "<C-p>", Action::Up),
+    (Mode::Global, "<C-b>", Action::Left),
+    (Mode::Global, "<C-f>", Action::Right),
+    (Mode::Global, "<C-v>", Action::PageDown),
+    (Mode::Global, "<A-v>", Action::PageUp),
+    (Mode::Global, "<A-lt>", Action::First),
+    (Mode::Global, "<A->>", Action::Last),
+    (Mode::Global, "<C-g>", Action::Back),
+    (Mode::Global, "<C-x>o", Action::SwitchPane),
+    (Mode::Global, "<C-x><C-c>", Action::Quit),
+    (Mode::Global, "<C-x>u", Action::Undo),
+    (Mode::Tree, "<C-s>", Action::Search),
+];
+
+impl Keymap {
+    // A preset ("default", "vim" or "emacs") with overrides from the config:
+    // mode name to key sequence to action name, or "none" to unbind
+    pub fn new(
+        preset: &str,
+        overrides: &HashMap<String, HashMap<String, String>>,
+    ) -> Result<Self, String> {
+        let extra = match preset {
+            "default" => &[][..],
+            "vim" => VIM,
+            "emacs" => EMACS,
+            _ => {
+                return Err(format!(
+                    "unknown keymap \"{}\" (expected \"default\", \"vim\" or \"emacs\")",
+                    preset
+                ))
+            }
+        };
+        let mut keymap = Self {
+            bindings: Vec::new(),
+            pending: Vec::new(),
+            pending_since: None,
+        };
+        for (mode, keys, action) in DEFAULT.iter().chain(extra) {
+            keymap.bind(*mode, parse_keys(keys)?, Some(*action));
+        }
+
+        // Sorted so the result does not depend on hash order
+        let mut overrides: Vec<_> = overrides.iter().collect();
+        overrides.sort_by_key(|(mode_name, _)| *mode_name);
+        for (mode_name, bindings) in overrides {
+            let Some((mode, _)) = MODES.iter().find(|(_, name)| name == mode_name) else {
+                return Err(format!("unknown mode [keys.{}]", mode_name));
+            };
+            let mut bindings: Vec<_> = bindings.iter().collect();
+            bindings.sort();
+            for (keys, action_name) in bindings {
+                let context = |e: String| format!("keys.{} \"{}\": {}", mode_name, keys, e);
+                let keys = parse_keys(keys).map_err(context)?;
+                let action = match action_name.as_str() {
+                    "none" => None,
+                    name => Some(
+                        Action::named(name)
+                            .ok_or_else(|| context(format!("unknown action \"{}\"", name)))?,
+                    ),
+                };
+                keymap.bind(*mode, keys, action);
+            }
+        }
+        Ok(keymap)
+    }
+
+    fn bind(&mut self, mode: Mode, keys: Vec<Key>, action: Option<Action>) {
+        self.bindings
+            .retain(|(bound_mode, bound_keys, _)| (*bound_mode, bound_keys) != (mode, &keys));
+        if let Some(action) = action {
+            self.bindings.push((mode, keys, action));
+        }
+    }
+
+    // Take one key press in `mode`. A complete sequence gives its action; a
+    // key that starts a longer sequence waits for the next one. A bound key
+    // always wins over longer sequences starting with it.
+    pub fn feed(&mut self, mode: Mode, event: KeyEvent) -> Option<Action> {
+        self.feed_at(mode, event, Instant::now())
+    }
+
+    // Same as feed, with the key pressed at `now`
+    pub fn feed_at(&mut self, mode: Mode, event: KeyEvent, now: Instant) -> Option<Action> {
+        self.expire(now);
+        self.pending.push(Key::from(event));
+        if let Some(action) = self.lookup(mode, &self.pending) {
+            self.reset();
+            return Some(action);
+        }
+        if self.starts_sequence(mode) {
+            self.pending_since = Some(now);
+            return None;
+        }
+        // Not bound: try the last key on its own
+        let retry = self.pending.len() > 1;
+        self.reset();
+        if retry {
+            self.feed_at(mode, event, now)
+        } else {
+            None
+        }
+    }
+
+    // Drop an unfinished sequence whose next key did not come in time
+    pub fn expire(&mut self, now: Instant) {
+        if self
+            .pending_since
+            .is_some_and(|since| now.duration_since(since) >= SEQUENCE_TIMEOUT)
+        {
+            self.reset();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.pending.clear();
+        self.pending_since = None;
+    }
+
+    fn lookup(&self, mode: Mode, keys: &[Key]) -> Option<Action> {
+        [mode, Mode::Global].iter().find_map(|lookup_mode| {
+            self.bindings
+                .iter()
+                .find(|(bound_mode, bound_keys, _)| bound_mode == lookup_mode && bound_keys == keys)
+                .map(|(_, _, action)| *action)
+        })
+    }
+
+    fn starts_sequence(&self, mode: Mode) -> bool {
+        self.bindings.iter().any(|(bound_mode, keys, _)| {
+            (*bound_mode == mode || *bound_mode == Mode::Global)
+                && keys.len() > self.pending.len()
+                && keys.starts_with(&self.pending)
+        })
+    }
+
+    // Keys of an unfinished sequence, e.g. "g"
+    pub fn pending(&self) -> String {
+        display_keys(&self.pending)
+    }
+
+    // Every key sequence for `action` that works in `mode`, e.g. ["j", "<Down>"]
+    pub fn keys_for(&self, mode: Mode, action: Action) -> Vec<String> {
+        self.bindings
+            .iter()
+            .filter(|(bound_mode, keys, bound_action)| {
+                *bound_action == action
+                    && (*bound_mode == mode
+                        || (*bound_mode == Mode::Global && self.lookup(mode, keys) == Some(action)))
+            })
+            .map(|(_, keys, _)| display_keys(keys))
+            .collect()
+    }
+
+    // Actions available in `mode` with their keys, view bindings first
+    pub fn help(&self, mode: Mode) -> Vec<(String, Action)> {
+        let mut actions: Vec<Action> = Vec::new();
+        for lookup_mode in [mode, Mode::Global] {
+            for (bound_mode, _, action) in &self.bindings {
+                if *bound_mode == lookup_mode && !actions.contains(action) {
+                    actions.push(*action);
+                }
+            }
+        }
+        actions
+            .into_iter()
+            .filter_map(|action| {
+                let keys = self.keys_for(mode, action);
+                (!keys.is_empty()).then(|| (keys.join(", "), action))
+            })
+            .collect()
+    }
+}
+
+fn display_keys(keys: &[Key]) -> String {
+    keys.iter().map(Key::to_string).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    fn char(c: char) -> KeyEvent {
+        key(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    fn ctrl(c: char) -> KeyEvent {
+        key(KeyCode::Char(c), KeyModifiers::CONTROL)
+    }
+
+    fn preset(name: &str) -> Keymap {
+        Keymap::new(name, &HashMap::new()).unwrap()
+    }
+
+    // Feed keys at `now`, returning what the last one did
+    fn type_keys(
+        keymap: &mut Keymap,
+        mode: Mode,
+        events: &[KeyEvent],
+        now: Instant,
+    ) -> Option<Action> {
+        let mut action = None;
+        for event in events {
+            action = keymap.feed_at(mode, *event, now);
+        }
+        action
+    }
+
+    #[test]
+    fn parses_characters_and_sequences() {
+        let keys = parse_keys("gg").unwrap();
+        assert_eq!(keys, vec![Key::from(char('g')); 2]);
+        let keys = parse_keys("<C-w>j").unwrap();
+        assert_eq!(keys, vec![Key::from(ctrl('w')), Key::from(char('j'))]);
+    }
+
+    #[test]
+    fn parses_names_and_modifiers() {
+        let parse_one = |text: &str| {
+            let keys = parse_keys(text).unwrap();
+            assert_eq!(keys.len(), 1, "{}", text);
+            keys[0]
+        };
+        assert_eq!(parse_one("<lt>"), Key::from(char('<')));
+        assert_eq!(
+            parse_one("<cr>"),
+            Key::new(KeyCode::Enter, KeyModifiers::NONE)
+        );
+        assert_eq!(
+            parse_one("<PageDown>"),
+            Key::new(KeyCode::PageDown, KeyModifiers::NONE)
+        );
+        assert_eq!(
+            parse_one("<F5>"),
+            Key::new(KeyCode::F(5), KeyModifiers::NONE)
+        );
+        assert_eq!(parse_one("<S-a>"), Key::from(char('A')));
+        assert_eq!(
+            parse_one("<A->>"),
+            Key::new(KeyCode::Char('>'), KeyModifiers::ALT)
+        );
+        assert_eq!(
+            parse_one("<M-v>"),
+            Key::new(KeyCode::Char('v'), KeyModifiers::ALT)
+        );
+        assert_eq!(
+            parse_one("<C-A-x>"),
+            Key::new(
+                KeyCode::Char('x'),
+                KeyModifiers::CONTROL | KeyModifiers::ALT
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_specs() {
+        assert_eq!(parse_keys(""), Err("empty key".to_string()));
+        assert_eq!(parse_keys("<C-x"), Err("missing > in \"<C-x\"".to_string()));
+        assert_eq!(
+            parse_keys("<Foo>"),
+            Err("unknown key \"<Foo>\"".to_string())
+        );
+        assert_eq!(
+            parse_keys("a<X-b>"),
+            Err("unknown key \"<X-b>\"".to_string())
+        );
+        assert!(parse_keys("<>").is_err());
+    }
+
+    #[test]
+    fn displays_in_the_notation_it_parses() {
+        for text in [
+            "gg",
+            "<C-x><C-c>",
+            "<A-lt>",
+            "<A->>",
+            "<PageUp>",
+            "<F12>",
+            "<Space>",
+        ] {
+            assert_eq!(display_keys(&parse_keys(text).unwrap()), text);
+        }
+    }
+
+    #[test]
+    fn multi_key_sequence_waits_for_the_next_key() {
+        let mut keymap = preset("vim");
+        let now = Instant::now();
+        assert_eq!(keymap.feed_at(Mode::Tree, char('g'), now), None);
+        assert_eq!(keymap.pending(), "g");
+        assert_eq!(
+            keymap.feed_at(Mode::Tree, char('g'), now),
+            Some(Action::First)
+        );
+        assert_eq!(keymap.pending(), "");
+
+        let events = [ctrl('w'), char('w')];
+        assert_eq!(
+            type_keys(&mut keymap, Mode::Tree, &events, now),
+            Some(Action::SwitchPane)
+        );
+    }
+
+    #[test]
+    fn unbound_continuation_falls_back_to_the_last_key() {
+        let mut keymap = preset("vim");
+        let now = Instant::now();
+        let events = [char('g'), char('j')];
+        assert_eq!(
+            type_keys(&mut keymap, Mode::Tree, &events, now),
+            Some(Action::Down)
+        );
+        assert_eq!(keymap.pending(), "");
+
+        let events = [char('g'), char('z')];
+        assert_eq!(type_keys(&mut keymap, Mode::Tree, &events, now), None);
+        assert_eq!(keymap.pending(), "");
+    }
+
+    #[test]
+    fn unfinished_sequence_times_out() {
+        let mut keymap = preset("vim");
+        let start = Instant::now();
+        assert_eq!(keymap.feed_at(Mode::Tree, char('g'), start), None);
+
+        // Just in time
+        let late = start + SEQUENCE_TIMEOUT - Duration::from_millis(1);
+        assert_eq!(
+            keymap.feed_at(Mode::Tree, char('g'), late),
+            Some(Action::First)
+        );
+
+        // Too late: the second g starts a new sequence
+        assert_eq!(keymap.feed_at(Mode::Tree, char('g'), start), None);
+        let later = start + SEQUENCE_TIMEOUT;
+        assert_eq!(keymap.feed_at(Mode::Tree, char('g'), later), None);
+        assert_eq!(keymap.pending(), "g");
+
+        // Without a key press
+        keymap.expire(later + SEQUENCE_TIMEOUT);
+        assert_eq!(keymap.pending(), "");
+    }
+
+    #[test]
+    fn bound_key_wins_over_longer_sequences() {
+        let overrides = HashMap::from([(
+            "tree".to_string(),
+            HashMap::from([("g".to_string(), "help".to_string())]),
+        )]);
+        let mut keymap = Keymap::new("vim", &overrides).unwrap();
+        let now = Instant::now();
+        assert_eq!(
+            keymap.feed_at(Mode::Tree, char('g'), now),
+            Some(Action::Help)
+        );
+        assert_eq!(keymap.pending(), "");
+        // Other modes still have gg
+        let events = [char('g'), char('g')];
+        assert_eq!(
+            type_keys(&mut keymap, Mode::Log, &events, now),
+            Some(Action::First)
+        );
+    }
+
+    #[test]
+    fn view_bindings_win_over_global_ones() {
+        let mut keymap = preset("default");
+        let now = Instant::now();
+        assert_eq!(
+            keymap.feed_at(Mode::Links, char('u'), now),
+            Some(Action::Unlink)
+        );
+        assert_eq!(
+            keymap.feed_at(Mode::Tree, char('u'), now),
+            Some(Action::Undo)
+        );
+        assert_eq!(
+            keymap.feed_at(Mode::Log, char('q'), now),
+            Some(Action::Back)
+        );
+        assert_eq!(
+            keymap.feed_at(Mode::Tree, char('q'), now),
+            Some(Action::Quit)
+        );
+    }
+
+    #[test]
+    fn presets_add_to_the_defaults() {
+        let now = Instant::now();
+        let mut default = preset("default");
+        assert_eq!(default.feed_at(Mode::Tree, char('j'), now), None);
+        assert_eq!(
+            default.feed_at(Mode::Tree, key(KeyCode::Down, KeyModifiers::NONE), now),
+            Some(Action::Down)
+        );
+
+        let mut vim = preset("vim");
+        assert_eq!(vim.feed_at(Mode::Tree, char('j'), now), Some(Action::Down));
+        assert_eq!(vim.feed_at(Mode::Tree, char('G'), now), Some(Action::Last));
+        assert_eq!(
+            vim.feed_at(Mode::Tree, ctrl('d'), now),
+            Some(Action::PageDown)
+        );
+
+        let mut emacs = preset("emacs");
+        assert_eq!(emacs.feed_at(Mode::Tree, char('j'), now), None);
+        assert_eq!(
+            emacs.feed_at(Mode::Tree, ctrl('n'), now),
+            Some(Action::Down)
+        );
+        let alt_lt = key(KeyCode::Char('<'), KeyModifiers::ALT);
+        assert_eq!(emacs.feed_at(Mode::Tree, alt_lt, now), Some(Action::First));
+        let events = [ctrl('x'), ctrl('c')];
+        assert_eq!(
+            type_keys(&mut emacs, Mode::Tree, &events, now),
+            Some(Action::Quit)
+        );
+        let events = [ctrl('x'), char('u')];
+        assert_eq!(
+            type_keys(&mut emacs, Mode::Tree, &events, now),
+            Some(Action::Undo)
+        );
+    }
+
+    #[test]
+    fn overrides_rebind_and_unbind() {
+        let overrides = HashMap::from([(
+            "global".to_string(),
+            HashMap::from([
+                ("q".to_string(), "none".to_string()),
+                ("<C-q>".to_string(), "quit".to_string()),
+            ]),
+        )]);
+        let mut keymap = Keymap::new("default", &overrides).unwrap();
+        let now = Instant::now();
+        assert_eq!(keymap.feed_at(Mode::Tree, char('q'), now), None);
+        assert_eq!(
+            keymap.feed_at(Mode::Tree, ctrl('q'), now),
+            Some(Action::Quit)
+        );
+        assert_eq!(keymap.keys_for(Mode::Tree, Action::Quit), vec!["<C-q>"]);
+    }
+
+    #[test]
+    fn rejects_unknown_presets_modes_and_actions() {
+        let error = |preset: &str, mode: &str, keys: &str, action: &str| {
+            let overrides = HashMap::from([(
+                mode.to_string(),
+                HashMap::from([(keys.to_string(), action.to_string())]),
+            )]);
+            Keymap::new(preset, &overrides).unwrap_err()
+        };
+        assert!(error("helix", "tree", "j", "down").starts_with("unknown keymap \"helix\""));
+        assert_eq!(
+            error("vim", "nope", "j", "down"),
+            "unknown mode [keys.nope]"
+        );
+        assert_eq!(
+            error("vim", "tree", "j", "fly"),
+            "keys.tree \"j\": unknown action \"fly\""
+        );
+        assert_eq!(
+            error("vim", "tree", "<C-", "down"),
+            "keys.tree \"<C-\": missing > in \"<C-\""
+        );
+    }
+}
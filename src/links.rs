@@ -0,0 +1,545 @@
+use std::{
+    fmt, fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+use ratatui::widgets::ListState;
+
+use crate::{
+    fileops,
+    scan::{self, ScanRoot},
+};
+
+// State of a HOME path compared with its file in the managed repo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkStatus {
+    // A symlink to the repo file
+    Linked,
+    // Nothing at the HOME path yet
+    Missing,
+    // A regular file, directory or symlink to somewhere else is in the way
+    Conflict,
+    // A symlink into the repo whose target does not exist, e.g. after the
+    // file was renamed there. Dangling links elsewhere are conflicts.
+    Broken,
+}
+
+impl LinkStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Linked => "linked",
+            Self::Missing => "missing",
+            Self::Conflict => "conflict",
+            Self::Broken => "broken",
+        }
+    }
+}
+
+// A file in the managed repo and where it belongs under HOME
+#[derive(Debug, Clone)]
+pub struct Managed {
+    pub source: PathBuf,
+    pub target: PathBuf,
+    pub status: LinkStatus,
+}
+
+// Every file in `repo`, mirrored onto `home`: repo/.config/x belongs at ~/.config/x.
+// The repo's own ignore files and the default cache excludes apply.
+pub fn scan_repo(repo: &Path, home: &Path) -> Vec<Managed> {
+    let root = ScanRoot::new(repo.to_path_buf(), None, false);
+    let mut entries: Vec<Managed> = scan::find_dotfiles(&[root])
+        .into_iter()
+        .filter(|source| !source.is_dir())
+        .filter_map(|source| {
+            let target = home.join(source.strip_prefix(repo).ok()?);
+            let status = status_of(repo, &source, &target);
+            Some(Managed {
+                source,
+                target,
+                status,
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| a.target.cmp(&b.target));
+    entries
+}
+
+pub fn status_of(repo: &Path, source: &Path, target: &Path) -> LinkStatus {
+    let Ok(metadata) = fs::symlink_metadata(target) else {
+        return LinkStatus::Missing;
+    };
+    if !metadata.file_type().is_symlink() {
+        return LinkStatus::Conflict;
+    }
+    // Following the link fails when its target is gone
+    match (fs::canonicalize(target), fs::canonicalize(source)) {
+        (Err(_), _) if points_into(target, repo) => LinkStatus::Broken,
+        (Ok(resolved), Ok(source)) if resolved == source => LinkStatus::Linked,
+        _ => LinkStatus::Conflict,
+    }
+}
+
+// Whether the symlink at `link` names a path inside `dir`, without following it
+fn points_into(link: &Path, dir: &Path) -> bool {
+    let Ok(destination) = fs::read_link(link) else {
+        return false;
+    };
+    let destination = match link.parent() {
+        Some(parent) => normalize(&parent.join(destination)),
+        None => normalize(&destination),
+    };
+    let canonical = fs::canonicalize(dir).ok();
+    destination.starts_with(dir) || canonical.is_some_and(|dir| destination.starts_with(dir))
+}
+
+// Resolve "." and ".." without touching the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normal.pop();
+            }
+            component => normal.push(component),
+        }
+    }
+    normal
+}
+
+// One filesystem change; plans are shown as a dry run before they are applied
+#[derive(Debug, Clone)]
+pub enum Operation {
+    CreateDir(PathBuf),
+    Symlink { source: PathBuf, target: PathBuf },
+    RemoveLink(PathBuf),
+    // Move a HOME file into the repo, replacing the repo's copy
+    Move { from: PathBuf, to: PathBuf },
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let show = |path: &Path| scan::display_path(path);
+        match self {
+            Self::CreateDir(path) => write!(f, "mkdir   {}", show(path)),
+            Self::Symlink { source, target } => {
+                write!(f, "link    {} -> {}", show(target), show(source))
+            }
+            Self::RemoveLink(path) => write!(f, "unlink  {}", show(path)),
+            Self::Move { from, to } => write!(f, "move    {} -> {}", show(from), show(to)),
+        }
+    }
+}
+
+impl Operation {
+    pub fn apply(&self) -> io::Result<()> {
+        match self {
+            Self::CreateDir(path) => fs::create_dir_all(path),
+            Self::Symlink { source, target } => {
+                // Replace a dangling link, never anything else
+                let is_link =
+                    fs::symlink_metadata(target).is_ok_and(|m| m.file_type().is_symlink());
+                if is_link && fs::metadata(target).is_err() {
+                    fs::remove_file(target)?;
+                }
+                symlink(source, target)
+            }
+            Self::RemoveLink(path) => {
+                if !fs::symlink_metadata(path)?.file_type().is_symlink() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("{} is not a symlink", scan::display_path(path)),
+                    ));
+                }
+                fs::remove_file(path)
+            }
+            Self::Move { from, to } => {
+                fileops::create_parent(to)?;
+                fileops::move_path(from, to)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkAction {
+    Link,
+    Unlink,
+    Adopt,
+}
+
+impl LinkAction {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Link => "link",
+            Self::Unlink => "unlink",
+            Self::Adopt => "adopt",
+        }
+    }
+}
+
+// Operations needed to apply `action` to `entries`, plus a reason for each
+// entry that has to be skipped
+pub fn plan(action: LinkAction, entries: &[&Managed]) -> (Vec<Operation>, Vec<String>) {
+    let mut operations = Vec::new();
+    let mut skipped = Vec::new();
+    let mut created: Vec<PathBuf> = Vec::new();
+
+    for entry in entries {
+        let target = scan::display_path(&entry.target);
+        match (action, entry.status) {
+            (LinkAction::Link, LinkStatus::Missing | LinkStatus::Broken) => {
+                create_parents(&entry.target, &mut created, &mut operations);
+                operations.push(Operation::Symlink {
+                    source: entry.source.clone(),
+                    target: entry.target.clone(),
+                });
+            }
+            (LinkAction::Link, LinkStatus::Linked) => {}
+            (LinkAction::Link, LinkStatus::Conflict) if entry.target.is_symlink() => {
+                skipped.push(format!("{} links somewhere else", target))
+            }
+            (LinkAction::Link, LinkStatus::Conflict) => {
+                skipped.push(format!("{} is in the way (adopt it instead)", target))
+            }
+            (LinkAction::Unlink, LinkStatus::Linked | LinkStatus::Broken) => {
+                operations.push(Operation::RemoveLink(entry.target.clone()));
+            }
+            (LinkAction::Unlink, LinkStatus::Missing) => {}
+            (LinkAction::Unlink, LinkStatus::Conflict) => {
+                skipped.push(format!("{} is not linked to the repo", target))
+            }
+            (LinkAction::Adopt, LinkStatus::Conflict) if is_file(&entry.target) => {
+                operations.push(Operation::Move {
+                    from: entry.target.clone(),
+                    to: entry.source.clone(),
+                });
+                operations.push(Operation::Symlink {
+                    source: entry.source.clone(),
+                    target: entry.target.clone(),
+                });
+            }
+            // Moving the link would put it in place of the repo's copy
+            (LinkAction::Adopt, LinkStatus::Conflict) if entry.target.is_symlink() => {
+                skipped.push(format!("{} links somewhere else", target))
+            }
+            (LinkAction::Adopt, LinkStatus::Conflict) => {
+                skipped.push(format!("{} is not a regular file", target))
+            }
+            (LinkAction::Adopt, _) => skipped.push(format!("{} has nothing to adopt", target)),
+        }
+    }
+
+    (operations, skipped)
+}
+
+// A regular file itself, not a symlink to one
+fn is_file(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_file())
+}
+
+// mkdir for each missing ancestor of `target`, once per plan
+fn create_parents(target: &Path, created: &mut Vec<PathBuf>, operations: &mut Vec<Operation>) {
+    let Some(parent) = target.parent() else {
+        return;
+    };
+    if parent.exists() || created.iter().any(|dir| dir == parent) {
+        return;
+    }
+    created.push(parent.to_path_buf());
+    operations.push(Operation::CreateDir(parent.to_path_buf()));
+}
+
+// Apply operations in order, stopping at the first failure.
+// Returns how many were applied.
+pub fn apply(operations: &[Operation]) -> Result<usize, (usize, String)> {
+    for (done, operation) in operations.iter().enumerate() {
+        if let Err(e) = operation.apply() {
+            return Err((done, format!("{}: {}", operation, e)));
+        }
+    }
+    Ok(operations.len())
+}
+
+#[cfg(unix)]
+pub fn symlink(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(source, target)
+}
+
+#[cfg(not(unix))]
+pub fn symlink(_source: &Path, _target: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "symlinks are only supported on Unix",
+    ))
+}
+
+// The managed repo view
+#[derive(Debug)]
+pub struct LinkView {
+    pub repo: PathBuf,
+    pub entries: Vec<Managed>,
+    pub list_state: ListState,
+}
+
+impl LinkView {
+    pub fn new(repo: PathBuf) -> io::Result<Self> {
+        let mut view = Self {
+            repo,
+            entries: Vec::new(),
+            list_state: ListState::default(),
+        };
+        view.refresh()?;
+        Ok(view)
+    }
+
+    // Re-read the repo and the state of every HOME path
+    pub fn refresh(&mut self) -> io::Result<()> {
+        if !self.repo.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("repo {} is not a directory", scan::display_path(&self.repo)),
+            ));
+        }
+        let selected = self.selected().map(|entry| entry.source.clone());
+        self.entries = scan_repo(&self.repo, &scan::home_dir()?);
+
+        let row = selected
+            .and_then(|source| self.entries.iter().position(|entry| entry.source == source))
+            .unwrap_or(0);
+        self.list_state
+            .select((!self.entries.is_empty()).then_some(row));
+        Ok(())
+    }
+
+    pub fn selected(&self) -> Option<&Managed> {
+        self.entries.get(self.list_state.selected()?)
+    }
+
+    pub fn move_selection(&mut self, delta: isize) {
+        if self.entries.is_empty() {
+            return;
+        }
+        let selected = self.list_state.selected().unwrap_or(0);
+        let last = self.entries.len() - 1;
+        self.list_state
+            .select(Some(selected.saturating_add_signed(delta).min(last)));
+    }
+
+    pub fn count(&self, status: LinkStatus) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.status == status)
+            .count()
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+
+    // A repo and a HOME to link it into
+    struct Setup {
+        dir: TestDir,
+        repo: PathBuf,
+        home: PathBuf,
+    }
+
+    impl Setup {
+        fn new(name: &str) -> Self {
+            let dir = TestDir::new(name);
+            let repo = dir.mkdir("repo");
+            let home = dir.mkdir("home");
+            Self { dir, repo, home }
+        }
+
+        // The repo file `name`, compared with its place in HOME
+        fn managed(&self, name: &str) -> Managed {
+            let source = self.repo.join(name);
+            let target = self.home.join(name);
+            let status = status_of(&self.repo, &source, &target);
+            Managed {
+                source,
+                target,
+                status,
+            }
+        }
+
+        fn status(&self, name: &str) -> LinkStatus {
+            self.managed(name).status
+        }
+
+        fn plan(&self, action: LinkAction, names: &[&str]) -> (Vec<Operation>, Vec<String>) {
+            let entries: Vec<Managed> = names.iter().map(|name| self.managed(name)).collect();
+            let entries: Vec<&Managed> = entries.iter().collect();
+            plan(action, &entries)
+        }
+    }
+
+    fn lines(operations: &[Operation]) -> Vec<String> {
+        operations.iter().map(Operation::to_string).collect()
+    }
+
+    #[test]
+    fn status_of_each_state() {
+        let setup = Setup::new("links-status");
+        let dir = &setup.dir;
+        for name in [
+            "linked",
+            "missing",
+            "file",
+            "elsewhere",
+            "broken",
+            "relative",
+        ] {
+            dir.write(&format!("repo/{}", name), "repo");
+        }
+        let outside = dir.write("outside", "other");
+        symlink(&setup.repo.join("linked"), &setup.home.join("linked")).unwrap();
+        dir.write("home/file", "mine");
+        symlink(&outside, &setup.home.join("elsewhere")).unwrap();
+        // Links into the repo whose file was renamed there
+        symlink(&setup.repo.join("renamed"), &setup.home.join("broken")).unwrap();
+        symlink(Path::new("../repo/renamed"), &setup.home.join("relative")).unwrap();
+        // A dangling link somewhere else belongs to someone else
+        symlink(&dir.path("gone"), &setup.home.join("dangling")).unwrap();
+        dir.write("repo/dangling", "repo");
+
+        assert_eq!(setup.status("linked"), LinkStatus::Linked);
+        assert_eq!(setup.status("missing"), LinkStatus::Missing);
+        assert_eq!(setup.status("file"), LinkStatus::Conflict);
+        assert_eq!(setup.status("elsewhere"), LinkStatus::Conflict);
+        assert_eq!(setup.status("broken"), LinkStatus::Broken);
+        assert_eq!(setup.status("relative"), LinkStatus::Broken);
+        assert_eq!(setup.status("dangling"), LinkStatus::Conflict);
+    }
+
+    #[test]
+    fn link_creates_directories_and_replaces_broken_links() {
+        let setup = Setup::new("links-link");
+        let dir = &setup.dir;
+        dir.write("repo/.config/a/x", "x");
+        dir.write("repo/.config/a/y", "y");
+        dir.write("repo/broken", "b");
+        dir.write("repo/file", "f");
+        dir.write("repo/elsewhere", "e");
+        symlink(&setup.repo.join("old"), &setup.home.join("broken")).unwrap();
+        dir.write("home/file", "mine");
+        symlink(&dir.write("outside", "o"), &setup.home.join("elsewhere")).unwrap();
+
+        let names = [".config/a/x", ".config/a/y", "broken", "file", "elsewhere"];
+        let (operations, skipped) = setup.plan(LinkAction::Link, &names);
+        let show = |path: &Path| scan::display_path(path);
+        assert_eq!(
+            lines(&operations),
+            vec![
+                format!("mkdir   {}", show(&setup.home.join(".config/a"))),
+                format!(
+                    "link    {} -> {}",
+                    show(&setup.home.join(".config/a/x")),
+                    show(&setup.repo.join(".config/a/x"))
+                ),
+                format!(
+                    "link    {} -> {}",
+                    show(&setup.home.join(".config/a/y")),
+                    show(&setup.repo.join(".config/a/y"))
+                ),
+                format!(
+                    "link    {} -> {}",
+                    show(&setup.home.join("broken")),
+                    show(&setup.repo.join("broken"))
+                ),
+            ]
+        );
+        assert_eq!(
+            skipped,
+            vec![
+                format!(
+                    "{} is in the way (adopt it instead)",
+                    show(&setup.home.join("file"))
+                ),
+                format!(
+                    "{} links somewhere else",
+                    show(&setup.home.join("elsewhere"))
+                ),
+            ]
+        );
+
+        assert_eq!(apply(&operations), Ok(operations.len()));
+        for name in [".config/a/x", ".config/a/y", "broken"] {
+            assert_eq!(setup.status(name), LinkStatus::Linked, "{}", name);
+        }
+        assert_eq!(fs::read_to_string(setup.home.join("file")).unwrap(), "mine");
+        // Linking again has nothing to do
+        assert!(setup.plan(LinkAction::Link, &names[..3]).0.is_empty());
+    }
+
+    #[test]
+    fn unlink_removes_only_links_into_the_repo() {
+        let setup = Setup::new("links-unlink");
+        let dir = &setup.dir;
+        dir.write("repo/linked", "l");
+        dir.write("repo/file", "f");
+        dir.write("repo/missing", "m");
+        symlink(&setup.repo.join("linked"), &setup.home.join("linked")).unwrap();
+        dir.write("home/file", "mine");
+
+        let (operations, skipped) = setup.plan(LinkAction::Unlink, &["linked", "file", "missing"]);
+        assert!(
+            matches!(&operations[..], [Operation::RemoveLink(path)] if *path == setup.home.join("linked"))
+        );
+        assert_eq!(skipped.len(), 1);
+        assert!(skipped[0].ends_with("file is not linked to the repo"));
+
+        assert_eq!(apply(&operations), Ok(1));
+        assert_eq!(setup.status("linked"), LinkStatus::Missing);
+        assert_eq!(fs::read_to_string(setup.repo.join("linked")).unwrap(), "l");
+        assert_eq!(fs::read_to_string(setup.home.join("file")).unwrap(), "mine");
+    }
+
+    #[test]
+    fn adopt_moves_a_regular_file_into_the_repo() {
+        let setup = Setup::new("links-adopt");
+        let dir = &setup.dir;
+        dir.write("repo/.vimrc", "repo");
+        dir.write("home/.vimrc", "mine");
+
+        let (operations, skipped) = setup.plan(LinkAction::Adopt, &[".vimrc"]);
+        assert!(skipped.is_empty());
+        assert!(matches!(
+            &operations[..],
+            [Operation::Move { .. }, Operation::Symlink { .. }]
+        ));
+        assert_eq!(apply(&operations), Ok(2));
+        assert_eq!(setup.status(".vimrc"), LinkStatus::Linked);
+        assert_eq!(
+            fs::read_to_string(setup.repo.join(".vimrc")).unwrap(),
+            "mine"
+        );
+    }
+
+    #[test]
+    fn adopt_skips_links_and_directories() {
+        let setup = Setup::new("links-adopt-link");
+        let dir = &setup.dir;
+        dir.write("repo/.vimrc", "repo");
+        dir.write("repo/.config", "repo");
+        dir.write("repo/.zshrc", "repo");
+        let outside = dir.write("outside", "other");
+        symlink(&outside, &setup.home.join(".vimrc")).unwrap();
+        dir.mkdir("home/.config");
+
+        let (operations, skipped) = setup.plan(LinkAction::Adopt, &[".vimrc", ".config", ".zshrc"]);
+        assert!(operations.is_empty());
+        assert_eq!(skipped.len(), 3);
+        assert!(skipped[0].ends_with(".vimrc links somewhere else"));
+        assert!(skipped[1].ends_with(".config is not a regular file"));
+        assert!(skipped[2].ends_with(".zshrc has nothing to adopt"));
+        // The repo's copy is untouched
+        assert_eq!(
+            fs::read_to_string(setup.repo.join(".vimrc")).unwrap(),
+            "repo"
+        );
+    }
+}
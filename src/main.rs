@@ -1,48 +1,80 @@
-use std::{
-    io::{self, stdout, Error, ErrorKind, Stdout},
-    path::PathBuf,
-};
-use walkdir::WalkDir;
+mod app;
+mod backup;
+mod cli;
+mod config;
+mod editor;
+mod export;
+mod fileops;
+mod fuzzy;
+mod git;
+mod grep;
+mod highlight;
+mod ignores;
+mod journal;
+mod keymap;
+mod links;
+mod metadata;
+mod preview;
+mod scan;
+mod sniff;
+mod state;
+#[cfg(test)]
+mod testdir;
+mod theme;
+mod tree;
+mod ui;
+mod watch;
+
+use std::io::{self, stdout, Stdout};
 
 use crossterm::{
-    event::{self, Event, KeyCode},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+        MouseButton, MouseEvent, MouseEventKind,
+    },
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
-use ratatui::{
-    layout::{Constraint, Direction, Layout},
-    prelude::{CrosstermBackend, Terminal},
-    style::{Color, Modifier, Style},
-    widgets::{Block, Borders, Clear, List, ListItem, ListState, Padding, Paragraph},
-};
-
-#[derive(Debug)]
-struct App {
-    dotfiles: Vec<PathBuf>,
-    list_state: ListState,
-}
-
-impl App {
-    fn new() -> io::Result<Self> {
-        let dotfiles = find_dotfiles()?;
+use ratatui::prelude::{CrosstermBackend, Terminal};
 
-        let mut list_state = ListState::default();
-        list_state.select(Some(0));
+use app::{App, Focus, NameKind};
+use cli::Command;
+use keymap::{Action, Mode};
+use links::LinkAction;
 
-        Ok(Self {
-            dotfiles,
-            list_state,
-        })
+fn main() -> io::Result<()> {
+    let command = match cli::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("dotfiles-tui: {}", e);
+            std::process::exit(2);
+        }
+    };
+    if command != Command::Tui {
+        let status = match cli::run(command) {
+            Ok(status) => status,
+            // Output piped into e.g. head was cut short
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => 0,
+            Err(e) => {
+                eprintln!("dotfiles-tui: {}", e);
+                1
+            }
+        };
+        std::process::exit(status);
     }
-}
 
-fn main() -> io::Result<()> {
+    // Create the app before touching the terminal so errors print normally
+    let mut app = match App::new() {
+        Ok(app) => app,
+        Err(e) => {
+            eprintln!("dotfiles-tui: {}", e);
+            std::process::exit(1);
+        }
+    };
+
     // Setup the terminal
     let mut terminal = init_terminal()?;
 
-    // Create the app
-    let mut app = App::new()?;
-
     // Main application loop
     let result = run(&mut terminal, &mut app);
 
@@ -56,127 +88,397 @@ fn main() -> io::Result<()> {
 fn init_terminal() -> io::Result<Terminal<CrosstermBackend<Stdout>>> {
     enable_raw_mode()?;
     stdout().execute(EnterAlternateScreen)?;
+    stdout().execute(EnableMouseCapture)?;
     Terminal::new(CrosstermBackend::new(stdout()))
 }
 
 // Main application loop
 fn run(terminal: &mut Terminal<CrosstermBackend<Stdout>>, app: &mut App) -> io::Result<()> {
     loop {
-        terminal.draw(|frame| {
-            let list_items: Vec<ListItem> = app
-                .dotfiles
-                .iter()
-                .map(|path| ListItem::new(path.to_string_lossy().to_string()))
-                .collect();
-
-            let list = List::new(list_items)
-                .highlight_style(
-                    Style::default()
-                        .add_modifier(Modifier::BOLD)
-                        .bg(Color::Gray),
-                )
-                .highlight_symbol(">> ")
-                .block(Block::default().title("Dotfiles").borders(Borders::ALL));
-
-            let chunks = Layout::default()
-                .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(30), Constraint::Percentage(70)].as_ref())
-                .split(frame.size());
-
-            frame.render_stateful_widget(list, chunks[0], &mut app.list_state);
-
-            let selected_path = if let Some(selected) = app.list_state.selected() {
-                app.dotfiles.get(selected)
-            } else {
-                None
-            };
-
-            let preview_content = if let Some(path) = selected_path {
-                if path.is_dir() {
-                    "This is a directory.".to_string()
-                } else {
-                    std::fs::read_to_string(path)
-                        .unwrap_or_else(|_| "Error reading file.".to_string())
-                }
-            } else {
-                "No file selected.".to_string()
-            };
-
-            let preview = Paragraph::new(preview_content).block(
-                Block::default()
-                    .title("Preview")
-                    .borders(Borders::ALL)
-                    .padding(Padding::horizontal(1)),
-            );
-
-            frame.render_widget(preview, chunks[1]);
-        })?;
-
-        // Handle input
-        if event::poll(std::time::Duration::from_millis(250))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') => break,
-                    KeyCode::Down => {
-                        if let Some(selected) = app.list_state.selected() {
-                            if selected < app.dotfiles.len() - 1 {
-                                app.list_state.select(Some(selected + 1));
-                            }
-                        }
-                    }
-                    KeyCode::Up => {
-                        if let Some(selected) = app.list_state.selected() {
-                            if selected > 0 {
-                                app.list_state.select(Some(selected - 1));
-                            }
-                        }
-                    }
-                    _ => {}
-                }
+        app.dir_sizes.poll();
+        app.poll_scan();
+        app.poll_watch();
+        app.poll_git();
+        app.keymap.expire(std::time::Instant::now());
+        terminal.draw(|frame| ui::draw(frame, app))?;
+
+        // Handle input, waking up sooner to animate the scan spinner
+        let timeout = if app.scan.is_some() { 100 } else { 250 };
+        if !event::poll(std::time::Duration::from_millis(timeout))? {
+            continue;
+        }
+        let key = match event::read()? {
+            Event::Key(key) => key,
+            Event::Mouse(mouse) => {
+                handle_mouse(app, mouse);
+                continue;
+            }
+            _ => continue,
+        };
+        app.status = None;
+        if app.confirm.is_some() {
+            match key.code {
+                KeyCode::Char('y') | KeyCode::Enter => app.accept_confirm(),
+                KeyCode::Char('n') | KeyCode::Esc | KeyCode::Char('q') => app.cancel_confirm(),
+                _ => {}
+            }
+            continue;
+        }
+        // Prompts take keys as text
+        if app.commit.is_some() {
+            handle_commit_key(app, key);
+            continue;
+        }
+        if app.name_prompt.is_some() {
+            handle_name_key(app, key);
+            continue;
+        }
+        if app.grep.as_ref().is_some_and(|grep| grep.editing) {
+            handle_grep_query_key(app, key);
+            continue;
+        }
+        if app.filter.is_some() {
+            handle_filter_key(terminal, app, key)?;
+            continue;
+        }
+
+        if app.help.is_some() {
+            if let Some(action) = app.keymap.feed(Mode::Global, key) {
+                handle_help_action(app, action);
+            }
+            continue;
+        }
+        let mode = app.mode();
+        let Some(action) = app.keymap.feed(mode, key) else {
+            continue;
+        };
+        match (action, mode) {
+            (Action::Quit, _) => break,
+            (Action::Help, _) => app.help = Some(0),
+            (Action::Undo, _) => app.plan_undo(),
+            (Action::Redo, _) => app.plan_redo(),
+            (_, Mode::Log) => handle_log_action(app, action),
+            (_, Mode::Backups) => handle_backups_action(app, action),
+            (_, Mode::Links) => handle_links_action(terminal, app, action)?,
+            (_, Mode::Journal) => handle_journal_action(app, action),
+            (_, Mode::Grep) => handle_grep_action(terminal, app, action)?,
+            _ => handle_tree_action(terminal, app, action)?,
+        }
+    }
+    Ok(())
+}
+
+// Clicks and the wheel, while no dialog or prompt has the keyboard
+fn handle_mouse(app: &mut App, mouse: MouseEvent) {
+    // Lines or rows per notch of the wheel
+    const WHEEL_STEP: isize = 3;
+
+    let prompting = app.commit.is_some()
+        || app.name_prompt.is_some()
+        || app.grep.as_ref().is_some_and(|grep| grep.editing);
+    if app.confirm.is_some() || app.help.is_some() || prompting {
+        return;
+    }
+    match mouse.kind {
+        MouseEventKind::Down(MouseButton::Left) => app.click(mouse.column, mouse.row),
+        MouseEventKind::Drag(MouseButton::Left) => app.drag(mouse.column),
+        MouseEventKind::Up(MouseButton::Left) => app.release(),
+        MouseEventKind::ScrollDown => app.wheel(mouse.column, mouse.row, WHEEL_STEP),
+        MouseEventKind::ScrollUp => app.wheel(mouse.column, mouse.row, -WHEEL_STEP),
+        _ => {}
+    }
+}
+
+// Actions in the tree, moving the selection or scrolling the preview depending on focus
+fn handle_tree_action(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    app: &mut App,
+    action: Action,
+) -> io::Result<()> {
+    let on_file = app.selected_path().is_some_and(|path| !path.is_dir());
+
+    match (action, app.focus) {
+        (Action::Back, _) => app.cancel_scan(),
+        (Action::Edit, _) => edit_selected(terminal, app)?,
+        (Action::Open, Focus::List) if on_file => edit_selected(terminal, app)?,
+        (Action::SwitchPane, _) => app.toggle_focus(),
+        (Action::Search, _) => app.start_filter(),
+        (Action::SearchContents, _) => app.start_grep(),
+        (Action::Links, _) => app.open_links(),
+        (Action::RefreshGit, _) => app.refresh_git(),
+        (Action::Diff, _) => app.toggle_diff(),
+        (Action::DiffBase, _) => app.switch_diff_base(),
+        (Action::NextHunk, _) => app.jump_to_hunk(true),
+        (Action::PreviousHunk, _) => app.jump_to_hunk(false),
+        (Action::Stage, _) => app.stage_selected(),
+        (Action::Unstage, _) => app.unstage_selected(),
+        (Action::Commit, _) => app.start_commit(),
+        (Action::Log, _) => app.open_log(),
+        (Action::Backup, _) => app.backup_selected(),
+        (Action::BackupAll, _) => app.backup_all(),
+        (Action::Backups, _) => app.open_backups(),
+        (Action::NewFile, _) => app.start_name_prompt(NameKind::NewFile),
+        (Action::NewDir, _) => app.start_name_prompt(NameKind::NewDir),
+        (Action::Rename, _) => app.start_name_prompt(NameKind::Rename),
+        (Action::Duplicate, _) => app.start_name_prompt(NameKind::Duplicate),
+        (Action::Delete, _) => app.plan_delete(),
+        (Action::Journal, _) => app.open_journal(),
+        (Action::Down, Focus::List) => app.select_next(),
+        (Action::Up, Focus::List) => app.select_previous(),
+        (Action::PageDown, Focus::List) => app.move_selection(app.list_page()),
+        (Action::PageUp, Focus::List) => app.move_selection(-app.list_page()),
+        (Action::First, Focus::List) => app.select_first(),
+        (Action::Last, Focus::List) => app.select_last(),
+        (Action::Right, Focus::List) => app.expand_selected(),
+        (Action::Left, Focus::List) => app.collapse_selected(),
+        (Action::Open | Action::Toggle, Focus::List) => app.toggle_selected(),
+        (action, Focus::Preview) => scroll_preview(app, action),
+        _ => {}
+    }
+    Ok(())
+}
+
+// Up, down, page and first/last in the focused preview
+fn scroll_preview(app: &mut App, action: Action) {
+    match action {
+        Action::Down => app.scroll_preview(1),
+        Action::Up => app.scroll_preview(-1),
+        Action::PageDown => app.scroll_preview(app.preview_page()),
+        Action::PageUp => app.scroll_preview(-app.preview_page()),
+        Action::First => app.scroll_preview(isize::MIN),
+        Action::Last => app.scroll_preview(isize::MAX),
+        _ => {}
+    }
+}
+
+// How far a movement action moves a list selection, if it is one
+fn list_delta(app: &App, action: Action) -> Option<isize> {
+    match action {
+        Action::Down => Some(1),
+        Action::Up => Some(-1),
+        Action::PageDown => Some(app.list_page()),
+        Action::PageUp => Some(-app.list_page()),
+        Action::First => Some(isize::MIN),
+        Action::Last => Some(isize::MAX),
+        _ => None,
+    }
+}
+
+// Keys while typing a "/" search
+fn handle_filter_key(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    app: &mut App,
+    key: KeyEvent,
+) -> io::Result<()> {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Esc => app.cancel_filter(),
+        KeyCode::Enter => app.accept_filter(),
+        KeyCode::Down => app.update_filter(|filter| filter.move_selection(1)),
+        KeyCode::Up => app.update_filter(|filter| filter.move_selection(-1)),
+        KeyCode::Char('n') if ctrl => app.update_filter(|filter| filter.move_selection(1)),
+        KeyCode::Char('p') if ctrl => app.update_filter(|filter| filter.move_selection(-1)),
+        KeyCode::Char('e') if ctrl => edit_selected(terminal, app)?,
+        KeyCode::Backspace => app.update_filter(|filter| filter.pop()),
+        KeyCode::Char(c) if !ctrl => app.update_filter(|filter| filter.push(c)),
+        _ => {}
+    }
+    Ok(())
+}
+
+// Keys while typing a content search
+fn handle_grep_query_key(app: &mut App, key: KeyEvent) {
+    let Some(grep) = &mut app.grep else {
+        return;
+    };
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Esc => app.cancel_grep(),
+        KeyCode::Enter => app.run_grep(),
+        KeyCode::Backspace => {
+            grep.query.pop();
+        }
+        KeyCode::Char(c) if !ctrl => grep.query.push(c),
+        _ => {}
+    }
+}
+
+// Actions while browsing content search results
+fn handle_grep_action(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    app: &mut App,
+    action: Action,
+) -> io::Result<()> {
+    if let Some(delta) = list_delta(app, action) {
+        app.move_grep_selection(delta);
+        return Ok(());
+    }
+    match action {
+        Action::Back => app.cancel_grep(),
+        Action::Open => app.accept_grep(),
+        Action::SearchContents => {
+            if let Some(grep) = &mut app.grep {
+                grep.editing = true;
+            }
+        }
+        Action::Edit => edit_selected(terminal, app)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+// Keys while typing a commit message
+fn handle_commit_key(app: &mut App, key: KeyEvent) {
+    let Some(prompt) = &mut app.commit else {
+        return;
+    };
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Esc => app.cancel_commit(),
+        KeyCode::Enter => app.accept_commit(),
+        KeyCode::Backspace => {
+            prompt.message.pop();
+        }
+        KeyCode::Char(c) if !ctrl => prompt.message.push(c),
+        _ => {}
+    }
+}
+
+// Keys while typing a file name
+fn handle_name_key(app: &mut App, key: KeyEvent) {
+    let Some(prompt) = &mut app.name_prompt else {
+        return;
+    };
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match key.code {
+        KeyCode::Esc => app.cancel_name_prompt(),
+        KeyCode::Enter => app.accept_name_prompt(),
+        KeyCode::Backspace => {
+            prompt.input.pop();
+        }
+        KeyCode::Char(c) if !ctrl => prompt.input.push(c),
+        _ => {}
+    }
+}
+
+// Actions in the key binding overlay
+fn handle_help_action(app: &mut App, action: Action) {
+    match action {
+        Action::Back | Action::Help | Action::Quit => app.help = None,
+        action => {
+            if let Some(delta) = list_delta(app, action) {
+                app.scroll_help(delta);
+            }
+        }
+    }
+}
+
+// Actions in the log of one path; the preview follows the selected commit
+fn handle_log_action(app: &mut App, action: Action) {
+    match action {
+        Action::Back => app.close_log(),
+        Action::SwitchPane => app.toggle_focus(),
+        action if app.focus == Focus::Preview => scroll_preview(app, action),
+        action => {
+            if let Some(delta) = list_delta(app, action) {
+                app.move_log_selection(delta);
+            }
+        }
+    }
+}
+
+// Actions in the snapshots view; the preview diffs the selected file against the live one
+fn handle_backups_action(app: &mut App, action: Action) {
+    match action {
+        Action::Back | Action::Left => app.close_backups(),
+        Action::SwitchPane => app.toggle_focus(),
+        Action::Open | Action::Right => app.open_snapshot(),
+        Action::Restore => app.plan_restore(),
+        Action::NextHunk => app.jump_to_hunk(true),
+        Action::PreviousHunk => app.jump_to_hunk(false),
+        action if app.focus == Focus::Preview => scroll_preview(app, action),
+        action => {
+            if let Some(delta) = list_delta(app, action) {
+                app.move_backups_selection(delta);
+            }
+        }
+    }
+}
+
+// Actions in the undo journal; undo and redo work everywhere
+fn handle_journal_action(app: &mut App, action: Action) {
+    match action {
+        Action::Back | Action::Left => app.close_journal(),
+        Action::SwitchPane => app.toggle_focus(),
+        action if app.focus == Focus::Preview => scroll_preview(app, action),
+        action => {
+            if let Some(delta) = list_delta(app, action) {
+                app.move_journal_selection(delta);
+            }
+        }
+    }
+}
+
+// Actions in the managed repo view
+fn handle_links_action(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    app: &mut App,
+    action: Action,
+) -> io::Result<()> {
+    if let Some(delta) = list_delta(app, action) {
+        app.move_links_selection(delta);
+        return Ok(());
+    }
+    match action {
+        Action::Back => app.close_links(),
+        Action::Link => app.plan_links(LinkAction::Link, false),
+        Action::Unlink => app.plan_links(LinkAction::Unlink, false),
+        Action::Adopt => app.plan_links(LinkAction::Adopt, false),
+        Action::LinkAll => app.plan_links(LinkAction::Link, true),
+        Action::UnlinkAll => app.plan_links(LinkAction::Unlink, true),
+        Action::Edit => edit_selected(terminal, app)?,
+        Action::Refresh => {
+            if let Some(Err(e)) = app.links.as_mut().map(|links| links.refresh()) {
+                app.status = Some(e.to_string());
             }
         }
+        _ => {}
     }
     Ok(())
 }
 
-fn find_dotfiles() -> io::Result<Vec<PathBuf>> {
-    let home_dir = std::env::var("HOME").map_err(|e| Error::new(ErrorKind::NotFound, e))?;
-    let config_dir_path = PathBuf::from(format!("{}/.config", home_dir));
-
-    // 1. Find dotfiles/dot-directories in the HOME directory (shallow)
-    let mut home_dotfiles: Vec<PathBuf> = WalkDir::new(&home_dir)
-        .max_depth(1)
-        .min_depth(1) // Exclude the home directory itself
-        .into_iter()
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| {
-            entry
-                .file_name()
-                .to_str()
-                .map(|s| s.starts_with('.'))
-                .unwrap_or(false)
-        })
-        .map(|entry| entry.into_path())
-        .collect();
-
-    // 2. Find all files and directories inside .config (recursive)
-    // We also exclude the .config directory itself from the list
-    let mut config_files: Vec<PathBuf> = WalkDir::new(&config_dir_path)
-        .min_depth(1)
-        .into_iter()
-        .filter_map(|entry| entry.ok())
-        .map(|entry| entry.into_path())
-        .collect();
-
-    // 3. Combine the lists
-    home_dotfiles.append(&mut config_files);
-
-    Ok(home_dotfiles)
+// Suspend the TUI, edit the selected entry in the user's editor, then come back
+fn edit_selected(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    app: &mut App,
+) -> io::Result<()> {
+    let Some(path) = app.selected_path().map(|path| path.to_path_buf()) else {
+        return Ok(());
+    };
+
+    restore_terminal()?;
+    let result = editor::open(&path);
+    *terminal = init_terminal()?;
+    terminal.clear()?;
+
+    let editor = editor::editor_command().join(" ");
+    app.status = match result {
+        Ok(status) if status.success() => None,
+        Ok(status) => Some(match status.code() {
+            Some(code) => format!("{} exited with status {}", editor, code),
+            None => format!("{} was terminated by a signal", editor),
+        }),
+        Err(e) => Some(format!("Could not start {}: {}", editor, e)),
+    };
+    app.refresh_selected();
+
+    Ok(())
 }
 
 // Restore the terminal
 fn restore_terminal() -> io::Result<()> {
     disable_raw_mode()?;
+    stdout().execute(DisableMouseCapture)?;
     stdout().execute(LeaveAlternateScreen)?;
     Ok(())
 }
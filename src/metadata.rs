@@ -0,0 +1,438 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use walkdir::WalkDir;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    File,
+    Dir,
+    Symlink,
+    Other,
+}
+
+// What the metadata panel shows about one path (the link itself, not its target)
+#[derive(Debug, Clone)]
+pub struct FileInfo {
+    pub kind: Kind,
+    pub size: u64,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub inode: u64,
+    pub links: u64,
+    pub modified: Option<SystemTime>,
+    pub link_target: Option<PathBuf>,
+    // Whether the symlink target exists
+    pub link_resolves: bool,
+}
+
+impl FileInfo {
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let metadata = fs::symlink_metadata(path)?;
+        let file_type = metadata.file_type();
+        let kind = if file_type.is_symlink() {
+            Kind::Symlink
+        } else if file_type.is_dir() {
+            Kind::Dir
+        } else if file_type.is_file() {
+            Kind::File
+        } else {
+            Kind::Other
+        };
+        let link_target = if kind == Kind::Symlink {
+            fs::read_link(path).ok()
+        } else {
+            None
+        };
+        let (mode, uid, gid, inode, links) = unix_fields(&metadata);
+
+        Ok(Self {
+            kind,
+            size: metadata.len(),
+            mode,
+            uid,
+            gid,
+            inode,
+            links,
+            modified: metadata.modified().ok(),
+            link_target,
+            link_resolves: kind == Kind::Symlink && path.exists(),
+        })
+    }
+
+    // ls-style permissions, e.g. "drwxr-xr-x"
+    pub fn permissions(&self) -> String {
+        let kind = match self.kind {
+            Kind::Dir => 'd',
+            Kind::Symlink => 'l',
+            Kind::Other => '?',
+            Kind::File => '-',
+        };
+        let mut out = String::from(kind);
+        for shift in [6, 3, 0] {
+            let bits = (self.mode >> shift) & 0o7;
+            out.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+            out.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+            out.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+        }
+        out
+    }
+}
+
+#[cfg(unix)]
+fn unix_fields(metadata: &fs::Metadata) -> (u32, u32, u32, u64, u64) {
+    use std::os::unix::fs::MetadataExt;
+
+    (
+        metadata.mode() & 0o7777,
+        metadata.uid(),
+        metadata.gid(),
+        metadata.ino(),
+        metadata.nlink(),
+    )
+}
+
+#[cfg(not(unix))]
+fn unix_fields(_metadata: &fs::Metadata) -> (u32, u32, u32, u64, u64) {
+    (0, 0, 0, 0, 1)
+}
+
+// User and group names from /etc/passwd and /etc/group
+#[derive(Debug, Default)]
+pub struct Names {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl Names {
+    pub fn load() -> Self {
+        Self {
+            users: read_id_file("/etc/passwd"),
+            groups: read_id_file("/etc/group"),
+        }
+    }
+
+    pub fn user(&self, uid: u32) -> String {
+        self.users
+            .get(&uid)
+            .cloned()
+            .unwrap_or_else(|| uid.to_string())
+    }
+
+    pub fn group(&self, gid: u32) -> String {
+        self.groups
+            .get(&gid)
+            .cloned()
+            .unwrap_or_else(|| gid.to_string())
+    }
+}
+
+// "name:x:id:..." lines, as used by both files
+fn read_id_file(path: &str) -> HashMap<u32, String> {
+    let text = fs::read_to_string(path).unwrap_or_default();
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((id, name.to_string()))
+        })
+        .collect()
+}
+
+// Number of direct children and total size of everything below a directory
+#[derive(Debug, Clone, Copy)]
+pub struct DirSummary {
+    pub children: usize,
+    pub total_size: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum DirSize {
+    Pending,
+    Done(DirSummary),
+}
+
+// Threads walking directories; further requests wait in a queue
+const WORKERS: usize = 2;
+// Entries walked between checks whether the result is still wanted
+const CANCEL_CHECK: usize = 1024;
+
+// A directory to summarize, tagged with the generation of its request
+type Job = (PathBuf, u64);
+
+// Directory summaries computed on a few worker threads so the UI never waits.
+// Each request gets a new generation; results whose generation is no longer
+// live were cancelled or invalidated and are dropped.
+#[derive(Debug)]
+pub struct DirSizes {
+    sizes: HashMap<PathBuf, (u64, DirSize)>,
+    next_generation: u64,
+    // Generations still wanted, shared with the workers so they can give up
+    live: Arc<Mutex<HashSet<u64>>>,
+    jobs: Option<Sender<Job>>,
+    sender: Sender<(u64, DirSummary)>,
+    receiver: Receiver<(u64, DirSummary)>,
+}
+
+impl Default for DirSizes {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sizes: HashMap::new(),
+            next_generation: 0,
+            live: Arc::default(),
+            jobs: None,
+            sender,
+            receiver,
+        }
+    }
+}
+
+impl DirSizes {
+    // The summary of `dir`, starting the computation on first request. Only
+    // the latest request is worked on: earlier ones still pending are
+    // cancelled and start over if asked for again.
+    pub fn get(&mut self, dir: &Path) -> DirSize {
+        if let Some((_, size)) = self.sizes.get(dir) {
+            return *size;
+        }
+        let pending: Vec<PathBuf> = self
+            .sizes
+            .iter()
+            .filter(|(_, (_, size))| matches!(size, DirSize::Pending))
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in pending {
+            self.forget(&path);
+        }
+
+        let generation = self.next_generation;
+        self.next_generation += 1;
+        self.sizes
+            .insert(dir.to_path_buf(), (generation, DirSize::Pending));
+        lock(&self.live).insert(generation);
+        let jobs = self
+            .jobs
+            .get_or_insert_with(|| start_workers(self.live.clone(), self.sender.clone()));
+        // The workers only stop once `jobs` is dropped
+        let _ = jobs.send((dir.to_path_buf(), generation));
+        DirSize::Pending
+    }
+
+    // Collect results from finished workers
+    pub fn poll(&mut self) {
+        while let Ok((generation, summary)) = self.receiver.try_recv() {
+            if !lock(&self.live).remove(&generation) {
+                continue;
+            }
+            if let Some(entry) = self
+                .sizes
+                .values_mut()
+                .find(|(entry_generation, _)| *entry_generation == generation)
+            {
+                entry.1 = DirSize::Done(summary);
+            }
+        }
+    }
+
+    // Forget the summaries that include any of `paths`: their own and those
+    // of every directory above them
+    pub fn invalidate(&mut self, paths: &[&Path]) {
+        let stale: Vec<PathBuf> = self
+            .sizes
+            .keys()
+            .filter(|dir| paths.iter().any(|path| path.starts_with(dir)))
+            .cloned()
+            .collect();
+        for dir in stale {
+            self.forget(&dir);
+        }
+    }
+
+    fn forget(&mut self, dir: &Path) {
+        if let Some((generation, _)) = self.sizes.remove(dir) {
+            lock(&self.live).remove(&generation);
+        }
+    }
+}
+
+fn lock(live: &Mutex<HashSet<u64>>) -> MutexGuard<'_, HashSet<u64>> {
+    // A worker panicking leaves the set itself intact
+    live.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn start_workers(
+    live: Arc<Mutex<HashSet<u64>>>,
+    results: Sender<(u64, DirSummary)>,
+) -> Sender<Job> {
+    let (jobs, queue) = mpsc::channel::<Job>();
+    let queue = Arc::new(Mutex::new(queue));
+    for _ in 0..WORKERS {
+        let queue = queue.clone();
+        let live = live.clone();
+        let results = results.clone();
+        thread::spawn(move || loop {
+            // One worker waits on the queue at a time; it closes when the app drops it
+            let job = match queue.lock() {
+                Ok(queue) => queue.recv(),
+                Err(_) => return,
+            };
+            let Ok((dir, generation)) = job else {
+                return;
+            };
+            let wanted = || lock(&live).contains(&generation);
+            if !wanted() {
+                continue;
+            }
+            if let Some(summary) = summarize(&dir, wanted) {
+                // The app may have quit in the meantime
+                let _ = results.send((generation, summary));
+            }
+        });
+    }
+    jobs
+}
+
+// None if `wanted` turned false during the walk
+fn summarize(dir: &Path, wanted: impl Fn() -> bool) -> Option<DirSummary> {
+    let children = fs::read_dir(dir)
+        .map(|entries| entries.count())
+        .unwrap_or(0);
+    let mut total_size = 0;
+    let entries = WalkDir::new(dir).min_depth(1).into_iter();
+    for (i, entry) in entries.filter_map(|entry| entry.ok()).enumerate() {
+        if i % CANCEL_CHECK == 0 && !wanted() {
+            return None;
+        }
+        if let Ok(metadata) = entry.metadata() {
+            if !metadata.is_dir() {
+                total_size += metadata.len();
+            }
+        }
+    }
+    Some(DirSummary {
+        children,
+        total_size,
+    })
+}
+
+// Human readable size, e.g. "12.3 KiB"
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+// Seconds since 1970-01-01 UTC, negative before
+pub fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    }
+}
+
+// "2024-05-01_13-37-00", for file names
+pub fn format_stamp(time: SystemTime) -> String {
+    format_time(time)
+        .trim_end_matches(" UTC")
+        .replace(' ', "_")
+        .replace(':', "-")
+}
+
+// "2024-05-01 13:37:00 UTC"
+pub fn format_time(time: SystemTime) -> String {
+    let seconds = unix_seconds(time);
+    let (days, seconds) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+// Days since 1970-01-01 to a (year, month, day) date in the proleptic Gregorian calendar
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(seconds: i64) -> SystemTime {
+        if seconds < 0 {
+            UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())
+        } else {
+            UNIX_EPOCH + Duration::from_secs(seconds as u64)
+        }
+    }
+
+    #[test]
+    fn civil_from_days_known_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(24_855), (2038, 1, 19));
+        // 2100 is not a leap year
+        assert_eq!(civil_from_days(47_540), (2100, 2, 28));
+        assert_eq!(civil_from_days(47_541), (2100, 3, 1));
+    }
+
+    #[test]
+    fn format_time_known_dates() {
+        assert_eq!(format_time(UNIX_EPOCH), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_time(at(-1)), "1969-12-31 23:59:59 UTC");
+        assert_eq!(format_time(at(951_827_696)), "2000-02-29 12:34:56 UTC");
+        // One second past the 32-bit time_t limit
+        assert_eq!(format_time(at(2_147_483_648)), "2038-01-19 03:14:08 UTC");
+    }
+
+    #[test]
+    fn format_stamp_is_safe_in_file_names() {
+        assert_eq!(format_stamp(UNIX_EPOCH), "1970-01-01_00-00-00");
+        assert_eq!(format_stamp(at(-1)), "1969-12-31_23-59-59");
+        assert_eq!(format_stamp(at(951_827_696)), "2000-02-29_12-34-56");
+        assert_eq!(format_stamp(at(2_208_988_800)), "2040-01-01_00-00-00");
+    }
+}
@@ -0,0 +1,374 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+
+use crate::{
+    highlight::{self, Language},
+    metadata::{self, FileInfo},
+    sniff,
+    theme::Theme,
+};
+
+// Larger files are previewed from their first bytes only
+const MAX_PREVIEW_BYTES: u64 = 256 * 1024;
+// Binary files are dumped up to this many bytes, HEX_ROW per line
+const MAX_HEX_BYTES: usize = 64 * 1024;
+const HEX_ROW: usize = 16;
+// Previews kept for files selected earlier
+const CACHE_ENTRIES: usize = 32;
+
+// A file highlighted and ready to draw
+#[derive(Debug)]
+pub struct Preview {
+    pub title: String,
+    pub lines: Vec<Line<'static>>,
+    // File contents get a line number gutter, messages do not
+    pub numbered: bool,
+}
+
+impl Preview {
+    fn message(text: &str) -> Self {
+        Self {
+            title: "Preview".to_string(),
+            lines: vec![Line::from(text.to_string())],
+            numbered: false,
+        }
+    }
+}
+
+// Modification time and size the preview was made from (None if unreadable)
+type Signature = Option<(Option<SystemTime>, u64)>;
+
+#[derive(Debug)]
+struct Cached {
+    signature: Signature,
+    preview: Preview,
+    last_used: u64,
+}
+
+// Previews by path. A file is read when it is selected, and only read again
+// once it changed on disk, so redrawing the same selection does no I/O.
+#[derive(Debug, Default)]
+pub struct PreviewCache {
+    entries: HashMap<PathBuf, Cached>,
+    // Path compared with the disk since it was selected
+    checked: Option<PathBuf>,
+    // Metadata of the selected path, read when it was selected
+    info: Option<(PathBuf, io::Result<FileInfo>)>,
+    uses: u64,
+}
+
+impl PreviewCache {
+    pub fn get(&mut self, path: &Path, theme: &Theme) -> &Preview {
+        if self.checked.as_deref() != Some(path) {
+            self.checked = Some(path.to_path_buf());
+            let signature = signature(path);
+            if self
+                .entries
+                .get(path)
+                .is_some_and(|cached| cached.signature != signature)
+            {
+                self.entries.remove(path);
+            }
+            if !self.entries.contains_key(path) {
+                self.evict();
+            }
+        }
+
+        self.uses += 1;
+        let cached = self
+            .entries
+            .entry(path.to_path_buf())
+            .or_insert_with(|| Cached {
+                signature: signature(path),
+                preview: load(path, theme),
+                last_used: 0,
+            });
+        cached.last_used = self.uses;
+        &cached.preview
+    }
+
+    // Metadata of `path` for the info panel and symlink previews, read
+    // again only once another path was asked for or it changed
+    pub fn info(&mut self, path: &Path) -> &io::Result<FileInfo> {
+        if self.info.as_ref().is_some_and(|(read, _)| read != path) {
+            self.info = None;
+        }
+        let (_, info) = self
+            .info
+            .get_or_insert_with(|| (path.to_path_buf(), FileInfo::read(path)));
+        info
+    }
+
+    // Compare the selected file with the disk again on the next draw, e.g.
+    // after it was edited
+    pub fn recheck(&mut self) {
+        self.checked = None;
+        self.info = None;
+    }
+
+    // Drop the preview of a file that changed on disk
+    pub fn invalidate(&mut self, path: &Path) {
+        self.entries.remove(path);
+        if self.checked.as_deref() == Some(path) {
+            self.checked = None;
+        }
+        if self.info.as_ref().is_some_and(|(read, _)| read == path) {
+            self.info = None;
+        }
+    }
+
+    // Make room for one more entry by dropping the least recently drawn
+    fn evict(&mut self) {
+        if self.entries.len() < CACHE_ENTRIES {
+            return;
+        }
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, cached)| cached.last_used)
+            .map(|(path, _)| path.clone());
+        if let Some(path) = oldest {
+            self.entries.remove(&path);
+        }
+    }
+}
+
+fn signature(path: &Path) -> Signature {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok(), metadata.len()))
+}
+
+fn load(path: &Path, theme: &Theme) -> Preview {
+    let Ok(metadata) = fs::metadata(path) else {
+        return Preview::message("Error reading file.");
+    };
+    if metadata.is_dir() {
+        return Preview::message("This is a directory.");
+    }
+
+    let mut content = Vec::new();
+    let read = fs::File::open(path)
+        .and_then(|file| file.take(MAX_PREVIEW_BYTES).read_to_end(&mut content));
+    if read.is_err() {
+        return Preview::message("Error reading file.");
+    }
+    let size = metadata.len();
+    let truncated = size > content.len() as u64;
+    if truncated {
+        trim_partial_char(&mut content);
+    }
+
+    let file_type = sniff::file_type(&content);
+    if sniff::is_binary(&content) {
+        return hex_preview(&content, file_type, size, theme);
+    }
+
+    // Mostly text: invalid sequences become U+FFFD and are marked after highlighting
+    let invalid = sniff::invalid_bytes(&content);
+    let read = content.len() as u64;
+    let content = String::from_utf8_lossy(&content);
+    let language = Language::detect(path, &content);
+    let mut lines = highlight::highlight(&content, language, &theme.syntax);
+    let mut notes = vec![language.name().to_string()];
+    if let Some(file_type) = file_type {
+        notes.push(file_type.to_string());
+    }
+    if invalid > 0 {
+        mark_invalid(&mut lines, theme.invalid);
+        notes.push(format!("{} not UTF-8", count_bytes(invalid)));
+    }
+    if truncated {
+        notes.push(format!(
+            "first {} of {}",
+            metadata::format_size(read),
+            metadata::format_size(size)
+        ));
+    }
+    Preview {
+        title: format!("Preview ({})", notes.join(", ")),
+        lines,
+        numbered: true,
+    }
+}
+
+// A summary line, then offset, hex bytes and ASCII like `hexdump -C`
+fn hex_preview(content: &[u8], file_type: Option<&str>, size: u64, theme: &Theme) -> Preview {
+    let shown = &content[..content.len().min(MAX_HEX_BYTES)];
+    let mut summary = format!(
+        "{}, {}",
+        file_type.unwrap_or("Binary data"),
+        metadata::format_size(size)
+    );
+    if (shown.len() as u64) < size {
+        summary.push_str(&format!(
+            ", first {} shown",
+            metadata::format_size(shown.len() as u64)
+        ));
+    }
+
+    let mut lines = vec![
+        Line::from(Span::styled(
+            summary,
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::default(),
+    ];
+    for (row, bytes) in shown.chunks(HEX_ROW).enumerate() {
+        let mut hex = String::new();
+        for (i, byte) in bytes.iter().enumerate() {
+            // An extra space halfway, like hexdump
+            if i == HEX_ROW / 2 {
+                hex.push(' ');
+            }
+            hex.push_str(&format!("{:02x} ", byte));
+        }
+        let ascii: String = bytes
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:08x}  ", row * HEX_ROW), theme.muted),
+            Span::raw(format!("{:<50}", hex)),
+            Span::styled(format!("|{}|", ascii), theme.accent),
+        ]));
+    }
+    Preview {
+        title: "Preview (hex)".to_string(),
+        lines,
+        numbered: false,
+    }
+}
+
+// Show the replacement characters standing in for invalid bytes
+fn mark_invalid(lines: &mut [Line<'static>], style: Style) {
+    for line in lines {
+        let has_invalid = line
+            .spans
+            .iter()
+            .any(|span| span.content.contains(char::REPLACEMENT_CHARACTER));
+        if !has_invalid {
+            continue;
+        }
+        let mut spans = Vec::new();
+        for span in std::mem::take(&mut line.spans) {
+            let mut rest = span.content.as_ref();
+            while let Some(i) = rest.find(char::REPLACEMENT_CHARACTER) {
+                if i > 0 {
+                    spans.push(Span::styled(rest[..i].to_string(), span.style));
+                }
+                spans.push(Span::styled(char::REPLACEMENT_CHARACTER.to_string(), style));
+                rest = &rest[i + char::REPLACEMENT_CHARACTER.len_utf8()..];
+            }
+            if !rest.is_empty() {
+                spans.push(Span::styled(rest.to_string(), span.style));
+            }
+        }
+        line.spans = spans;
+    }
+}
+
+// Drop a character cut in half by the size cap
+fn trim_partial_char(content: &mut Vec<u8>) {
+    // The last byte that is not a continuation byte starts the last character
+    let Some(back) = content
+        .iter()
+        .rev()
+        .take(4)
+        .position(|byte| byte & 0xc0 != 0x80)
+    else {
+        return;
+    };
+    let start = content.len() - 1 - back;
+    let width = match content[start] {
+        0xf0.. => 4,
+        0xe0.. => 3,
+        0xc0.. => 2,
+        _ => 1,
+    };
+    if start + width > content.len() {
+        content.truncate(start);
+    }
+}
+
+fn count_bytes(n: usize) -> String {
+    if n == 1 {
+        "1 byte".to_string()
+    } else {
+        format!("{} bytes", n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::Color;
+
+    fn trimmed(bytes: &[u8]) -> Vec<u8> {
+        let mut content = bytes.to_vec();
+        trim_partial_char(&mut content);
+        content
+    }
+
+    #[test]
+    fn trim_partial_char_drops_a_character_cut_off_at_the_limit() {
+        assert_eq!(trimmed(b"ab\xe2\x82"), b"ab");
+        assert_eq!(trimmed(b"ab\xe2"), b"ab");
+        assert_eq!(trimmed(b"ab\xf0\x9f\x98"), b"ab");
+        assert_eq!(trimmed(b"ab\xc3"), b"ab");
+    }
+
+    #[test]
+    fn trim_partial_char_keeps_whole_characters() {
+        assert_eq!(trimmed("ab€".as_bytes()), "ab€".as_bytes());
+        assert_eq!(trimmed("ab😀".as_bytes()), "ab😀".as_bytes());
+        assert_eq!(trimmed(b"abc"), b"abc");
+        assert_eq!(trimmed(b""), b"");
+        // Invalid bytes earlier on are not for it to fix
+        assert_eq!(trimmed(b"a\xe9b"), b"a\xe9b");
+    }
+
+    #[test]
+    fn mark_invalid_styles_only_the_replacement_characters() {
+        let text = Style::default().fg(Color::Green);
+        let invalid = Style::default().bg(Color::Red);
+        let content = String::from_utf8_lossy(b"caf\xe9 au lait\nfine\n\xff");
+        let mut lines: Vec<Line<'static>> = content
+            .lines()
+            .map(|line| Line::from(Span::styled(line.to_string(), text)))
+            .collect();
+        mark_invalid(&mut lines, invalid);
+
+        let spans = |line: &Line| -> Vec<(String, Style)> {
+            line.spans
+                .iter()
+                .map(|span| (span.content.to_string(), span.style))
+                .collect()
+        };
+        assert_eq!(
+            spans(&lines[0]),
+            [
+                ("caf".to_string(), text),
+                ("\u{fffd}".to_string(), invalid),
+                (" au lait".to_string(), text),
+            ]
+        );
+        assert_eq!(spans(&lines[1]), [("fine".to_string(), text)]);
+        assert_eq!(spans(&lines[2]), [("\u{fffd}".to_string(), invalid)]);
+    }
+}
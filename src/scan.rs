@@ -0,0 +1,260 @@
+use std::{
+    fs::FileType,
+    io::{self, Error, ErrorKind},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use globset::GlobSet;
+use walkdir::{DirEntry, WalkDir};
+
+use crate::ignores::{self, IgnoreFiles};
+
+// A directory the app shows as a top-level entry of the tree
+#[derive(Debug, Clone)]
+pub struct ScanRoot {
+    pub path: PathBuf,
+    // How many levels below the root may be expanded (None = unlimited)
+    pub max_depth: Option<usize>,
+    // Only show entries whose name starts with '.' directly under the root
+    pub hidden_only: bool,
+    pub follow_symlinks: bool,
+    // Files must match one of these, relative to the root (None = everything)
+    pub include: Option<GlobSet>,
+    // Entries matching these are skipped, along with everything below them
+    pub exclude: GlobSet,
+    // Honor .gitignore, .ignore and .dotfilesignore files below the root
+    pub ignore_files: bool,
+    // Skip well-known cache and browser profile directories
+    pub default_excludes: bool,
+    pub ignores: Arc<IgnoreFiles>,
+}
+
+impl ScanRoot {
+    pub fn new(path: PathBuf, max_depth: Option<usize>, hidden_only: bool) -> Self {
+        Self {
+            path,
+            max_depth,
+            hidden_only,
+            follow_symlinks: false,
+            include: None,
+            exclude: GlobSet::empty(),
+            ignore_files: true,
+            default_excludes: true,
+            ignores: Arc::default(),
+        }
+    }
+
+    // Whether an entry is listed as a directory, following symlinks if configured
+    pub fn is_dir(&self, path: &Path, file_type: FileType) -> bool {
+        file_type.is_dir() || (self.follow_symlinks && file_type.is_symlink() && path.is_dir())
+    }
+
+    // Whether an entry `depth` levels below the root should be listed
+    pub fn accepts(&self, path: &Path, file_type: FileType, depth: usize) -> bool {
+        let relative = path.strip_prefix(&self.path).unwrap_or(path);
+        let is_dir = self.is_dir(path, file_type);
+
+        if is_special(file_type) {
+            return false;
+        }
+
+        if self.hidden_only && depth == 1 {
+            let hidden = relative
+                .to_str()
+                .map(|s| s.starts_with('.'))
+                .unwrap_or(false);
+            if !hidden {
+                return false;
+            }
+        }
+        if self.exclude.is_match(relative) {
+            return false;
+        }
+        if self.default_excludes && is_dir {
+            let name = path.file_name().and_then(|name| name.to_str());
+            if name.is_some_and(ignores::is_default_exclude) {
+                return false;
+            }
+        }
+        if self.ignore_files && self.ignores.is_ignored(&self.path, path, is_dir) {
+            return false;
+        }
+        // Directories are always entered so that included files inside them are found
+        match &self.include {
+            Some(include) if !is_dir => include.is_match(relative),
+            _ => true,
+        }
+    }
+
+    // Whether entries `depth` levels below the root may have children listed
+    pub fn can_descend(&self, depth: usize) -> bool {
+        self.max_depth.is_none_or(|max_depth| depth < max_depth)
+    }
+}
+
+// Sockets, FIFOs and device nodes are never configuration
+#[cfg(unix)]
+fn is_special(file_type: FileType) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    file_type.is_socket()
+        || file_type.is_fifo()
+        || file_type.is_block_device()
+        || file_type.is_char_device()
+}
+
+#[cfg(not(unix))]
+fn is_special(_file_type: FileType) -> bool {
+    false
+}
+
+// The dotfiles in HOME (shallow) and everything inside .config
+pub fn default_roots() -> io::Result<Vec<ScanRoot>> {
+    let home_dir = home_dir()?;
+
+    Ok(vec![
+        ScanRoot::new(home_dir.clone(), Some(1), true),
+        ScanRoot::new(home_dir.join(".config"), None, false),
+    ])
+}
+
+pub fn home_dir() -> io::Result<PathBuf> {
+    std::env::var("HOME")
+        .map(PathBuf::from)
+        .map_err(|e| Error::new(ErrorKind::NotFound, e))
+}
+
+// Expand a leading ~ to HOME
+pub fn expand_tilde(path: &str) -> io::Result<PathBuf> {
+    if path == "~" {
+        return home_dir();
+    }
+    match path.strip_prefix("~/") {
+        Some(rest) => Ok(home_dir()?.join(rest)),
+        None => Ok(PathBuf::from(path)),
+    }
+}
+
+// Shorten paths under HOME to the familiar ~/ form for display
+pub fn display_path(path: &Path) -> String {
+    if let Ok(home) = home_dir() {
+        if let Ok(rest) = path.strip_prefix(&home) {
+            if rest.as_os_str().is_empty() {
+                return "~".to_string();
+            }
+            return format!("~/{}", rest.display());
+        }
+    }
+    path.display().to_string()
+}
+
+// Every entry under the scan roots, honouring each root's depth and hidden-only settings
+pub fn find_dotfiles(roots: &[ScanRoot]) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    walk(roots, |_, entry| {
+        paths.push(entry.into_path());
+        true
+    });
+    paths
+}
+
+// Visit the entries find_dotfiles returns, in the same order, until `visit` returns false
+pub fn walk(roots: &[ScanRoot], mut visit: impl FnMut(&ScanRoot, DirEntry) -> bool) {
+    for root in roots {
+        let mut walk = WalkDir::new(&root.path)
+            .min_depth(1)
+            .follow_links(root.follow_symlinks);
+        if let Some(max_depth) = root.max_depth {
+            walk = walk.max_depth(max_depth);
+        }
+        let entries = walk
+            .into_iter()
+            .filter_entry(|entry| root.accepts(entry.path(), entry.file_type(), entry.depth()))
+            .filter_map(|entry| entry.ok());
+        for entry in entries {
+            if !visit(root, entry) {
+                return;
+            }
+        }
+    }
+}
+
+// Found paths are sent in batches of this many, or after BATCH_INTERVAL
+const BATCH_SIZE: usize = 512;
+const BATCH_INTERVAL: Duration = Duration::from_millis(50);
+
+// find_dotfiles running on a worker thread, so the UI can start before it finishes
+#[derive(Debug)]
+pub struct Scanner {
+    receiver: Receiver<Vec<PathBuf>>,
+    cancelled: Arc<AtomicBool>,
+    pub started: Instant,
+    // Directory of the last path received
+    pub current_dir: Option<PathBuf>,
+}
+
+impl Scanner {
+    pub fn start(roots: Vec<ScanRoot>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+
+        let stop = cancelled.clone();
+        thread::spawn(move || {
+            let mut batch = Vec::new();
+            let mut last_sent = Instant::now();
+            walk(&roots, |_, entry| {
+                if stop.load(Ordering::Relaxed) {
+                    return false;
+                }
+                batch.push(entry.into_path());
+                if batch.len() >= BATCH_SIZE || last_sent.elapsed() >= BATCH_INTERVAL {
+                    last_sent = Instant::now();
+                    // Nobody is listening any more once the app is gone
+                    return sender.send(std::mem::take(&mut batch)).is_ok();
+                }
+                true
+            });
+            if !batch.is_empty() {
+                let _ = sender.send(batch);
+            }
+        });
+
+        Self {
+            receiver,
+            cancelled,
+            started: Instant::now(),
+            current_dir: None,
+        }
+    }
+
+    // Append the paths found since the last call to `index`, returning how many
+    // there were and whether the scan is still running
+    pub fn poll(&mut self, index: &mut Vec<PathBuf>) -> (usize, bool) {
+        let mut found = 0;
+        loop {
+            match self.receiver.try_recv() {
+                Ok(batch) => {
+                    found += batch.len();
+                    if let Some(dir) = batch.last().and_then(|path| path.parent()) {
+                        self.current_dir = Some(dir.to_path_buf());
+                    }
+                    index.extend(batch);
+                }
+                Err(TryRecvError::Empty) => return (found, true),
+                Err(TryRecvError::Disconnected) => return (found, false),
+            }
+        }
+    }
+
+    // Stop the worker at the next entry
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
@@ -0,0 +1,89 @@
+// Guessing what a file holds from its first bytes
+
+// Like git, a NUL byte this early means binary
+const NUL_WINDOW: usize = 8000;
+// Text with more invalid UTF-8 than this fraction of its bytes is treated as binary
+const MAX_INVALID_RATIO: usize = 10;
+
+// Magic numbers at the start of the file
+const SIGNATURES: &[(&[u8], &str)] = &[
+    (b"SQLite format 3\0", "SQLite database"),
+    (b"\x7fELF", "ELF binary"),
+    (b"\xcf\xfa\xed\xfe", "Mach-O binary"),
+    (b"\0asm", "WebAssembly module"),
+    (b"\x1f\x8b", "gzip compressed data"),
+    (b"BZh", "bzip2 compressed data"),
+    (b"\xfd7zXZ\0", "xz compressed data"),
+    (b"\x28\xb5\x2f\xfd", "zstd compressed data"),
+    (b"7z\xbc\xaf\x27\x1c", "7-Zip archive"),
+    (b"PK\x03\x04", "Zip archive"),
+    (b"%PDF-", "PDF document"),
+    (b"\x89PNG\r\n\x1a\n", "PNG image"),
+    (b"\xff\xd8\xff", "JPEG image"),
+    (b"GIF87a", "GIF image"),
+    (b"GIF89a", "GIF image"),
+    (b"bplist00", "binary property list"),
+    (b"\x1a\x01", "compiled terminfo entry"),
+    (b"\x1e\x02", "compiled terminfo entry"),
+];
+
+// e.g. "SQLite database", if the format is recognized
+pub fn file_type(bytes: &[u8]) -> Option<&'static str> {
+    if let Some((_, name)) = SIGNATURES
+        .iter()
+        .find(|(magic, _)| bytes.starts_with(magic))
+    {
+        return Some(name);
+    }
+    // POSIX tar has its magic in the header of the first member
+    if bytes.get(257..262) == Some(b"ustar") {
+        return Some("tar archive");
+    }
+    None
+}
+
+pub fn is_binary(bytes: &[u8]) -> bool {
+    if bytes[..bytes.len().min(NUL_WINDOW)].contains(&0) {
+        return true;
+    }
+    invalid_bytes(bytes) * MAX_INVALID_RATIO > bytes.len()
+}
+
+// Bytes that are not part of valid UTF-8
+pub fn invalid_bytes(bytes: &[u8]) -> usize {
+    bytes.utf8_chunks().map(|chunk| chunk.invalid().len()).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_bytes_counts_only_bad_sequences() {
+        assert_eq!(invalid_bytes("grüße, ✓".as_bytes()), 0);
+        // A stray Latin-1 byte in the middle
+        assert_eq!(invalid_bytes(b"caf\xe9 au lait"), 1);
+        // "€" cut off after two of its three bytes
+        assert_eq!(invalid_bytes(b"price: \xe2\x82"), 2);
+    }
+
+    #[test]
+    fn a_few_invalid_bytes_are_still_text() {
+        assert!(!is_binary(b"caf\xe9 au lait, two sugars"));
+        assert!(!is_binary(
+            b"the price is five euros and ten cents, or 5.10 \xe2\x82"
+        ));
+        assert!(is_binary(b"\xe9\xe9\xe9 abc"));
+        assert!(is_binary(b"text\0more text"));
+    }
+
+    #[test]
+    fn file_type_from_magic_numbers() {
+        assert_eq!(file_type(b"\x7fELF\x02\x01"), Some("ELF binary"));
+        assert_eq!(file_type(b"\x1f\x8b\x08"), Some("gzip compressed data"));
+        let mut tar = vec![0; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(file_type(&tar), Some("tar archive"));
+        assert_eq!(file_type(b"[core]\n"), None);
+    }
+}
@@ -0,0 +1,51 @@
+use std::{
+    fs,
+    io::{self, Error, ErrorKind},
+    path::PathBuf,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{backup, config};
+
+// Narrowest and widest the list pane can be dragged, in percent
+pub const MIN_SPLIT: u16 = 10;
+pub const MAX_SPLIT: u16 = 90;
+
+// What the interface remembers between runs, in
+// $XDG_DATA_HOME/dotfiles-tui/state.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    // Width of the list pane in percent of the screen
+    pub split: u16,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self { split: 30 }
+    }
+}
+
+impl State {
+    // The saved state, or the defaults if there is none or it is unreadable
+    pub fn load() -> Self {
+        let state: Self = path()
+            .and_then(fs::read_to_string)
+            .ok()
+            .and_then(|text| toml::from_str(&text).ok())
+            .unwrap_or_default();
+        Self {
+            split: state.split.clamp(MIN_SPLIT, MAX_SPLIT),
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let text = toml::to_string(self).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        backup::write_atomic(&path()?, text.as_bytes())
+    }
+}
+
+fn path() -> io::Result<PathBuf> {
+    Ok(config::data_dir()?.join("state.toml"))
+}
@@ -0,0 +1,550 @@
+use std::{collections::HashMap, env, fs, io::ErrorKind, path::Path, str::FromStr};
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+use crate::{config, highlight::SyntaxTheme, scan};
+
+// Styles of every widget. Bundled themes and theme files change some of them
+// on top of the default, by name, with specs like "bold black on #cfe2f3":
+// colors by name, 0-255 or #rrggbb, "on" before the background, modifiers
+// bold, dim, italic, underlined and reversed.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    // List items and preview text
+    pub text: Style,
+    pub directory: Style,
+    // The selected row of a list
+    pub highlight: Style,
+    pub border: Style,
+    pub border_focused: Style,
+    pub title: Style,
+    // Key hints in the status line
+    pub status: Style,
+    // Messages in the status line
+    pub message: Style,
+    // Prompts, keys and paths
+    pub accent: Style,
+    // Labels, guides, dates and line numbers
+    pub muted: Style,
+    // Search matches
+    pub matched: Style,
+    // Names right after they changed on disk
+    pub changed: Style,
+    pub ok: Style,
+    pub warning: Style,
+    pub error: Style,
+    // Untracked files and broken links
+    pub special: Style,
+    pub diff_added: Style,
+    pub diff_removed: Style,
+    pub diff_hunk: Style,
+    pub diff_header: Style,
+    // The preview line jumped to
+    pub current_line: Style,
+    // Bytes that are not UTF-8
+    pub invalid: Style,
+    pub syntax: SyntaxTheme,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            text: Style::default(),
+            directory: Style::default().add_modifier(Modifier::BOLD),
+            highlight: Style::default()
+                .add_modifier(Modifier::BOLD)
+                .bg(Color::Gray),
+            border: Style::default(),
+            border_focused: Style::default().fg(Color::Cyan),
+            title: Style::default(),
+            status: Style::default().fg(Color::DarkGray),
+            message: Style::default().fg(Color::Yellow),
+            accent: Style::default().fg(Color::Cyan),
+            muted: Style::default().fg(Color::DarkGray),
+            matched: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            changed: Style::default().fg(Color::Black).bg(Color::Yellow),
+            ok: Style::default().fg(Color::Green),
+            warning: Style::default().fg(Color::Yellow),
+            error: Style::default().fg(Color::Red),
+            special: Style::default().fg(Color::Magenta),
+            diff_added: Style::default().fg(Color::Green),
+            diff_removed: Style::default().fg(Color::Red),
+            diff_hunk: Style::default().fg(Color::Cyan),
+            diff_header: Style::default().add_modifier(Modifier::BOLD),
+            current_line: Style::default().bg(Color::DarkGray),
+            invalid: Style::default().fg(Color::White).bg(Color::Red),
+            syntax: SyntaxTheme::default(),
+        }
+    }
+}
+
+// Changes to the default theme, for terminals with a light background
+const LIGHT: &[(&str, &str)] = &[
+    ("highlight", "bold on #cfe2f3"),
+    ("border_focused", "blue"),
+    ("status", "#6c6c6c"),
+    ("message", "#9a6700"),
+    ("accent", "#0550ae"),
+    ("muted", "#6c6c6c"),
+    ("matched", "bold #8250df"),
+    ("changed", "black on #ffe08a"),
+    ("ok", "#1a7f37"),
+    ("warning", "#9a6700"),
+    ("error", "#cf222e"),
+    ("special", "#8250df"),
+    ("diff_added", "#1a7f37"),
+    ("diff_removed", "#cf222e"),
+    ("diff_hunk", "#0550ae"),
+    ("current_line", "on #e8e8e8"),
+    ("syntax.comment", "italic #6e7781"),
+    ("syntax.string", "#0a3069"),
+    ("syntax.number", "#0550ae"),
+    ("syntax.keyword", "bold #cf222e"),
+    ("syntax.key", "#116329"),
+    ("syntax.section", "bold #8250df"),
+    ("syntax.variable", "#953800"),
+    ("syntax.punctuation", "#57606a"),
+];
+
+// Bright colors only and no dim text
+const HIGH_CONTRAST: &[(&str, &str)] = &[
+    ("text", "white"),
+    ("directory", "bold white"),
+    ("highlight", "bold black on white"),
+    ("border", "white"),
+    ("border_focused", "bold light-yellow"),
+    ("title", "bold white"),
+    ("status", "white"),
+    ("message", "bold light-yellow"),
+    ("accent", "light-cyan"),
+    ("muted", "gray"),
+    ("matched", "bold underlined light-yellow"),
+    ("changed", "bold black on light-yellow"),
+    ("ok", "light-green"),
+    ("warning", "light-yellow"),
+    ("error", "light-red"),
+    ("special", "light-magenta"),
+    ("diff_added", "light-green"),
+    ("diff_removed", "light-red"),
+    ("diff_hunk", "light-cyan"),
+    ("diff_header", "bold white"),
+    ("current_line", "on blue"),
+    ("invalid", "bold white on red"),
+    ("syntax.plain", "white"),
+    ("syntax.comment", "italic gray"),
+    ("syntax.string", "light-green"),
+    ("syntax.number", "light-magenta"),
+    ("syntax.keyword", "bold light-yellow"),
+    ("syntax.key", "light-cyan"),
+    ("syntax.section", "bold light-blue"),
+    ("syntax.variable", "light-red"),
+    ("syntax.punctuation", "white"),
+];
+
+const BUNDLED: [(&str, &[(&str, &str)]); 3] = [
+    ("default", &[]),
+    ("light", LIGHT),
+    ("high-contrast", HIGH_CONTRAST),
+];
+
+// $XDG_CONFIG_HOME/dotfiles-tui/themes/<name>.toml, e.g.
+//
+//     # Bundled theme to start from, "default" if left out
+//     base = "light"
+//
+//     [styles]
+//     highlight = "bold black on #ffd787"
+//     border_focused = "magenta"
+//
+//     [syntax]
+//     comment = "italic 244"
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    base: Option<String>,
+    #[serde(default)]
+    styles: HashMap<String, String>,
+    #[serde(default)]
+    syntax: HashMap<String, String>,
+}
+
+impl Theme {
+    // A bundled theme, or a theme file in `themes_dir` (or at a path)
+    pub fn load(name: &str, themes_dir: &Path) -> Result<Self, String> {
+        if let Some(theme) = Self::bundled(name) {
+            return Ok(theme);
+        }
+        let by_name = !name.contains('/');
+        let path = if by_name {
+            themes_dir.join(format!("{}.toml", name))
+        } else {
+            scan::expand_tilde(name).map_err(|e| e.to_string())?
+        };
+        let context = |e: String| format!("theme {}: {}", scan::display_path(&path), e);
+        let text = fs::read_to_string(&path).map_err(|e| match e.kind() {
+            ErrorKind::NotFound if by_name => format!(
+                "unknown theme \"{}\" (expected {} or a file {})",
+                name,
+                bundled_names(),
+                scan::display_path(&path)
+            ),
+            _ => context(e.to_string()),
+        })?;
+        let file: ThemeFile =
+            toml::from_str(&text).map_err(|e| context(config::toml_error(&text, e)))?;
+
+        let base = file.base.as_deref().unwrap_or("default");
+        let mut theme = Self::bundled(base).ok_or_else(|| {
+            context(format!(
+                "unknown base \"{}\" (expected {})",
+                base,
+                bundled_names()
+            ))
+        })?;
+        // Sorted so errors do not depend on hash order
+        let mut styles: Vec<(String, &String)> = file
+            .styles
+            .iter()
+            .map(|(name, spec)| (name.clone(), spec))
+            .chain(
+                file.syntax
+                    .iter()
+                    .map(|(name, spec)| (format!("syntax.{}", name), spec)),
+            )
+            .collect();
+        styles.sort();
+        for (name, spec) in styles {
+            theme.set(&name, spec).map_err(context)?;
+        }
+        Ok(theme)
+    }
+
+    fn bundled(name: &str) -> Option<Self> {
+        let (_, changes) = BUNDLED.iter().find(|(bundled, _)| *bundled == name)?;
+        let mut theme = Self::default();
+        for (name, spec) in *changes {
+            // The tables above are known to parse
+            let _ = theme.set(name, spec);
+        }
+        Some(theme)
+    }
+
+    fn set(&mut self, name: &str, spec: &str) -> Result<(), String> {
+        let style = parse_style(spec).map_err(|e| format!("{} = \"{}\": {}", name, spec, e))?;
+        let Some((_, field)) = self
+            .styles_mut()
+            .into_iter()
+            .find(|(field, _)| *field == name)
+        else {
+            return Err(format!("unknown style \"{}\"", name));
+        };
+        *field = style;
+        Ok(())
+    }
+
+    // Every style by name, syntax tokens as "syntax.comment"
+    fn styles_mut(&mut self) -> Vec<(&'static str, &mut Style)> {
+        let mut styles = vec![
+            ("text", &mut self.text),
+            ("directory", &mut self.directory),
+            ("highlight", &mut self.highlight),
+            ("border", &mut self.border),
+            ("border_focused", &mut self.border_focused),
+            ("title", &mut self.title),
+            ("status", &mut self.status),
+            ("message", &mut self.message),
+            ("accent", &mut self.accent),
+            ("muted", &mut self.muted),
+            ("matched", &mut self.matched),
+            ("changed", &mut self.changed),
+            ("ok", &mut self.ok),
+            ("warning", &mut self.warning),
+            ("error", &mut self.error),
+            ("special", &mut self.special),
+            ("diff_added", &mut self.diff_added),
+            ("diff_removed", &mut self.diff_removed),
+            ("diff_hunk", &mut self.diff_hunk),
+            ("diff_header", &mut self.diff_header),
+            ("current_line", &mut self.current_line),
+            ("invalid", &mut self.invalid),
+        ];
+        styles.extend(self.syntax.styles_mut());
+        styles
+    }
+
+    // Bring the colors down to what the terminal can show
+    pub fn fit(mut self, depth: ColorDepth) -> Self {
+        for (_, style) in self.styles_mut() {
+            *style = depth.fit(*style);
+        }
+        self
+    }
+}
+
+fn bundled_names() -> String {
+    let names: Vec<String> = BUNDLED
+        .iter()
+        .map(|(name, _)| format!("\"{}\"", name))
+        .collect();
+    names.join(", ")
+}
+
+fn parse_style(spec: &str) -> Result<Style, String> {
+    const MODIFIERS: [(&str, Modifier); 5] = [
+        ("bold", Modifier::BOLD),
+        ("dim", Modifier::DIM),
+        ("italic", Modifier::ITALIC),
+        ("underlined", Modifier::UNDERLINED),
+        ("reversed", Modifier::REVERSED),
+    ];
+    let mut style = Style::default();
+    let mut words = spec.split_whitespace();
+    while let Some(word) = words.next() {
+        if word == "on" {
+            let color = words.next().ok_or("missing color after \"on\"")?;
+            style = style.bg(parse_color(color)?);
+        } else if let Some((_, modifier)) = MODIFIERS.iter().find(|(name, _)| *name == word) {
+            style = style.add_modifier(*modifier);
+        } else {
+            style = style.fg(parse_color(word)?);
+        }
+    }
+    Ok(style)
+}
+
+fn parse_color(word: &str) -> Result<Color, String> {
+    Color::from_str(word).map_err(|_| format!("unknown color or modifier \"{}\"", word))
+}
+
+// How many colors the terminal shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+    // NO_COLOR is set: modifiers only
+    None,
+}
+
+// The 16 ANSI colors as xterm draws them
+const ANSI: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+// Channel values of the 6x6x6 cube in the 256 color palette
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl ColorDepth {
+    // From NO_COLOR (https://no-color.org), COLORTERM and TERM
+    pub fn detect() -> Self {
+        if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            return Self::None;
+        }
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let term = env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" || term.ends_with("-direct") {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
+
+    fn fit(self, style: Style) -> Style {
+        if self == Self::None {
+            // Without a background, selections and flashes still need to stand out
+            let had_background = style.bg.is_some_and(|bg| bg != Color::Reset);
+            let mut style = Style {
+                fg: None,
+                bg: None,
+                ..style
+            };
+            if had_background {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            return style;
+        }
+        Style {
+            fg: style.fg.map(|color| self.fit_color(color)),
+            bg: style.bg.map(|color| self.fit_color(color)),
+            ..style
+        }
+    }
+
+    fn fit_color(self, color: Color) -> Color {
+        match (self, color) {
+            (Self::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(nearest_indexed((r, g, b))),
+            (Self::Ansi16, Color::Rgb(r, g, b)) => nearest_ansi((r, g, b)),
+            (Self::Ansi16, Color::Indexed(index)) => nearest_ansi(indexed_rgb(index)),
+            _ => color,
+        }
+    }
+}
+
+// The closest color of the cube or the gray ramp
+fn nearest_indexed(rgb: (u8, u8, u8)) -> u8 {
+    let level = |value: u8| {
+        (0..CUBE.len())
+            .min_by_key(|&i| CUBE[i].abs_diff(value))
+            .unwrap_or(0) as u8
+    };
+    let cube = 16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2);
+    let average = (rgb.0 as u16 + rgb.1 as u16 + rgb.2 as u16) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+    if distance(indexed_rgb(gray), rgb) < distance(indexed_rgb(cube), rgb) {
+        gray
+    } else {
+        cube
+    }
+}
+
+fn nearest_ansi(rgb: (u8, u8, u8)) -> Color {
+    ANSI.iter()
+        .min_by_key(|(_, ansi)| distance(*ansi, rgb))
+        .map_or(Color::Reset, |(color, _)| *color)
+}
+
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI[index as usize].1,
+        16..=231 => {
+            let i = index - 16;
+            (
+                CUBE[(i / 36) as usize],
+                CUBE[(i / 6 % 6) as usize],
+                CUBE[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + 10 * (index - 232);
+            (level, level, level)
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let channel = |x: u8, y: u8| (x.abs_diff(y) as u32).pow(2);
+    channel(a.0, b.0) + channel(a.1, b.1) + channel(a.2, b.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_style_reads_colors_and_modifiers() {
+        assert_eq!(
+            parse_style("bold black on #cfe2f3").unwrap(),
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Rgb(0xcf, 0xe2, 0xf3))
+                .add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(
+            parse_style("italic 244").unwrap(),
+            Style::default()
+                .fg(Color::Indexed(244))
+                .add_modifier(Modifier::ITALIC)
+        );
+        assert_eq!(parse_style("").unwrap(), Style::default());
+        assert_eq!(
+            parse_style("bold on").unwrap_err(),
+            "missing color after \"on\""
+        );
+        assert_eq!(
+            parse_style("shiny").unwrap_err(),
+            "unknown color or modifier \"shiny\""
+        );
+    }
+
+    #[test]
+    fn fit_picks_the_nearest_palette_color() {
+        let fit = |depth: ColorDepth, color| depth.fit(Style::default().fg(color)).fg;
+        let rgb = |hex: &str| Color::from_str(hex).unwrap();
+
+        assert_eq!(
+            fit(ColorDepth::TrueColor, rgb("#cfe2f3")),
+            Some(rgb("#cfe2f3"))
+        );
+
+        assert_eq!(
+            fit(ColorDepth::Ansi256, rgb("#ff0000")),
+            Some(Color::Indexed(196))
+        );
+        assert_eq!(
+            fit(ColorDepth::Ansi256, rgb("#5f87af")),
+            Some(Color::Indexed(67))
+        );
+        // Grays go to the gray ramp rather than the cube
+        assert_eq!(
+            fit(ColorDepth::Ansi256, rgb("#808080")),
+            Some(Color::Indexed(244))
+        );
+        assert_eq!(fit(ColorDepth::Ansi256, Color::Cyan), Some(Color::Cyan));
+
+        assert_eq!(
+            fit(ColorDepth::Ansi16, rgb("#ff0000")),
+            Some(Color::LightRed)
+        );
+        assert_eq!(fit(ColorDepth::Ansi16, rgb("#cf222e")), Some(Color::Red));
+        assert_eq!(fit(ColorDepth::Ansi16, rgb("#1a7f37")), Some(Color::Green));
+        assert_eq!(
+            fit(ColorDepth::Ansi16, Color::Indexed(196)),
+            Some(Color::LightRed)
+        );
+        assert_eq!(
+            fit(ColorDepth::Ansi16, Color::Indexed(244)),
+            Some(Color::DarkGray)
+        );
+    }
+
+    #[test]
+    fn no_color_keeps_highlights_visible() {
+        for name in ["default", "light", "high-contrast"] {
+            let theme = Theme::bundled(name).unwrap().fit(ColorDepth::None);
+            assert_eq!(theme.highlight.fg, None, "{}", name);
+            assert_eq!(theme.highlight.bg, None, "{}", name);
+            assert!(
+                theme
+                    .highlight
+                    .add_modifier
+                    .contains(Modifier::BOLD | Modifier::REVERSED),
+                "{}",
+                name
+            );
+            assert!(
+                theme.current_line.add_modifier.contains(Modifier::REVERSED),
+                "{}",
+                name
+            );
+        }
+
+        let theme = Theme::default().fit(ColorDepth::None);
+        assert_eq!(theme.error, Style::default());
+        assert_eq!(
+            theme.directory,
+            Style::default().add_modifier(Modifier::BOLD)
+        );
+        let reset = Style::default().bg(Color::Reset);
+        assert_eq!(ColorDepth::None.fit(reset), Style::default());
+    }
+}
@@ -0,0 +1,415 @@
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::scan::{self, ScanRoot};
+
+#[derive(Debug)]
+pub struct Node {
+    pub path: PathBuf,
+    pub name: String,
+    pub is_dir: bool,
+    pub expanded: bool,
+    // None until the directory is expanded for the first time
+    children: Option<Vec<Node>>,
+    // The scan root this node belongs to and how far below it the node is
+    root: Arc<ScanRoot>,
+    depth: usize,
+}
+
+impl Node {
+    fn new(path: PathBuf, name: String, is_dir: bool, root: Arc<ScanRoot>, depth: usize) -> Self {
+        Self {
+            path,
+            name,
+            is_dir,
+            expanded: false,
+            children: None,
+            root,
+            depth,
+        }
+    }
+
+    // A scan root from the config rather than something found under one
+    pub fn is_root(&self) -> bool {
+        self.depth == 0
+    }
+
+    pub fn is_expandable(&self) -> bool {
+        self.is_dir && self.root.can_descend(self.depth)
+    }
+
+    fn load_children(&mut self) -> io::Result<()> {
+        if self.children.is_some() {
+            return Ok(());
+        }
+
+        let depth = self.depth + 1;
+        let mut children: Vec<Node> = fs::read_dir(&self.path)?
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let path = entry.path();
+                let file_type = entry.file_type().ok()?;
+                if !self.root.accepts(&path, file_type, depth) {
+                    return None;
+                }
+                let is_dir = self.root.is_dir(&path, file_type);
+                let name = entry.file_name().to_string_lossy().to_string();
+                Some(Node::new(path, name, is_dir, self.root.clone(), depth))
+            })
+            .collect();
+
+        // Directories first, then alphabetical
+        children.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        self.children = Some(children);
+        Ok(())
+    }
+
+    // Re-read loaded children from disk, keeping the state of entries that still exist
+    fn reload(&mut self) {
+        let Some(old) = self.children.take() else {
+            return;
+        };
+        // An edited ignore file should take effect
+        self.root.ignores.forget(&self.path);
+        if self.load_children().is_err() {
+            self.children = Some(Vec::new());
+            return;
+        }
+
+        let mut old: HashMap<PathBuf, Node> = old
+            .into_iter()
+            .map(|node| (node.path.clone(), node))
+            .collect();
+        for child in self.children.iter_mut().flatten() {
+            if let Some(previous) = old.remove(&child.path) {
+                if previous.is_dir == child.is_dir {
+                    *child = previous;
+                }
+            }
+        }
+    }
+}
+
+// One visible line of the tree
+#[derive(Debug)]
+pub struct Row {
+    // Position of the node: root index followed by child indices
+    pub index: Vec<usize>,
+    // Indentation guides drawn before the name, e.g. "│  ├─ "
+    pub guide: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Tree {
+    roots: Vec<Node>,
+    rows: Vec<Row>,
+}
+
+impl Tree {
+    pub fn new(roots: &[ScanRoot]) -> Self {
+        let roots = roots
+            .iter()
+            .map(|root| {
+                let name = scan::display_path(&root.path);
+                let root = Arc::new(root.clone());
+                let mut node = Node::new(root.path.clone(), name, true, root, 0);
+                node.expanded = node.load_children().is_ok();
+                node
+            })
+            .collect();
+
+        let mut tree = Self {
+            roots,
+            rows: Vec::new(),
+        };
+        tree.rebuild();
+        tree
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    // Visible rows paired with their nodes, in display order
+    pub fn iter(&self) -> impl Iterator<Item = (&Row, &Node)> {
+        self.rows
+            .iter()
+            .filter_map(|row| self.node_at(&row.index).map(|node| (row, node)))
+    }
+
+    pub fn node(&self, row: usize) -> Option<&Node> {
+        self.node_at(&self.rows.get(row)?.index)
+    }
+
+    // Expand the directory at `row`, reading its children on first use.
+    // Unreadable directories expand to nothing.
+    pub fn expand(&mut self, row: usize) {
+        let Some(index) = self.rows.get(row).map(|row| row.index.clone()) else {
+            return;
+        };
+        if let Some(node) = self.node_at_mut(&index) {
+            if node.is_expandable() && !node.expanded {
+                if node.load_children().is_err() {
+                    node.children = Some(Vec::new());
+                }
+                node.expanded = true;
+            }
+        }
+        self.rebuild();
+    }
+
+    // Collapse the directory at `row`. Returns the row to select afterwards,
+    // which is the parent when the entry was not expanded.
+    pub fn collapse(&mut self, row: usize) -> usize {
+        let Some(index) = self.rows.get(row).map(|row| row.index.clone()) else {
+            return row;
+        };
+        if let Some(node) = self.node_at_mut(&index) {
+            if node.expanded {
+                node.expanded = false;
+                self.rebuild();
+                return row;
+            }
+        }
+
+        let parent = &index[..index.len() - 1];
+        if parent.is_empty() {
+            return row;
+        }
+        self.rows
+            .iter()
+            .position(|row| row.index == parent)
+            .unwrap_or(row)
+    }
+
+    // Row currently showing `path`, if it is visible
+    pub fn row_of(&self, path: &Path) -> Option<usize> {
+        self.iter().position(|(_, node)| node.path == path)
+    }
+
+    // Expand every directory on the way to `path` and return its row
+    pub fn reveal(&mut self, path: &Path) -> Option<usize> {
+        // The deepest root containing the path, so ~/.config/x is found under ~/.config
+        let root = self
+            .roots
+            .iter()
+            .enumerate()
+            .filter(|(_, root)| path.starts_with(&root.path))
+            .max_by_key(|(_, root)| root.path.components().count())
+            .map(|(i, _)| i)?;
+
+        let mut index = vec![root];
+        let mut node = &mut self.roots[root];
+        while node.path != path {
+            if !node.is_expandable() {
+                return None;
+            }
+            if node.load_children().is_err() {
+                node.children = Some(Vec::new());
+            }
+            node.expanded = true;
+
+            let children = node.children.as_mut()?;
+            let i = children
+                .iter()
+                .position(|child| path.starts_with(&child.path))?;
+            index.push(i);
+            node = &mut children[i];
+        }
+
+        self.rebuild();
+        self.rows.iter().position(|row| row.index == index)
+    }
+
+    // Re-read a directory if it has been loaded
+    pub fn reload_dir(&mut self, dir: &Path) {
+        fn find<'a>(nodes: &'a mut [Node], dir: &Path) -> Option<&'a mut Node> {
+            let node = nodes.iter_mut().find(|node| dir.starts_with(&node.path))?;
+            if node.path == dir {
+                return Some(node);
+            }
+            find(node.children.as_mut()?, dir)
+        }
+
+        // Overlapping roots may both show the directory
+        for i in 0..self.roots.len() {
+            if let Some(node) = find(&mut self.roots[i..=i], dir) {
+                node.reload();
+            }
+        }
+        self.rebuild();
+    }
+
+    // Re-read the directory containing the entry at `row` (or the root itself)
+    pub fn reload_parent(&mut self, row: usize) {
+        let Some(index) = self.rows.get(row).map(|row| row.index.clone()) else {
+            return;
+        };
+        let parent = if index.len() > 1 {
+            &index[..index.len() - 1]
+        } else {
+            &index[..]
+        };
+        if let Some(node) = self.node_at_mut(parent) {
+            node.reload();
+        }
+        self.rebuild();
+    }
+
+    pub fn toggle(&mut self, row: usize) {
+        match self.node(row) {
+            Some(node) if node.expanded => {
+                self.collapse(row);
+            }
+            Some(_) => self.expand(row),
+            None => {}
+        }
+    }
+
+    fn node_at(&self, index: &[usize]) -> Option<&Node> {
+        let (first, rest) = index.split_first()?;
+        rest.iter().try_fold(self.roots.get(*first)?, |node, i| {
+            node.children.as_ref()?.get(*i)
+        })
+    }
+
+    fn node_at_mut(&mut self, index: &[usize]) -> Option<&mut Node> {
+        let (first, rest) = index.split_first()?;
+        rest.iter()
+            .try_fold(self.roots.get_mut(*first)?, |node, i| {
+                node.children.as_mut()?.get_mut(*i)
+            })
+    }
+
+    // Recompute the visible rows after the shape of the tree changed
+    fn rebuild(&mut self) {
+        let mut rows = Vec::new();
+        for (i, root) in self.roots.iter().enumerate() {
+            push_rows(root, vec![i], String::new(), "", &mut rows);
+        }
+        self.rows = rows;
+    }
+}
+
+fn push_rows(node: &Node, index: Vec<usize>, guide: String, indent: &str, rows: &mut Vec<Row>) {
+    rows.push(Row {
+        index: index.clone(),
+        guide,
+    });
+
+    let children = match (&node.children, node.expanded) {
+        (Some(children), true) => children.as_slice(),
+        _ => return,
+    };
+
+    for (i, child) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        let child_guide = format!("{}{}", indent, if last { "└─ " } else { "├─ " });
+        let child_indent = format!("{}{}", indent, if last { "   " } else { "│  " });
+
+        let mut child_index = index.clone();
+        child_index.push(i);
+        push_rows(child, child_index, child_guide, &child_indent, rows);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testdir::TestDir;
+
+    fn setup(name: &str) -> (TestDir, Tree) {
+        let dir = TestDir::new(name);
+        dir.write("a/b/c.conf", "");
+        dir.write("a/b/d.conf", "");
+        dir.write("a/e.conf", "");
+        dir.write("f.conf", "");
+        let tree = Tree::new(&[ScanRoot::new(dir.root.clone(), None, false)]);
+        (dir, tree)
+    }
+
+    // Every row below the root, drawn as in the tree view
+    fn rows(tree: &Tree) -> Vec<String> {
+        tree.iter()
+            .skip(1)
+            .map(|(row, node)| format!("{}{}", row.guide, node.name))
+            .collect()
+    }
+
+    #[test]
+    fn reveal_expands_the_way_to_a_path() {
+        let (dir, mut tree) = setup("tree-reveal");
+        assert_eq!(rows(&tree), ["├─ a", "└─ f.conf"]);
+
+        let row = tree.reveal(&dir.path("a/b/d.conf")).unwrap();
+        assert_eq!(tree.node(row).unwrap().path, dir.path("a/b/d.conf"));
+        assert_eq!(
+            rows(&tree),
+            [
+                "├─ a",
+                "│  ├─ b",
+                "│  │  ├─ c.conf",
+                "│  │  └─ d.conf",
+                "│  └─ e.conf",
+                "└─ f.conf",
+            ]
+        );
+
+        assert_eq!(tree.reveal(&dir.root), Some(0));
+        assert_eq!(tree.reveal(&dir.path("a/missing")), None);
+        assert_eq!(tree.reveal(Path::new("/elsewhere")), None);
+    }
+
+    #[test]
+    fn collapse_hides_children_or_moves_to_the_parent() {
+        let (dir, mut tree) = setup("tree-collapse");
+        let c = tree.reveal(&dir.path("a/b/c.conf")).unwrap();
+        let b = tree.row_of(&dir.path("a/b")).unwrap();
+
+        // A file moves the selection to its directory
+        assert_eq!(tree.collapse(c), b);
+        assert_eq!(tree.len(), 7);
+        assert_eq!(tree.collapse(b), b);
+        assert_eq!(
+            rows(&tree),
+            ["├─ a", "│  ├─ b", "│  └─ e.conf", "└─ f.conf"]
+        );
+
+        // Expanding again shows what was loaded before
+        tree.toggle(b);
+        assert_eq!(tree.row_of(&dir.path("a/b/c.conf")), Some(c));
+        // Roots have no parent to move to
+        assert_eq!(tree.collapse(0), 0);
+        assert_eq!(tree.len(), 1);
+        assert_eq!(tree.collapse(0), 0);
+    }
+
+    #[test]
+    fn reload_keeps_expanded_directories_and_the_selected_path() {
+        let (dir, mut tree) = setup("tree-reload");
+        tree.reveal(&dir.path("a/b/c.conf")).unwrap();
+        let selected = dir.path("a/e.conf");
+
+        dir.write("a/0.conf", "");
+        fs::remove_file(dir.path("f.conf")).unwrap();
+        tree.reload_dir(&dir.path("a"));
+        tree.reload_dir(&dir.root);
+
+        assert_eq!(
+            rows(&tree),
+            [
+                "└─ a",
+                "   ├─ b",
+                "   │  ├─ c.conf",
+                "   │  └─ d.conf",
+                "   ├─ 0.conf",
+                "   └─ e.conf",
+            ]
+        );
+        assert_eq!(tree.row_of(&selected), Some(6));
+    }
+}
@@ -0,0 +1,1130 @@
+use std::{
+    borrow::Cow,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Margin, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, Borders, Clear, List, ListItem, ListState, Padding, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState,
+    },
+    Frame,
+};
+
+use crate::{
+    app::{App, Focus, NameKind},
+    backup::{BackupView, LiveState},
+    git::FileStatus,
+    highlight::{self, Language},
+    journal::JournalView,
+    keymap::Action,
+    links::LinkStatus,
+    metadata::{self, DirSize, FileInfo, Kind},
+    preview::PreviewCache,
+    scan::{self, Scanner},
+    theme::Theme,
+    tree::Node,
+};
+
+// How long the name of an entry stays highlighted after it changed on disk
+const CHANGE_FLASH: Duration = Duration::from_millis(1500);
+
+pub fn draw(frame: &mut Frame, app: &mut App) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+        .split(frame.size());
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage(app.split),
+                Constraint::Percentage(100 - app.split),
+            ]
+            .as_ref(),
+        )
+        .split(rows[0]);
+
+    let right = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(6), Constraint::Min(0)].as_ref())
+        .split(chunks[1]);
+
+    app.body_area = rows[0];
+    app.list_area = chunks[0];
+    app.preview_area = right[1];
+    draw_list(frame, chunks[0], app);
+    draw_info(frame, right[0], app);
+    draw_preview(frame, right[1], app);
+    draw_status(frame, rows[1], app);
+
+    if app.help.is_some() {
+        draw_help(frame, app);
+    }
+    if app.confirm.is_some() {
+        draw_confirm(frame, app);
+    }
+}
+
+// Every binding of the current view, generated from the keymap
+fn draw_help(frame: &mut Frame, app: &mut App) {
+    let area = centered(frame.size(), 60, 80);
+    let mode = app.mode();
+    let theme = &app.theme;
+    let bindings = app.keymap.help(mode);
+    let width = bindings
+        .iter()
+        .map(|(keys, _)| keys.chars().count())
+        .max()
+        .unwrap_or(0);
+    let description_width = bindings
+        .iter()
+        .map(|(_, action)| action.description().chars().count())
+        .max()
+        .unwrap_or(0);
+
+    // Two rows for the borders
+    let height = area.height.saturating_sub(2) as usize;
+    let max_scroll = bindings.len().saturating_sub(height);
+    let Some(scroll) = &mut app.help else {
+        return;
+    };
+    *scroll = (*scroll).min(max_scroll);
+    let lines: Vec<Line> = bindings
+        .iter()
+        .skip(*scroll)
+        .map(|(keys, action)| {
+            Line::from(vec![
+                Span::styled(format!("{:<width$}  ", keys), theme.accent),
+                Span::raw(format!("{:<description_width$}  ", action.description())),
+                Span::styled(action.name(), theme.muted),
+            ])
+        })
+        .collect();
+
+    let block = Block::default()
+        .title(format!("Keys ({})", mode.name()))
+        .title_style(theme.title)
+        .borders(Borders::ALL)
+        .border_style(theme.border_focused)
+        .padding(Padding::horizontal(1));
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).style(theme.text).block(block), area);
+}
+
+// Dry-run summary in a popup over the middle of the screen
+fn draw_confirm(frame: &mut Frame, app: &App) {
+    let Some(confirm) = &app.confirm else {
+        return;
+    };
+    let theme = &app.theme;
+    let area = centered(frame.size(), 70, 60);
+
+    let mut lines: Vec<Line> = confirm
+        .lines
+        .iter()
+        .map(|line| Line::from(line.as_str()))
+        .collect();
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "y apply · n cancel",
+        theme.warning,
+    )));
+
+    let block = Block::default()
+        .title(confirm.title.as_str())
+        .title_style(theme.title)
+        .borders(Borders::ALL)
+        .border_style(theme.warning)
+        .padding(Padding::horizontal(1));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).style(theme.text).block(block), area);
+}
+
+// A rectangle of the given percentage size in the middle of `area`
+fn centered(area: Rect, width_percent: u16, height_percent: u16) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - height_percent) / 2),
+            Constraint::Percentage(height_percent),
+            Constraint::Percentage((100 - height_percent) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - width_percent) / 2),
+            Constraint::Percentage(width_percent),
+            Constraint::Percentage((100 - width_percent) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+// Last message, or a reminder of the main keys
+fn draw_status(frame: &mut Frame, area: Rect, app: &App) {
+    let theme = &app.theme;
+    if let Some(grep) = app.grep.as_ref().filter(|grep| grep.editing) {
+        let line = Line::from(vec![
+            Span::styled("search contents: ", theme.accent),
+            Span::raw(grep.query.as_str()),
+            Span::styled("█", theme.muted),
+        ]);
+        frame.render_widget(Paragraph::new(line), area);
+        return;
+    }
+    if let Some(prompt) = &app.commit {
+        let line = Line::from(vec![
+            Span::styled(
+                format!("commit to {}: ", scan::display_path(&prompt.repo.work_tree)),
+                theme.accent,
+            ),
+            Span::raw(prompt.message.as_str()),
+            Span::styled("█", theme.muted),
+        ]);
+        frame.render_widget(Paragraph::new(line), area);
+        return;
+    }
+    if let Some(prompt) = &app.name_prompt {
+        let dir = scan::display_path(&prompt.dir);
+        let label = match prompt.kind {
+            NameKind::NewFile => format!("new file in {}/: ", dir.trim_end_matches('/')),
+            NameKind::NewDir => format!("new directory in {}/: ", dir.trim_end_matches('/')),
+            NameKind::Rename => format!("rename {} to: ", scan::display_path(&prompt.source)),
+            NameKind::Duplicate => format!("copy {} to: ", scan::display_path(&prompt.source)),
+        };
+        let line = Line::from(vec![
+            Span::styled(label, theme.accent),
+            Span::raw(prompt.input.as_str()),
+            Span::styled("█", theme.muted),
+        ]);
+        frame.render_widget(Paragraph::new(line), area);
+        return;
+    }
+    if let Some(filter) = &app.filter {
+        let line = Line::from(vec![
+            Span::styled("/", theme.accent),
+            Span::raw(filter.query.as_str()),
+            Span::styled("█", theme.muted),
+        ]);
+        frame.render_widget(Paragraph::new(line), area);
+        return;
+    }
+
+    let pending = app.keymap.pending();
+    let line = match (&app.status, &app.scan) {
+        _ if !pending.is_empty() => Line::from(Span::styled(pending, theme.accent)),
+        (Some(status), _) => Line::from(Span::styled(status.as_str(), theme.message)),
+        (None, Some(scan)) => {
+            scan_progress(scan, app.index.len(), &hint_key(app, Action::Back), theme)
+        }
+        (None, None) => Line::from(Span::styled(hints(app), theme.status)),
+    };
+    frame.render_widget(Paragraph::new(line), area);
+}
+
+// Spinner, entries found so far and where the scan is
+fn scan_progress(scan: &Scanner, found: usize, cancel_key: &str, theme: &Theme) -> Line<'static> {
+    const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+    let frame = scan.started.elapsed().as_millis() / 100 % SPINNER.len() as u128;
+    let dir = scan
+        .current_dir
+        .as_deref()
+        .map(scan::display_path)
+        .unwrap_or_default();
+    Line::from(vec![
+        Span::styled(
+            format!("{} Scanning: {} entries ", SPINNER[frame as usize], found),
+            theme.accent,
+        ),
+        Span::raw(dir),
+        Span::styled(format!("  {} cancel", cancel_key), theme.status),
+    ])
+}
+
+// Reminder of the keys available in the current view
+fn hints(app: &App) -> String {
+    use Action::*;
+    let snapshot_open = app.backups.as_ref().is_some_and(|view| view.open.is_some());
+    let hints: &[(&[Action], &str)] = if snapshot_open {
+        &[
+            (&[Restore], "restore file"),
+            (&[NextHunk, PreviousHunk], "next/previous hunk"),
+            (&[SwitchPane], "scroll preview"),
+            (&[Back], "back"),
+        ]
+    } else if app.backups.is_some() {
+        &[
+            (&[Open], "show files"),
+            (&[Restore], "restore snapshot"),
+            (&[Back], "back"),
+        ]
+    } else if app.log.is_some() {
+        &[
+            (&[Up, Down], "select commit"),
+            (&[SwitchPane], "scroll preview"),
+            (&[Back], "back"),
+        ]
+    } else if app.journal.is_some() {
+        &[
+            (&[Undo], "undo"),
+            (&[Redo], "redo"),
+            (&[SwitchPane], "scroll preview"),
+            (&[Back], "back"),
+        ]
+    } else if app.links.is_some() {
+        &[
+            (&[Link], "link"),
+            (&[Unlink], "unlink"),
+            (&[Adopt], "adopt"),
+            (&[LinkAll, UnlinkAll], "link/unlink all"),
+            (&[Refresh], "refresh"),
+            (&[Edit], "edit"),
+            (&[Back], "back"),
+        ]
+    } else if app.grep.is_some() {
+        &[
+            (&[Open], "go to match"),
+            (&[SearchContents], "new search"),
+            (&[Edit], "edit"),
+            (&[Back], "back"),
+        ]
+    } else {
+        &[
+            (&[Quit], "quit"),
+            (&[SwitchPane], "pane"),
+            (&[Edit], "edit"),
+            (&[NewFile, NewDir], "new"),
+            (&[Rename], "rename"),
+            (&[Duplicate], "copy"),
+            (&[Delete], "delete"),
+            (&[Undo, Redo], "undo/redo"),
+            (&[Journal], "journal"),
+            (&[Search], "search"),
+            (&[SearchContents], "contents"),
+            (&[Links], "links"),
+            (&[Diff], "diff"),
+            (&[Stage, Unstage], "stage/unstage"),
+            (&[Commit], "commit"),
+            (&[Log], "log"),
+            (&[Backup, BackupAll, Backups], "backup"),
+        ]
+    };
+
+    let mut parts: Vec<String> = hints
+        .iter()
+        .filter(|(actions, _)| {
+            actions
+                .iter()
+                .all(|action| !hint_key(app, *action).is_empty())
+        })
+        .map(|(actions, label)| {
+            let keys: Vec<String> = actions
+                .iter()
+                .map(|action| hint_key(app, *action))
+                .collect();
+            format!("{} {}", keys.join("/"), label)
+        })
+        .collect();
+    // First, as the line is cut off on narrow terminals
+    let help = hint_key(app, Help);
+    if !help.is_empty() {
+        parts.insert(0, format!("{} keys", help));
+    }
+    parts.join(" · ")
+}
+
+// The first key bound to `action` in the current view, written short: "Esc", "↑", "C-n"
+fn hint_key(app: &App, action: Action) -> String {
+    let Some(keys) = app.keymap.keys_for(app.mode(), action).into_iter().next() else {
+        return String::new();
+    };
+    match keys.as_str() {
+        "<Up>" => "↑".to_string(),
+        "<Down>" => "↓".to_string(),
+        _ if keys.starts_with('<') && keys.ends_with('>') && keys.matches('<').count() == 1 => {
+            keys[1..keys.len() - 1].to_string()
+        }
+        _ => keys,
+    }
+}
+
+fn draw_list(frame: &mut Frame, area: Rect, app: &mut App) {
+    if app.log.is_some() {
+        draw_log(frame, area, app);
+        return;
+    }
+    if app.backups.is_some() {
+        draw_backups(frame, area, app);
+        return;
+    }
+    if app.journal.is_some() {
+        draw_journal(frame, area, app);
+        return;
+    }
+    if app.links.is_some() {
+        draw_links(frame, area, app);
+        return;
+    }
+    if app.grep.is_some() {
+        draw_grep(frame, area, app);
+        return;
+    }
+    if app.filter.is_some() {
+        draw_filter(frame, area, app);
+        return;
+    }
+
+    let list_items: Vec<ListItem> = app
+        .dotfiles
+        .iter()
+        .map(|(row, node)| {
+            let git = app.git.status(&node.path);
+            let changed = app.changed.get(&node.path).copied();
+            ListItem::new(tree_line(&row.guide, node, git, changed, &app.theme))
+        })
+        .collect();
+
+    let block = pane_block("Dotfiles".to_string(), app.focus == Focus::List, &app.theme);
+    app.list_height = block.inner(area).height as usize;
+
+    let list = List::new(list_items)
+        .style(app.theme.text)
+        .highlight_style(app.theme.highlight)
+        .highlight_symbol(">> ")
+        .block(block);
+
+    frame.render_stateful_widget(list, area, &mut app.list_state);
+}
+
+// Search results ranked by score, with the matched characters highlighted
+fn draw_filter(frame: &mut Frame, area: Rect, app: &mut App) {
+    let Some(filter) = &mut app.filter else {
+        return;
+    };
+    let match_style = app.theme.matched;
+
+    let title = format!(
+        "Filter ({}/{})",
+        filter.results.len(),
+        filter.candidate_count()
+    );
+    let block = pane_block(title, true, &app.theme);
+    let height = block.inner(area).height as usize;
+    app.list_height = height;
+
+    // Only the visible rows are built: there can be hundreds of thousands of results
+    let selected = filter.list_state.selected().unwrap_or(0);
+    let offset = filter
+        .list_state
+        .offset()
+        .min(selected)
+        .max((selected + 1).saturating_sub(height));
+    let list_items: Vec<ListItem> = filter
+        .results
+        .iter()
+        .skip(offset)
+        .take(height)
+        .map(|result| {
+            let spans: Vec<Span> = result
+                .display
+                .chars()
+                .enumerate()
+                .map(|(i, c)| {
+                    if result.positions.contains(&i) {
+                        Span::styled(c.to_string(), match_style)
+                    } else {
+                        Span::raw(c.to_string())
+                    }
+                })
+                .collect();
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let list = List::new(list_items)
+        .style(app.theme.text)
+        .highlight_style(app.theme.highlight)
+        .highlight_symbol(">> ")
+        .block(block);
+
+    *filter.list_state.offset_mut() = offset;
+    let mut window =
+        ListState::default().with_selected(filter.list_state.selected().map(|_| selected - offset));
+    frame.render_stateful_widget(list, area, &mut window);
+}
+
+// Files in the managed repo with the state of their HOME path
+fn draw_links(frame: &mut Frame, area: Rect, app: &mut App) {
+    let Some(view) = &mut app.links else {
+        return;
+    };
+
+    let list_items: Vec<ListItem> = view
+        .entries
+        .iter()
+        .map(|entry| {
+            let style = match entry.status {
+                LinkStatus::Linked => app.theme.ok,
+                LinkStatus::Missing => app.theme.warning,
+                LinkStatus::Conflict => app.theme.error,
+                LinkStatus::Broken => app.theme.special,
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<9}", entry.status.label()), style),
+                Span::raw(scan::display_path(&entry.target)),
+            ]))
+        })
+        .collect();
+
+    let counts: Vec<String> = [
+        LinkStatus::Linked,
+        LinkStatus::Missing,
+        LinkStatus::Conflict,
+        LinkStatus::Broken,
+    ]
+    .into_iter()
+    .filter(|&status| view.count(status) > 0)
+    .map(|status| format!("{} {}", view.count(status), status.label()))
+    .collect();
+    let title = format!("{}: {}", scan::display_path(&view.repo), counts.join(", "));
+    let block = pane_block(title, true, &app.theme);
+    app.list_height = block.inner(area).height as usize;
+
+    let list = List::new(list_items)
+        .style(app.theme.text)
+        .highlight_style(app.theme.highlight)
+        .highlight_symbol(">> ")
+        .block(block);
+
+    frame.render_stateful_widget(list, area, &mut view.list_state);
+}
+
+// Commits touching one path, newest first
+fn draw_log(frame: &mut Frame, area: Rect, app: &mut App) {
+    let Some(view) = &mut app.log else {
+        return;
+    };
+
+    let list_items: Vec<ListItem> = view
+        .commits
+        .iter()
+        .map(|commit| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{} ", commit.hash), app.theme.warning),
+                Span::styled(format!("{} ", commit.date), app.theme.muted),
+                Span::raw(commit.subject.as_str()),
+            ]))
+        })
+        .collect();
+
+    let title = format!(
+        "Log {} ({} {})",
+        scan::display_path(&view.path),
+        view.commits.len(),
+        if view.commits.len() == 1 {
+            "commit"
+        } else {
+            "commits"
+        }
+    );
+    let block = pane_block(title, app.focus == Focus::List, &app.theme);
+    app.list_height = block.inner(area).height as usize;
+
+    let list = List::new(list_items)
+        .style(app.theme.text)
+        .highlight_style(app.theme.highlight)
+        .highlight_symbol(">> ")
+        .block(block);
+
+    frame.render_stateful_widget(list, area, &mut view.list_state);
+}
+
+// Snapshots newest first, or the files of one snapshot and how they compare
+// with the live ones
+fn draw_backups(frame: &mut Frame, area: Rect, app: &mut App) {
+    let Some(view) = &mut app.backups else {
+        return;
+    };
+
+    let (title, list_items): (String, Vec<ListItem>) = match &view.open {
+        Some((snapshot, states)) => {
+            let snapshot = &view.snapshots[*snapshot];
+            let items = snapshot
+                .entries
+                .iter()
+                .zip(states)
+                .map(|(entry, state)| {
+                    let style = match state {
+                        LiveState::Unchanged => app.theme.ok,
+                        LiveState::Changed => app.theme.warning,
+                        LiveState::Missing => app.theme.error,
+                    };
+                    ListItem::new(Line::from(vec![
+                        Span::styled(format!("{:<8}", state.label()), style),
+                        Span::raw(scan::display_path(&entry.path)),
+                    ]))
+                })
+                .collect();
+            (format!("Snapshot {}", snapshot.id), items)
+        }
+        None => {
+            let items = view
+                .snapshots
+                .iter()
+                .map(|snapshot| {
+                    ListItem::new(Line::from(vec![
+                        Span::styled(format!("{} ", snapshot.id), app.theme.warning),
+                        Span::raw(format!(
+                            "{} {}",
+                            snapshot.entries.len(),
+                            if snapshot.entries.len() == 1 {
+                                "file"
+                            } else {
+                                "files"
+                            }
+                        )),
+                        Span::styled(format!(" {}", snapshot.label), app.theme.muted),
+                    ]))
+                })
+                .collect();
+            (format!("Snapshots ({})", view.snapshots.len()), items)
+        }
+    };
+
+    let block = pane_block(title, app.focus == Focus::List, &app.theme);
+    app.list_height = block.inner(area).height as usize;
+
+    let list = List::new(list_items)
+        .style(app.theme.text)
+        .highlight_style(app.theme.highlight)
+        .highlight_symbol(">> ")
+        .block(block);
+
+    frame.render_stateful_widget(list, area, &mut view.list_state);
+}
+
+// Journal records newest first; undone ones can be redone
+fn draw_journal(frame: &mut Frame, area: Rect, app: &mut App) {
+    let Some(view) = &mut app.journal else {
+        return;
+    };
+
+    let list_items: Vec<ListItem> = view
+        .rows()
+        .map(|(record, undone)| {
+            let path = record
+                .steps
+                .first()
+                .map(|step| scan::display_path(step.path()))
+                .unwrap_or_default();
+            let mut spans = vec![
+                Span::styled(format!("{:<8}", record.action), app.theme.accent),
+                Span::raw(path),
+            ];
+            if record.steps.len() > 1 {
+                spans.push(Span::raw(format!(" +{}", record.steps.len() - 1)));
+            }
+            if undone {
+                spans.push(Span::raw(" (undone)"));
+            }
+            spans.push(Span::styled(format!(" {}", record.time), app.theme.muted));
+            let item = ListItem::new(Line::from(spans));
+            if undone {
+                item.style(app.theme.muted)
+            } else {
+                item
+            }
+        })
+        .collect();
+
+    let title = format!(
+        "Journal ({}, {} undone)",
+        view.journal.records.len(),
+        view.journal.undone
+    );
+    let block = pane_block(title, app.focus == Focus::List, &app.theme);
+    app.list_height = block.inner(area).height as usize;
+
+    let list = List::new(list_items)
+        .style(app.theme.text)
+        .highlight_style(app.theme.highlight)
+        .highlight_symbol(">> ")
+        .block(block);
+
+    frame.render_stateful_widget(list, area, &mut view.list_state);
+}
+
+// Content search results as path:line: text, occurrences highlighted
+fn draw_grep(frame: &mut Frame, area: Rect, app: &mut App) {
+    let Some(grep) = &mut app.grep else {
+        return;
+    };
+    let match_style = app.theme.matched;
+
+    let list_items: Vec<ListItem> = grep
+        .results
+        .iter()
+        .map(|found| {
+            let mut spans = vec![
+                Span::styled(scan::display_path(&found.path), app.theme.accent),
+                Span::styled(format!(":{}: ", found.line), app.theme.muted),
+            ];
+            let mut end = 0;
+            for &(start, stop) in &found.ranges {
+                spans.push(Span::raw(found.text[end..start].to_string()));
+                spans.push(Span::styled(
+                    found.text[start..stop].to_string(),
+                    match_style,
+                ));
+                end = stop;
+            }
+            spans.push(Span::raw(found.text[end..].to_string()));
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let mut title = if grep.query.is_empty() || (grep.editing && grep.results.is_empty()) {
+        "Search contents".to_string()
+    } else {
+        format!(
+            "\"{}\": {}{} {} in {} files",
+            grep.query,
+            grep.results.len(),
+            if grep.truncated { "+" } else { "" },
+            if grep.results.len() == 1 {
+                "match"
+            } else {
+                "matches"
+            },
+            grep.files_searched
+        )
+    };
+    // Files the scan had not reached yet were not searched
+    if grep.incomplete {
+        title.push_str(" (index incomplete)");
+    }
+    let block = pane_block(title, true, &app.theme);
+    app.list_height = block.inner(area).height as usize;
+
+    let list = List::new(list_items)
+        .style(app.theme.text)
+        .highlight_style(app.theme.highlight)
+        .highlight_symbol(">> ")
+        .block(block);
+
+    frame.render_stateful_widget(list, area, &mut grep.list_state);
+}
+
+// Metadata of the selected entry
+fn draw_info(frame: &mut Frame, area: Rect, app: &mut App) {
+    let theme = &app.theme;
+    let block = Block::default()
+        .title("Info")
+        .title_style(theme.title)
+        .borders(Borders::ALL)
+        .border_style(theme.border)
+        .padding(Padding::horizontal(1));
+
+    let Some(path) = app.selected_path().map(|path| path.to_path_buf()) else {
+        frame.render_widget(Paragraph::new("").block(block), area);
+        return;
+    };
+    let info = match app.preview.info(&path) {
+        Ok(info) => info,
+        Err(e) => {
+            frame.render_widget(Paragraph::new(e.to_string()).block(block), area);
+            return;
+        }
+    };
+
+    let label = |text: &'static str| Span::styled(text, theme.muted);
+    let modified = info.modified.map(metadata::format_time).unwrap_or_default();
+    let mut lines = vec![
+        Line::from(vec![
+            label("Size "),
+            Span::raw(format!("{:<24}", metadata::format_size(info.size))),
+            label("Mode "),
+            Span::raw(format!("{} ({:04o})", info.permissions(), info.mode)),
+        ]),
+        Line::from(vec![
+            label("Owner "),
+            Span::raw(format!(
+                "{:<23}",
+                format!("{}:{}", app.names.user(info.uid), app.names.group(info.gid))
+            )),
+            label("Modified "),
+            Span::raw(modified),
+        ]),
+        Line::from(vec![
+            label("Inode "),
+            Span::raw(format!(
+                "{:<23}",
+                format!(
+                    "{} · {} {}",
+                    info.inode,
+                    info.links,
+                    if info.links == 1 { "link" } else { "links" }
+                )
+            )),
+            label("Git "),
+            Span::raw(match app.git.status(&path) {
+                Some(status) => status.describe(),
+                None => "not tracked".to_string(),
+            }),
+        ]),
+    ];
+
+    if let Some(target) = &info.link_target {
+        let (state, style) = if info.link_resolves {
+            ("ok", theme.ok)
+        } else {
+            ("broken", theme.error)
+        };
+        lines.push(Line::from(vec![
+            label("Target "),
+            Span::raw(format!("{} ", scan::display_path(target))),
+            Span::styled(format!("({})", state), style),
+        ]));
+    } else if info.kind == Kind::Dir {
+        let contents = match app.dir_sizes.get(&path) {
+            DirSize::Pending => "computing…".to_string(),
+            DirSize::Done(summary) => format!(
+                "{} entries, {} total",
+                summary.children,
+                metadata::format_size(summary.total_size)
+            ),
+        };
+        lines.push(Line::from(vec![label("Contents "), Span::raw(contents)]));
+    }
+
+    frame.render_widget(Paragraph::new(lines).style(theme.text).block(block), area);
+}
+
+fn draw_preview(frame: &mut Frame, area: Rect, app: &mut App) {
+    let base = app.diff_base;
+    let selected = app.selected_path().map(Path::to_path_buf);
+    // current_diff() borrows all of `app`
+    let theme = app.theme.clone();
+    // File contents get a line number gutter, messages and diffs do not
+    let (title, lines, numbered) = if let Some(view) = &mut app.backups {
+        let (title, lines, numbered) = backup_preview(view, &mut app.preview, &theme);
+        (title, Cow::Owned(lines), numbered)
+    } else if let Some(view) = &app.journal {
+        (
+            journal_title(view),
+            journal_preview(view, &theme).into(),
+            false,
+        )
+    } else if let Some(log) = &mut app.log {
+        let commit = log
+            .selected()
+            .map(|commit| format!("{} by {}", commit.hash, commit.author))
+            .unwrap_or_default();
+        let path = log.path.clone();
+        match log.revision() {
+            Some(Ok(content)) => {
+                let language = Language::detect(&path, content);
+                let lines = highlight::highlight(content, language, &theme.syntax);
+                let title = format!("Preview @ {} ({})", commit, language.name());
+                (title, lines.into(), true)
+            }
+            Some(Err(e)) => (
+                format!("Preview @ {}", commit),
+                vec![Line::from(e.to_string())].into(),
+                false,
+            ),
+            None => (
+                "Preview".to_string(),
+                vec![Line::from("No commit selected.")].into(),
+                false,
+            ),
+        }
+    } else if let (Some(base), Some(diff)) = (base, app.current_diff()) {
+        let title = format!("Diff against {}", base.name());
+        match diff {
+            Ok(diff) if diff.lines.is_empty() => (
+                title,
+                vec![Line::from(format!("No changes against {}.", base.name()))].into(),
+                false,
+            ),
+            Ok(diff) => (
+                format!(
+                    "{} ({} {})",
+                    title,
+                    diff.hunks.len(),
+                    if diff.hunks.len() == 1 {
+                        "hunk"
+                    } else {
+                        "hunks"
+                    }
+                ),
+                diff.lines
+                    .iter()
+                    .map(|line| diff_line(line, &theme))
+                    .collect(),
+                false,
+            ),
+            Err(e) => (title, vec![Line::from(e.to_string())].into(), false),
+        }
+    } else if let Some(path) = &selected {
+        let preview = app.preview.get(path, &theme);
+        (
+            preview.title.clone(),
+            Cow::Borrowed(&preview.lines[..]),
+            preview.numbered,
+        )
+    } else {
+        (
+            "Preview".to_string(),
+            vec![Line::from("No file selected.")].into(),
+            false,
+        )
+    };
+
+    let block =
+        pane_block(title, app.focus == Focus::Preview, &theme).padding(Padding::horizontal(1));
+    let inner = block.inner(area);
+
+    app.preview_lines = lines.len();
+    app.preview_height = inner.height as usize;
+    // Same as scroll_preview(0), which would borrow all of `app`
+    app.preview_scroll = app
+        .preview_scroll
+        .min(app.preview_lines.saturating_sub(app.preview_height));
+
+    let gutter_width = lines.len().to_string().len();
+    let visible: Vec<Line> = lines
+        .iter()
+        .cloned()
+        .enumerate()
+        .skip(app.preview_scroll)
+        .take(app.preview_height)
+        .map(|(i, line)| {
+            let line = if app.preview_highlight == Some(i + 1) {
+                line.patch_style(theme.current_line)
+            } else {
+                line
+            };
+            if numbered {
+                with_gutter(i + 1, gutter_width, line, theme.muted)
+            } else {
+                line
+            }
+        })
+        .collect();
+
+    frame.render_widget(Paragraph::new(visible).style(theme.text).block(block), area);
+
+    if app.preview_lines > app.preview_height {
+        let max_scroll = app.preview_lines - app.preview_height;
+        let mut state = ScrollbarState::new(max_scroll + 1)
+            .position(app.preview_scroll)
+            .viewport_content_length(app.preview_height);
+        frame.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight),
+            area.inner(Margin {
+                vertical: 1,
+                horizontal: 0,
+            }),
+            &mut state,
+        );
+    }
+}
+
+// Bordered pane whose border is highlighted while it has focus
+fn pane_block<'a>(title: String, focused: bool, theme: &Theme) -> Block<'a> {
+    let border_style = if focused {
+        theme.border_focused
+    } else {
+        theme.border
+    };
+    Block::default()
+        .title(title)
+        .title_style(theme.title)
+        .borders(Borders::ALL)
+        .border_style(border_style)
+}
+
+// The selected snapshot file against the live one, or what a snapshot holds
+fn backup_preview(
+    view: &mut BackupView,
+    cache: &mut PreviewCache,
+    theme: &Theme,
+) -> (String, Vec<Line<'static>>, bool) {
+    if let Some(entry) = view.selected_entry() {
+        if let Some(link) = &entry.link {
+            let live = match cache.info(&entry.path) {
+                Ok(FileInfo {
+                    link_target: Some(target),
+                    ..
+                }) => scan::display_path(target),
+                _ => "not a symlink".to_string(),
+            };
+            let lines = vec![
+                Line::from(format!("snapshot: symlink to {}", scan::display_path(link))),
+                Line::from(format!("live:     {}", live)),
+            ];
+            return ("Symlink".to_string(), lines, false);
+        }
+        return match view.selected_diff() {
+            Some(Ok(diff)) if diff.lines.is_empty() => (
+                "Diff against live file".to_string(),
+                vec![Line::from("The live file is the same as the snapshot.")],
+                false,
+            ),
+            Some(Ok(diff)) => (
+                "Diff against live file".to_string(),
+                diff.lines
+                    .iter()
+                    .map(|line| diff_line(line, theme))
+                    .collect(),
+                false,
+            ),
+            Some(Err(e)) => ("Diff".to_string(), vec![Line::from(e.to_string())], false),
+            None => ("Preview".to_string(), Vec::new(), false),
+        };
+    }
+
+    let Some(snapshot) = view.selected_snapshot() else {
+        return (
+            "Preview".to_string(),
+            vec![Line::from("No snapshot selected.")],
+            false,
+        );
+    };
+    let mut lines = vec![
+        Line::from(format!("Created {} ({})", snapshot.created, snapshot.label)),
+        Line::from(""),
+    ];
+    lines.extend(snapshot.entries.iter().map(|entry| {
+        let size = match &entry.link {
+            Some(link) => format!("-> {}", scan::display_path(link)),
+            None => metadata::format_size(entry.size),
+        };
+        Line::from(format!("{:>10}  {}", size, scan::display_path(&entry.path)))
+    }));
+    (format!("Snapshot {}", snapshot.id), lines, false)
+}
+
+fn journal_title(view: &JournalView) -> String {
+    match view.selected() {
+        Some((record, _)) => format!("{} ({})", record.action, record.time),
+        None => "Preview".to_string(),
+    }
+}
+
+// What the selected record did, and what undoing or redoing it would do
+fn journal_preview(view: &JournalView, theme: &Theme) -> Vec<Line<'static>> {
+    let Some((record, undone)) = view.selected() else {
+        return vec![Line::from("No change selected.")];
+    };
+    let mut lines = vec![Line::styled("Done", theme.title)];
+    lines.extend(record.steps.iter().map(|step| Line::from(step.to_string())));
+    lines.push(Line::default());
+    if undone {
+        lines.push(Line::styled("Undone", theme.title));
+    } else {
+        lines.push(Line::styled("Undoing it", theme.title));
+    }
+    lines.extend(
+        record
+            .steps
+            .iter()
+            .rev()
+            .map(|step| Line::from(step.undo_line())),
+    );
+    lines
+}
+
+// One line of `git diff` output, colored like `git diff --color`
+fn diff_line(line: &str, theme: &Theme) -> Line<'static> {
+    const HEADERS: [&str; 4] = ["diff ", "index ", "--- ", "+++ "];
+    let style = if line.starts_with("@@") {
+        theme.diff_hunk
+    } else if HEADERS.iter().any(|header| line.starts_with(header)) {
+        theme.diff_header
+    } else if line.starts_with('+') {
+        theme.diff_added
+    } else if line.starts_with('-') {
+        theme.diff_removed
+    } else {
+        Style::default()
+    };
+    Line::from(Span::styled(line.to_string(), style))
+}
+
+fn with_gutter(number: usize, width: usize, line: Line<'static>, style: Style) -> Line<'static> {
+    let mut spans = vec![Span::styled(format!("{:>width$} │ ", number), style)];
+    spans.extend(line.spans);
+    Line::from(spans)
+}
+
+// Indentation guides, an expand marker for directories, the name, then a dot
+// if it changed on disk since startup and git badges
+fn tree_line<'a>(
+    guide: &'a str,
+    node: &'a Node,
+    git: Option<FileStatus>,
+    changed: Option<Instant>,
+    theme: &Theme,
+) -> Line<'a> {
+    let marker = match (node.is_expandable(), node.expanded) {
+        (true, true) => "▾ ",
+        (true, false) => "▸ ",
+        (false, _) => "",
+    };
+    let mut name_style = if node.is_dir {
+        theme.directory
+    } else {
+        Style::default()
+    };
+    if git.is_some_and(|status| status.ignored) {
+        name_style = name_style.patch(theme.muted);
+    }
+    // Flash the name right after a change
+    if changed.is_some_and(|time| time.elapsed() < CHANGE_FLASH) {
+        name_style = name_style.patch(theme.changed);
+    }
+
+    let mut spans = vec![
+        Span::styled(guide, theme.muted),
+        Span::raw(marker),
+        Span::styled(node.name.as_str(), name_style),
+    ];
+    if changed.is_some() {
+        spans.push(Span::styled(" ●", theme.warning));
+    }
+    if let Some(status) = git {
+        spans.extend(git_badges(status, theme));
+    }
+    Line::from(spans)
+}
+
+// U conflicted, + staged, M modified, ? untracked, ! ignored
+fn git_badges(status: FileStatus, theme: &Theme) -> Vec<Span<'static>> {
+    let badges = [
+        (status.conflicted, "U", theme.error),
+        (status.staged, "+", theme.ok),
+        (status.modified, "M", theme.warning),
+        (status.untracked, "?", theme.special),
+        (status.ignored, "!", theme.muted),
+    ];
+    let mut spans = Vec::new();
+    for (set, badge, style) in badges {
+        if !set {
+            continue;
+        }
+        if spans.is_empty() {
+            spans.push(Span::raw(" "));
+        }
+        spans.push(Span::styled(badge, style.add_modifier(Modifier::BOLD)));
+    }
+    spans
+}
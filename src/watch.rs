@@ -0,0 +1,142 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+use crate::scan::{self, ScanRoot};
+
+// Changes are handed over once no event came for this long, so a burst such
+// as a `git checkout` is handled once...
+const DEBOUNCE: Duration = Duration::from_millis(200);
+// ...but at least this often while events keep coming
+const MAX_DELAY: Duration = Duration::from_secs(1);
+
+// Change notifications (inotify on Linux) for the directories under the scan
+// roots whose entries are listed. Each directory is watched on its own, so
+// excluded caches and anything below max_depth never wake the app.
+#[derive(Debug)]
+pub struct Watcher {
+    inner: Arc<Mutex<RecommendedWatcher>>,
+    receiver: Receiver<notify::Result<notify::Event>>,
+    roots: Vec<ScanRoot>,
+    // Changes not handed over yet, and when the first and the latest came
+    pending: HashSet<PathBuf>,
+    first: Option<Instant>,
+    latest: Option<Instant>,
+    // Last failure reported by the watcher, until the app shows it
+    pub error: Option<String>,
+}
+
+impl Watcher {
+    pub fn start(roots: Vec<ScanRoot>) -> io::Result<Self> {
+        let (sender, receiver) = mpsc::channel();
+        let inner = notify::recommended_watcher(sender).map_err(io::Error::other)?;
+        let inner = Arc::new(Mutex::new(inner));
+
+        // Adding a watch per directory takes as long as a scan, so it runs on the side too
+        let adding = inner.clone();
+        let walk_roots = roots.clone();
+        thread::spawn(move || {
+            for root in &walk_roots {
+                watch(&adding, &root.path);
+            }
+            scan::walk(&walk_roots, |root, entry| {
+                if entry.file_type().is_dir() && root.can_descend(entry.depth()) {
+                    watch(&adding, entry.path());
+                }
+                true
+            });
+        });
+
+        Ok(Self {
+            inner,
+            receiver,
+            roots,
+            pending: HashSet::new(),
+            first: None,
+            latest: None,
+            error: None,
+        })
+    }
+
+    pub fn roots(&self) -> &[ScanRoot] {
+        &self.roots
+    }
+
+    // Listed paths created, changed or removed since the last call, once
+    // things settled down. New directories are watched from now on.
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        let now = Instant::now();
+        while let Ok(event) = self.receiver.try_recv() {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    self.error = Some(e.to_string());
+                    continue;
+                }
+            };
+            if matches!(event.kind, EventKind::Access(_)) {
+                continue;
+            }
+            for path in event.paths {
+                if !self.pending.contains(&path) && self.accepts(&path) {
+                    self.pending.insert(path);
+                    self.first.get_or_insert(now);
+                    self.latest = Some(now);
+                }
+            }
+        }
+
+        let settled = self
+            .latest
+            .is_some_and(|latest| now.duration_since(latest) >= DEBOUNCE);
+        let overdue = self
+            .first
+            .is_some_and(|first| now.duration_since(first) >= MAX_DELAY);
+        if !settled && !overdue {
+            return Vec::new();
+        }
+        self.first = None;
+        self.latest = None;
+        self.pending.drain().collect()
+    }
+
+    fn accepts(&self, path: &Path) -> bool {
+        // A removed entry was listed if its directory is watched
+        let Ok(metadata) = fs::symlink_metadata(path) else {
+            return true;
+        };
+        let file_type = metadata.file_type();
+        let mut accepted = false;
+        for root in &self.roots {
+            let Ok(relative) = path.strip_prefix(&root.path) else {
+                continue;
+            };
+            let depth = relative.components().count();
+            if depth == 0 || !root.can_descend(depth - 1) || !root.accepts(path, file_type, depth) {
+                continue;
+            }
+            accepted = true;
+            if root.is_dir(path, file_type) && root.can_descend(depth) {
+                watch(&self.inner, path);
+            }
+        }
+        accepted
+    }
+}
+
+fn watch(watcher: &Mutex<RecommendedWatcher>, dir: &Path) {
+    // Past the inotify watch limit the remaining directories just go unwatched
+    if let Ok(mut watcher) = watcher.lock() {
+        let _ = watcher.watch(dir, RecursiveMode::NonRecursive);
+    }
+}